@@ -0,0 +1,108 @@
+// RIFT compute-unit benchmarks
+//
+// Runs every instruction of the three programs in program-test's bank and
+// records the compute units each one consumed, so that growth shows up in
+// review instead of on mainnet:
+//
+//     anchor build
+//     cargo run -p rift-bench --release -- --baseline compute-units.json
+
+// The integration tests' instruction builders and fixtures, shared verbatim
+#[path = "../../integration-tests/tests/common/mod.rs"]
+mod common;
+mod report;
+mod runner;
+mod scenarios;
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use report::{regressions, Report};
+use runner::Programs;
+
+#[derive(Parser)]
+#[command(
+    name = "rift-bench",
+    about = "Measure compute units per RIFT instruction"
+)]
+struct Args {
+    /// Directory holding rift_mixer.so, rift_staking.so and rift_governance.so
+    #[arg(long, default_value = "target/deploy")]
+    sbf_dir: String,
+
+    /// Run the native builtins instead; checks the harness, not the costs
+    #[arg(long)]
+    native: bool,
+
+    /// Where to write the JSON report
+    #[arg(long, default_value = "compute-units.json")]
+    out: PathBuf,
+
+    /// Earlier report to compare against; regressions fail the run
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+
+    /// Allowed growth over the baseline, in percent
+    #[arg(long, default_value_t = 5.0)]
+    tolerance: f64,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+    let programs = if args.native {
+        Programs::Native
+    } else {
+        Programs::Sbf(args.sbf_dir.clone())
+    };
+
+    // Read before running: `--baseline` and `--out` may be the same file
+    let baseline: Option<Report> = args.baseline.as_ref().map(|path| {
+        let json = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e));
+        serde_json::from_str(&json)
+            .unwrap_or_else(|e| panic!("cannot parse {}: {}", path.display(), e))
+    });
+
+    let mut measurements = Vec::new();
+    scenarios::mixer(&programs, &mut measurements).await;
+    scenarios::staking(&programs, &mut measurements).await;
+    scenarios::governance(&programs, &mut measurements).await;
+
+    for m in &measurements {
+        let cu = m.compute_units.map_or("-".to_string(), |cu| cu.to_string());
+        let note = match (&m.error, m.within_default_limit) {
+            (Some(error), _) => format!("FAILED: {}", error),
+            (None, Some(false)) => "over the default 200k limit".to_string(),
+            _ => String::new(),
+        };
+        println!(
+            "{:<16} {:<26} {:<18} {:>9}  {}",
+            m.program, m.instruction, m.state, cu, note
+        );
+    }
+
+    let report = Report {
+        mode: programs.mode().to_string(),
+        measurements,
+    };
+    std::fs::write(
+        &args.out,
+        serde_json::to_string_pretty(&report).unwrap() + "\n",
+    )
+    .unwrap_or_else(|e| panic!("cannot write {}: {}", args.out.display(), e));
+    println!("Wrote {}", args.out.display());
+
+    if let Some(baseline) = baseline {
+        let regressions = regressions(&baseline, &report, args.tolerance);
+        if !regressions.is_empty() {
+            eprintln!("Regressions against the baseline:");
+            for regression in &regressions {
+                eprintln!("  {}", regression);
+            }
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
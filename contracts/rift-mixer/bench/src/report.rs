@@ -0,0 +1,69 @@
+// JSON report and baseline comparison
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Per-instruction limit when a transaction does not request one
+pub const DEFAULT_COMPUTE_UNIT_LIMIT: u64 = 200_000;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Measurement {
+    pub program: String,
+    pub instruction: String,
+    /// Account state the instruction ran against, e.g. "1000 leaves"
+    pub state: String,
+    /// `None` when the runtime did not report usage (native builtins)
+    pub compute_units: Option<u64>,
+    pub within_default_limit: Option<bool>,
+    /// Transaction error, if the instruction failed
+    pub error: Option<String>,
+}
+
+impl Measurement {
+    fn key(&self) -> (&str, &str, &str) {
+        (&self.program, &self.instruction, &self.state)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Report {
+    /// "sbf" for real program builds, "native" for the builtin smoke run
+    pub mode: String,
+    pub measurements: Vec<Measurement>,
+}
+
+/// Everything in `current` that got more expensive than `baseline` by more
+/// than `tolerance_pct`, or that used to succeed and now fails
+pub fn regressions(baseline: &Report, current: &Report, tolerance_pct: f64) -> Vec<String> {
+    let before: HashMap<_, _> = baseline.measurements.iter().map(|m| (m.key(), m)).collect();
+
+    let mut regressions = Vec::new();
+    for now in &current.measurements {
+        let Some(then) = before.get(&now.key()) else {
+            continue;
+        };
+        let (program, instruction, state) = now.key();
+        match (then.error.is_none(), now.error.as_deref()) {
+            (true, Some(error)) => {
+                regressions.push(format!(
+                    "{}::{} [{}] now fails: {}",
+                    program, instruction, state, error
+                ));
+                continue;
+            }
+            (_, Some(_)) => continue,
+            _ => {}
+        }
+        if let (Some(then_cu), Some(now_cu)) = (then.compute_units, now.compute_units) {
+            let limit = then_cu as f64 * (1.0 + tolerance_pct / 100.0);
+            if now_cu as f64 > limit {
+                regressions.push(format!(
+                    "{}::{} [{}] {} -> {} CU",
+                    program, instruction, state, then_cu, now_cu
+                ));
+            }
+        }
+    }
+    regressions
+}
@@ -0,0 +1,135 @@
+// Bank setup and per-instruction compute-unit measurement
+
+use std::path::Path;
+
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+
+use crate::common;
+use crate::report::{Measurement, DEFAULT_COMPUTE_UNIT_LIMIT};
+
+/// Every measured transaction asks for the maximum so that instructions over
+/// the default limit still report what they would need
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// How the programs are loaded into the bank
+#[derive(Clone)]
+pub enum Programs {
+    /// `anchor build` output; the only mode with meaningful compute units
+    Sbf(String),
+    /// Native builtins, for checking the harness itself
+    Native,
+}
+
+impl Programs {
+    pub fn program_test(&self) -> ProgramTest {
+        match self {
+            Programs::Native => common::program_test(),
+            Programs::Sbf(dir) => {
+                for name in ["rift_mixer", "rift_staking", "rift_governance"] {
+                    let so = Path::new(dir).join(format!("{}.so", name));
+                    assert!(
+                        so.is_file(),
+                        "{} not found; run `anchor build` first",
+                        so.display()
+                    );
+                }
+                // program-test looks up `<name>.so` in BPF_OUT_DIR
+                std::env::set_var("BPF_OUT_DIR", dir);
+                let mut program_test = ProgramTest::default();
+                program_test.prefer_bpf(true);
+                program_test.add_program("rift_mixer", rift_mixer::ID, None);
+                program_test.add_program("rift_staking", rift_staking::ID, None);
+                program_test.add_program("rift_governance", rift_governance::ID, None);
+                program_test
+            }
+        }
+    }
+
+    pub fn mode(&self) -> &'static str {
+        match self {
+            Programs::Sbf(_) => "sbf",
+            Programs::Native => "native",
+        }
+    }
+}
+
+pub struct Bench<'a> {
+    pub ctx: ProgramTestContext,
+    results: &'a mut Vec<Measurement>,
+    state: String,
+}
+
+impl<'a> Bench<'a> {
+    pub async fn start(
+        program_test: ProgramTest,
+        state: impl Into<String>,
+        results: &'a mut Vec<Measurement>,
+    ) -> Bench<'a> {
+        Bench {
+            ctx: program_test.start_with_context().await,
+            results,
+            state: state.into(),
+        }
+    }
+
+    /// Run `instruction` in its own transaction and record what it consumed
+    pub async fn measure(&mut self, name: &str, instruction: Instruction, signers: &[&Keypair]) {
+        let program_id = instruction.program_id;
+        let program = program_name(&program_id);
+
+        let blockhash = self.ctx.get_new_latest_blockhash().await.unwrap();
+        let mut all_signers: Vec<&Keypair> = vec![&self.ctx.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(MAX_COMPUTE_UNIT_LIMIT),
+                instruction,
+            ],
+            Some(&self.ctx.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        let outcome = self
+            .ctx
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .unwrap();
+
+        // The program's own "consumed" line, which includes any CPIs it made.
+        // The top-level invocation logs last.
+        let prefix = format!("Program {} consumed ", program_id);
+        let compute_units = outcome.metadata.as_ref().and_then(|metadata| {
+            metadata
+                .log_messages
+                .iter()
+                .rev()
+                .find_map(|line| line.strip_prefix(&prefix)?.split(' ').next()?.parse().ok())
+        });
+
+        self.results.push(Measurement {
+            program: program.to_string(),
+            instruction: name.to_string(),
+            state: self.state.clone(),
+            compute_units,
+            within_default_limit: compute_units.map(|cu| cu <= DEFAULT_COMPUTE_UNIT_LIMIT),
+            error: outcome.result.err().map(|error| error.to_string()),
+        });
+    }
+}
+
+fn program_name(program_id: &solana_sdk::pubkey::Pubkey) -> &'static str {
+    if *program_id == rift_mixer::ID {
+        "rift_mixer"
+    } else if *program_id == rift_staking::ID {
+        "rift_staking"
+    } else if *program_id == rift_governance::ID {
+        "rift_governance"
+    } else {
+        "unknown"
+    }
+}
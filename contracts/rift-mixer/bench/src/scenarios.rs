@@ -0,0 +1,344 @@
+// Workloads: every instruction of the three programs, in an order that lets
+// each one succeed, at the state sizes we care about
+
+use rift_governance::VoteType;
+use rift_mixer::{
+    note_commitment, Pool, PoolType, ShieldedPool, EMERGENCY_CLAIM_DELAY_SLOTS, MAX_DEPOSIT_BATCH,
+    RELAYER_FEE_BPS,
+};
+use rift_test_utils::clock::{warp_days, warp_seconds, warp_slots};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_instruction;
+
+use crate::common::{
+    self, fetch, funded_keypair, governance, legacy, mixer, send, shielded, staking,
+};
+use crate::report::Measurement;
+use crate::runner::{Bench, Programs};
+
+/// Tree sizes `deposit`, `deposit_batch`, `withdraw`, `get_pool_stats`,
+/// `swap_denomination` and the emergency exit are measured at
+pub const LEAF_COUNTS: [usize; 3] = [0, 1_000, 100_000];
+
+/// Shielded tree sizes `transact` is measured at. The preloaded tree keeps
+/// its initial `MerkleTree::SIZE`, which holds 1,024 notes.
+pub const NOTE_COUNTS: [usize; 2] = [0, 1_000];
+
+/// Leaves in the version 0 tree `migrate_pool` moves; version 0 held at
+/// most 1,024
+pub const LEGACY_LEAVES: usize = 1_000;
+
+/// Spent nullifiers for the full-registry `withdraw` measurement
+pub const NULLIFIER_COUNT: usize = 10_000;
+
+const SOL: u64 = 1_000_000_000;
+const POOL: PoolType = PoolType::Sol0_1;
+
+pub async fn mixer(programs: &Programs, results: &mut Vec<Measurement>) {
+    let mut bench = Bench::start(programs.program_test(), "empty", results).await;
+    let authority = bench.ctx.payer.pubkey();
+    bench
+        .measure(
+            "initialize",
+            mixer::initialize(&authority, PoolType::Sol1),
+            &[],
+        )
+        .await;
+
+    for leaves in LEAF_COUNTS {
+        let mut program_test = programs.program_test();
+        mixer::preload_pool_with_state(&mut program_test, POOL, Pubkey::new_unique(), leaves, 0);
+        let state = format!("{} leaves", leaves);
+        let mut bench = Bench::start(program_test, state, results).await;
+        pool_operations(&mut bench).await;
+
+        // The pool's tree has room for one deposit and a batch, so these get
+        // their own bank
+        let mut program_test = programs.program_test();
+        let deployer = Keypair::new();
+        mixer::preload_pool_with_state(&mut program_test, POOL, Pubkey::new_unique(), leaves, 0);
+        mixer::preload_pool_with_state(&mut program_test, SWAP_SOURCE, Pubkey::new_unique(), 2, 0);
+        mixer::preload_program_data(&mut program_test, deployer.pubkey());
+        let state = format!("{} leaves", leaves);
+        let mut bench = Bench::start(program_test, state, results).await;
+        swap(&mut bench).await;
+        emergency_exit(&mut bench, &deployer).await;
+    }
+
+    let mut program_test = programs.program_test();
+    // One unspent deposit left so the withdrawal has funds to pay out
+    mixer::preload_pool_with_state(
+        &mut program_test,
+        POOL,
+        Pubkey::new_unique(),
+        NULLIFIER_COUNT + 1,
+        NULLIFIER_COUNT,
+    );
+    let state = format!("{} nullifiers", NULLIFIER_COUNT);
+    let mut bench = Bench::start(program_test, state, results).await;
+    let withdraw = withdraw(&mut bench, Pubkey::new_unique()).await;
+    bench.measure("withdraw", withdraw, &[]).await;
+
+    for notes in NOTE_COUNTS {
+        let mut program_test = programs.program_test();
+        let notes: Vec<_> = (0..notes).map(mixer::numbered).collect();
+        shielded::preload_shielded_pool(&mut program_test, Pubkey::new_unique(), &notes, 2 * SOL);
+        let state = format!("{} notes", notes.len());
+        let mut bench = Bench::start(program_test, state, results).await;
+        transact(&mut bench).await;
+    }
+
+    let mut program_test = programs.program_test();
+    let authority = Keypair::new();
+    legacy::preload_pool(&mut program_test, POOL, authority.pubkey(), LEGACY_LEAVES, 0);
+    let state = format!("{} v0 leaves", LEGACY_LEAVES);
+    let mut bench = Bench::start(program_test, state, results).await;
+    migrate(&mut bench, &authority).await;
+}
+
+async fn pool_operations(bench: &mut Bench<'_>) {
+    let depositor = funded_keypair(&mut bench.ctx, 2 * SOL).await;
+    bench
+        .measure(
+            "deposit",
+            mixer::deposit(&depositor.pubkey(), POOL, [7u8; 32]),
+            &[&depositor],
+        )
+        .await;
+    // A full batch, to check MAX_DEPOSIT_BATCH fits the compute budget
+    let commitments = (0..MAX_DEPOSIT_BATCH).map(|i| [0x80 + i as u8; 32]).collect();
+    bench
+        .measure(
+            "deposit_batch",
+            mixer::deposit_batch(&depositor.pubkey(), POOL, commitments),
+            &[&depositor],
+        )
+        .await;
+    let withdraw = withdraw(bench, Pubkey::new_unique()).await;
+    bench.measure("withdraw", withdraw, &[]).await;
+    bench
+        .measure("get_pool_stats", mixer::get_pool_stats(POOL, 0), &[])
+        .await;
+}
+
+/// The pool `swap_denomination` splits a note of into ten of `POOL`'s
+const SWAP_SOURCE: PoolType = PoolType::Sol1;
+
+async fn swap(bench: &mut Bench<'_>) {
+    let root = mixer::current_root(&mut bench.ctx, &SWAP_SOURCE).await;
+    let commitments = (0..10).map(|i| [0x40 + i as u8; 32]).collect();
+    let swap = mixer::swap_denomination(SWAP_SOURCE, POOL, root, [9u8; 32], commitments);
+    bench.measure("swap_denomination", swap, &[]).await;
+}
+
+/// Deposit a note, then reclaim it through emergency mode. The reveal
+/// searches the tree for the note, which is its last leaf.
+async fn emergency_exit(bench: &mut Bench<'_>, deployer: &Keypair) {
+    let (nullifier, secret) = ([0x50u8; 32], [0x51u8; 32]);
+    let depositor = funded_keypair(&mut bench.ctx, 2 * SOL).await;
+    let deposit = mixer::deposit(&depositor.pubkey(), POOL, note_commitment(&nullifier, &secret));
+    send(&mut bench.ctx, &[deposit], &[&depositor]).await.unwrap();
+
+    bench
+        .measure(
+            "enter_emergency_mode",
+            mixer::enter_emergency_mode(&deployer.pubkey(), POOL),
+            &[deployer],
+        )
+        .await;
+    let recipient = Pubkey::new_unique();
+    let claimant = bench.ctx.payer.pubkey();
+    bench
+        .measure(
+            "commit_emergency_withdraw",
+            mixer::commit_emergency_withdraw(&claimant, POOL, nullifier, secret, recipient),
+            &[],
+        )
+        .await;
+    warp_slots(&mut bench.ctx, EMERGENCY_CLAIM_DELAY_SLOTS).await;
+    bench
+        .measure(
+            "emergency_withdraw",
+            mixer::emergency_withdraw(POOL, nullifier, secret, recipient, claimant),
+            &[],
+        )
+        .await;
+}
+
+/// A shielded withdrawal of one SOL that pays a relayer
+async fn transact(bench: &mut Bench<'_>) {
+    let sender = funded_keypair(&mut bench.ctx, SOL).await;
+    let pool: ShieldedPool = fetch(&mut bench.ctx, &shielded::shielded_pool_address()).await;
+    let fee = SOL * RELAYER_FEE_BPS / 10_000;
+    let ext_data =
+        shielded::ext_data(Pubkey::new_unique(), -(SOL as i64), Pubkey::new_unique(), fee);
+    let transact = shielded::transact(
+        &sender.pubkey(),
+        pool.merkle_root,
+        [[0x60u8; 32], [0x61u8; 32]],
+        [[0x62u8; 32], [0x63u8; 32]],
+        ext_data,
+    );
+    bench.measure("transact", transact, &[&sender]).await;
+}
+
+/// Call `migrate_pool` until the pool reads the current version. Each call
+/// moves part of the tree, so each is measured on its own.
+async fn migrate(bench: &mut Bench<'_>, authority: &Keypair) {
+    // The authority pays for everything the migration creates and grows
+    let payer = bench.ctx.payer.pubkey();
+    let transfer = system_instruction::transfer(&payer, &authority.pubkey(), SOL);
+    send(&mut bench.ctx, &[transfer], &[]).await.unwrap();
+
+    for call in 1..=8 {
+        let migrate = mixer::migrate_pool(&authority.pubkey(), POOL);
+        bench
+            .measure(&format!("migrate_pool (call {})", call), migrate, &[authority])
+            .await;
+        let pool: Pool = fetch(&mut bench.ctx, &mixer::pool_address(&POOL)).await;
+        if pool.version == Pool::VERSION {
+            return;
+        }
+    }
+}
+
+/// Withdraw against the pool's latest root
+async fn withdraw(
+    bench: &mut Bench<'_>,
+    recipient: Pubkey,
+) -> solana_sdk::instruction::Instruction {
+    let root = mixer::current_root(&mut bench.ctx, &POOL).await;
+    mixer::withdraw(POOL, mixer::garbage_proof(), root, [8u8; 32], recipient, None, 0)
+}
+
+pub async fn staking(programs: &Programs, results: &mut Vec<Measurement>) {
+    let mut program_test = programs.program_test();
+    // `initialize` does not create the vault (see known_holes.rs)
+    common::add_program_owned_account(
+        &mut program_test,
+        staking::vault_address(),
+        rift_staking::ID,
+        1_000 * SOL,
+    );
+    let mut bench = Bench::start(program_test, "single staker", results).await;
+    let authority = bench.ctx.payer.pubkey();
+    let staker = funded_keypair(&mut bench.ctx, 200 * SOL).await;
+    let key = staker.pubkey();
+
+    bench
+        .measure("initialize", staking::initialize(&authority), &[])
+        .await;
+    bench
+        .measure("stake", staking::stake(&key, 100 * SOL), &[&staker])
+        .await;
+    bench
+        .measure(
+            "register_relayer",
+            staking::register_relayer(&key, 30),
+            &[&staker],
+        )
+        .await;
+    bench
+        .measure(
+            "record_relay",
+            staking::record_relay(&authority, &key, true),
+            &[],
+        )
+        .await;
+    bench
+        .measure("deposit_fees", staking::deposit_fees(&authority, SOL), &[])
+        .await;
+    bench
+        .measure("get_stake_info", staking::get_stake_info(&key), &[])
+        .await;
+
+    warp_days(&mut bench.ctx, 3).await;
+    bench
+        .measure("claim_rewards", staking::claim_rewards(&key), &[&staker])
+        .await;
+    bench
+        .measure(
+            "slash_relayer",
+            staking::slash_relayer(&authority, &key, &Pubkey::new_unique()),
+            &[],
+        )
+        .await;
+    bench
+        .measure("unstake", staking::unstake(&key), &[&staker])
+        .await;
+}
+
+pub async fn governance(programs: &Programs, results: &mut Vec<Measurement>) {
+    let mut bench = Bench::start(programs.program_test(), "single proposal", results).await;
+    let authority = bench.ctx.payer.pubkey();
+    let proposer = funded_keypair(&mut bench.ctx, SOL).await;
+    let voter = funded_keypair(&mut bench.ctx, SOL).await;
+
+    bench
+        .measure(
+            "initialize",
+            governance::initialize(&authority, 1_000_000),
+            &[],
+        )
+        .await;
+    bench
+        .measure(
+            "create_proposal",
+            governance::create_proposal(&proposer.pubkey(), 1),
+            &[&proposer],
+        )
+        .await;
+    bench
+        .measure(
+            "vote",
+            governance::vote(&voter.pubkey(), 1, VoteType::For, 200_000),
+            &[&voter],
+        )
+        .await;
+    bench
+        .measure("get_proposal", governance::get_proposal(1), &[])
+        .await;
+
+    warp_seconds(&mut bench.ctx, rift_governance::VOTING_PERIOD + 1).await;
+    bench
+        .measure("finalize_proposal", governance::finalize_proposal(1), &[])
+        .await;
+    warp_seconds(&mut bench.ctx, rift_governance::TIMELOCK_DELAY).await;
+    bench
+        .measure(
+            "execute_proposal",
+            governance::execute_proposal(&authority, 1),
+            &[],
+        )
+        .await;
+
+    bench
+        .measure(
+            "create_proposal",
+            governance::create_proposal(&proposer.pubkey(), 2),
+            &[&proposer],
+        )
+        .await;
+    bench
+        .measure(
+            "cancel_proposal",
+            governance::cancel_proposal(&proposer.pubkey(), 2),
+            &[&proposer],
+        )
+        .await;
+    bench
+        .measure(
+            "pause_governance",
+            governance::pause_governance(&authority),
+            &[],
+        )
+        .await;
+    bench
+        .measure(
+            "unpause_governance",
+            governance::unpause_governance(&authority),
+            &[],
+        )
+        .await;
+}
@@ -0,0 +1,23 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rift_fuzz::{Action, Harness, MAX_ACTIONS};
+
+fuzz_target!(|actions: Vec<Action>| {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let mut harness = Harness::start().await;
+        if let Err(violation) = harness.run(&actions).await {
+            // Print only the prefix that reached the violation; `cargo fuzz
+            // tmin` shrinks it further
+            let prefix = &actions[..=violation.step.min(MAX_ACTIONS - 1)];
+            panic!(
+                "invariant violated after step {}: {}\nsequence: {:#?}",
+                violation.step, violation.message, prefix
+            );
+        }
+    });
+});
@@ -0,0 +1,277 @@
+// Instruction-sequence harness for the three RIFT programs.
+//
+// A fuzz input is a list of `Action`s. Each one becomes a single transaction
+// signed by one of a handful of fixed users; most random transactions fail,
+// which is fine. After every step, failed or not, the cross-account
+// invariants in `check_invariants` must still hold.
+
+#![allow(dead_code)]
+
+// The integration tests' builders and program-test setup, shared verbatim
+#[path = "../../integration-tests/tests/common/mod.rs"]
+mod common;
+
+use arbitrary::Arbitrary;
+use common::{fetch, governance, mixer, program_test, send, staking};
+use rift_governance::{Governance, Proposal, VoteRecord, VoteType};
+use rift_mixer::{Pool, PoolType};
+use rift_staking::{StakeAccount, StakingPool};
+use rift_test_utils::clock::warp_seconds;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{keypair_from_seed, Keypair, Signer};
+
+/// Longest sequence run per input; the rest is ignored
+pub const MAX_ACTIONS: usize = 32;
+
+const USERS: usize = 4;
+const SOL: u64 = 1_000_000_000;
+const USER_BALANCE: u64 = 5_000 * SOL;
+const STAKING_VAULT_RESERVE: u64 = 1_000 * SOL;
+const GOVERNANCE_SUPPLY: u64 = 1_000_000;
+const POOL_TYPES: [PoolType; 2] = [PoolType::Sol0_1, PoolType::Sol1];
+
+#[derive(Arbitrary, Debug, Clone)]
+pub enum Action {
+    Deposit { user: u8, pool: u8, commitment: u8 },
+    Withdraw { pool: u8, nullifier: u8, recipient: u8, relayer: Option<u8>, fee: u32 },
+    /// `tenths` of a SOL, so both stake limits are reachable
+    Stake { user: u8, tenths: u16 },
+    ClaimRewards { user: u8 },
+    Unstake { user: u8 },
+    RegisterRelayer { user: u8, fee: u16 },
+    SlashRelayer { user: u8 },
+    CreateProposal { user: u8 },
+    Vote { user: u8, proposal: u8, support: u8, weight: u32 },
+    FinalizeProposal { proposal: u8 },
+    WarpHours { hours: u8 },
+}
+
+/// An invariant that stopped holding, and after which step
+#[derive(Debug)]
+pub struct Violation {
+    pub step: usize,
+    pub message: String,
+}
+
+pub struct Harness {
+    ctx: ProgramTestContext,
+    users: Vec<Keypair>,
+}
+
+impl Harness {
+    pub async fn start() -> Self {
+        // Seeded keys keep addresses, and therefore reruns of a crash, stable
+        let users: Vec<Keypair> = (0..USERS)
+            .map(|i| keypair_from_seed(&[i as u8 + 1; 32]).unwrap())
+            .collect();
+
+        let mut program_test = program_test();
+        for user in &users {
+            program_test.add_account(
+                user.pubkey(),
+                Account::new(USER_BALANCE, 0, &solana_sdk::system_program::ID),
+            );
+        }
+        for pool_type in POOL_TYPES {
+            mixer::preload_pool(&mut program_test, pool_type, Pubkey::new_unique());
+        }
+        common::add_program_owned_account(
+            &mut program_test,
+            staking::vault_address(),
+            rift_staking::ID,
+            STAKING_VAULT_RESERVE,
+        );
+
+        let mut ctx = program_test.start_with_context().await;
+        let authority = ctx.payer.pubkey();
+        send(
+            &mut ctx,
+            &[
+                staking::initialize(&authority),
+                governance::initialize(&authority, GOVERNANCE_SUPPLY),
+            ],
+            &[],
+        )
+        .await
+        .expect("initialize staking and governance");
+
+        Self { ctx, users }
+    }
+
+    /// Run `actions` in order, stopping at the first broken invariant
+    pub async fn run(&mut self, actions: &[Action]) -> Result<(), Violation> {
+        for (step, action) in actions.iter().take(MAX_ACTIONS).enumerate() {
+            self.apply(action).await;
+            self.check_invariants()
+                .await
+                .map_err(|message| Violation { step, message })?;
+        }
+        Ok(())
+    }
+
+    async fn apply(&mut self, action: &Action) {
+        let authority = self.ctx.payer.pubkey();
+        let (instruction, signer) = match *action {
+            Action::Deposit { user, pool, commitment } => {
+                let user = self.user(user);
+                let ix = mixer::deposit(&user.pubkey(), pool_type(pool), [commitment; 32]);
+                (ix, Some(user))
+            }
+            Action::Withdraw { pool, nullifier, recipient, relayer, fee } => {
+                let recipient = self.user(recipient).pubkey();
+                let relayer = relayer.map(|relayer| self.user(relayer).pubkey());
+                let root = mixer::current_root(&mut self.ctx, &pool_type(pool)).await;
+                let ix = mixer::withdraw(
+                    pool_type(pool),
+                    mixer::garbage_proof(),
+                    root,
+                    [nullifier; 32],
+                    recipient,
+                    relayer,
+                    fee as u64,
+                );
+                (ix, None)
+            }
+            Action::Stake { user, tenths } => {
+                let user = self.user(user);
+                (staking::stake(&user.pubkey(), tenths as u64 * SOL / 10), Some(user))
+            }
+            Action::ClaimRewards { user } => {
+                let user = self.user(user);
+                (staking::claim_rewards(&user.pubkey()), Some(user))
+            }
+            Action::Unstake { user } => {
+                let user = self.user(user);
+                (staking::unstake(&user.pubkey()), Some(user))
+            }
+            Action::RegisterRelayer { user, fee } => {
+                let user = self.user(user);
+                (staking::register_relayer(&user.pubkey(), fee as u64), Some(user))
+            }
+            Action::SlashRelayer { user } => {
+                let relayer = self.user(user).pubkey();
+                (staking::slash_relayer(&authority, &relayer, &authority), None)
+            }
+            Action::CreateProposal { user } => {
+                let id = self.proposal_count().await + 1;
+                let user = self.user(user);
+                (governance::create_proposal(&user.pubkey(), id), Some(user))
+            }
+            Action::Vote { user, proposal, support, weight } => {
+                let id = self.proposal_id(proposal).await;
+                let user = self.user(user);
+                let support = match support % 3 {
+                    0 => VoteType::For,
+                    1 => VoteType::Against,
+                    _ => VoteType::Abstain,
+                };
+                let ix = governance::vote(&user.pubkey(), id, support, weight as u64);
+                (ix, Some(user))
+            }
+            Action::FinalizeProposal { proposal } => {
+                let id = self.proposal_id(proposal).await;
+                (governance::finalize_proposal(id), None)
+            }
+            Action::WarpHours { hours } => {
+                warp_seconds(&mut self.ctx, hours as i64 * 60 * 60).await;
+                return;
+            }
+        };
+
+        let signer = signer.map(|user| user.insecure_clone());
+        let signers: Vec<&Keypair> = signer.iter().collect();
+        // Rejected transactions are expected; only the invariants matter
+        let _ = send(&mut self.ctx, &[instruction], &signers).await;
+    }
+
+    async fn check_invariants(&mut self) -> Result<(), String> {
+        for pool_type in POOL_TYPES {
+            let address = mixer::pool_address(&pool_type);
+            let pool: Pool = fetch(&mut self.ctx, &address).await;
+            let outstanding = pool
+                .total_deposits
+                .checked_sub(pool.total_withdrawals)
+                .ok_or_else(|| {
+                    format!(
+                        "{:?}: {} withdrawals exceed {} deposits",
+                        pool_type, pool.total_withdrawals, pool.total_deposits
+                    )
+                })?
+                * pool.deposit_amount;
+            let vault = common::lamports(&mut self.ctx, &mixer::vault_address(&address)).await;
+            if vault < outstanding {
+                return Err(format!(
+                    "{:?}: vault holds {} lamports, outstanding deposits are {}",
+                    pool_type, vault, outstanding
+                ));
+            }
+        }
+
+        let staking_pool: StakingPool = fetch(&mut self.ctx, &staking::staking_pool_address()).await;
+        let mut staked = 0;
+        for user in self.users.iter().map(Keypair::pubkey).collect::<Vec<_>>() {
+            if let Some(stake) = self.try_fetch::<StakeAccount>(&staking::stake_address(&user)).await
+            {
+                staked += stake.amount;
+            }
+        }
+        if staking_pool.total_staked != staked {
+            return Err(format!(
+                "total_staked is {}, stake accounts sum to {}",
+                staking_pool.total_staked, staked
+            ));
+        }
+
+        for id in 1..=self.proposal_count().await {
+            let proposal: Proposal = fetch(&mut self.ctx, &governance::proposal_address(id)).await;
+            let (mut votes_for, mut votes_against, mut votes_abstain) = (0, 0, 0);
+            for user in self.users.iter().map(Keypair::pubkey).collect::<Vec<_>>() {
+                let address = governance::vote_record_address(id, &user);
+                if let Some(record) = self.try_fetch::<VoteRecord>(&address).await {
+                    match record.support {
+                        VoteType::For => votes_for += record.weight,
+                        VoteType::Against => votes_against += record.weight,
+                        VoteType::Abstain => votes_abstain += record.weight,
+                    }
+                }
+            }
+            let totals = (proposal.votes_for, proposal.votes_against, proposal.votes_abstain);
+            if totals != (votes_for, votes_against, votes_abstain) {
+                return Err(format!(
+                    "proposal {} tallies {:?}, vote records sum to {:?}",
+                    id,
+                    totals,
+                    (votes_for, votes_against, votes_abstain)
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn user(&self, index: u8) -> &Keypair {
+        &self.users[index as usize % USERS]
+    }
+
+    async fn proposal_count(&mut self) -> u64 {
+        let governance: Governance = fetch(&mut self.ctx, &governance::governance_address()).await;
+        governance.proposal_count
+    }
+
+    /// Map a fuzzed byte onto an existing proposal id (1 when there are none)
+    async fn proposal_id(&mut self, proposal: u8) -> u64 {
+        let count = self.proposal_count().await.max(1);
+        proposal as u64 % count + 1
+    }
+
+    async fn try_fetch<T: anchor_lang::AccountDeserialize>(&mut self, address: &Pubkey) -> Option<T> {
+        let account = self.ctx.banks_client.get_account(*address).await.unwrap()?;
+        T::try_deserialize(&mut account.data.as_slice()).ok()
+    }
+}
+
+fn pool_type(pool: u8) -> PoolType {
+    POOL_TYPES[pool as usize % POOL_TYPES.len()].clone()
+}
@@ -0,0 +1,158 @@
+// Withdrawals that also prove membership of a curated association set
+
+mod common;
+
+use common::mixer::{self, association_registry_address, garbage_proof, pool_address};
+use common::{assert_error, fetch, funded_keypair, program_test, send, send_with_logs};
+use rift_mixer::{
+    AssociationRootRegistry, MixerError, Pool, PoolType, ASSOCIATION_ROOT_HISTORY,
+};
+use rift_sdk::events::withdraw_events;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SOL: u64 = 1_000_000_000;
+
+/// A pool with two deposits and an association registry run by the returned
+/// curator
+async fn start() -> (ProgramTestContext, Keypair) {
+    let authority = Keypair::new();
+    let mut program_test = program_test();
+    mixer::preload_pool(&mut program_test, PoolType::Sol1, authority.pubkey());
+    let mut ctx = program_test.start_with_context().await;
+
+    let depositor = funded_keypair(&mut ctx, 5 * SOL).await;
+    for commitment in [[1u8; 32], [2u8; 32]] {
+        send(
+            &mut ctx,
+            &[mixer::deposit(&depositor.pubkey(), PoolType::Sol1, commitment)],
+            &[&depositor],
+        )
+        .await
+        .unwrap();
+    }
+
+    let curator = Keypair::new();
+    let fund = solana_sdk::system_instruction::transfer(
+        &ctx.payer.pubkey(),
+        &authority.pubkey(),
+        SOL,
+    );
+    send(
+        &mut ctx,
+        &[
+            fund,
+            mixer::initialize_association_registry(
+                &authority.pubkey(),
+                PoolType::Sol1,
+                curator.pubkey(),
+            ),
+        ],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    (ctx, curator)
+}
+
+async fn publish(ctx: &mut ProgramTestContext, curator: &Keypair, root: [u8; 32]) {
+    send(
+        ctx,
+        &[mixer::publish_association_root(&curator.pubkey(), PoolType::Sol1, root)],
+        &[curator],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn withdraw_proves_membership_of_a_published_set() {
+    let (mut ctx, curator) = start().await;
+    publish(&mut ctx, &curator, [7u8; 32]).await;
+
+    let recipient = Pubkey::new_unique();
+    let root = mixer::current_root(&mut ctx, &PoolType::Sol1).await;
+    let withdraw = mixer::withdraw_with_association(
+        PoolType::Sol1,
+        garbage_proof(),
+        root,
+        [9u8; 32],
+        [7u8; 32],
+        recipient,
+        0,
+    );
+    let (result, logs) = send_with_logs(&mut ctx, &[withdraw], &[]).await;
+    result.unwrap();
+
+    let event = &withdraw_events(&logs)[0];
+    assert_eq!(event.association_root, Some([7u8; 32]));
+    assert_eq!(event.recipient, recipient);
+    let pool: Pool = fetch(&mut ctx, &pool_address(&PoolType::Sol1)).await;
+    assert_eq!(pool.total_withdrawals, 1);
+
+    // The nullifier is shared with plain withdrawals
+    let again = send(
+        &mut ctx,
+        &[mixer::withdraw(PoolType::Sol1, garbage_proof(), root, [9u8; 32], recipient, None, 0)],
+        &[],
+    )
+    .await;
+    assert_error(again, MixerError::NullifierAlreadyUsed);
+}
+
+#[tokio::test]
+async fn unpublished_and_expired_roots_are_rejected() {
+    let (mut ctx, curator) = start().await;
+    let pool_root = mixer::current_root(&mut ctx, &PoolType::Sol1).await;
+    let withdraw = move |association_root: [u8; 32]| {
+        mixer::withdraw_with_association(
+            PoolType::Sol1,
+            garbage_proof(),
+            pool_root,
+            [9u8; 32],
+            association_root,
+            Pubkey::new_unique(),
+            0,
+        )
+    };
+
+    let unpublished = send(&mut ctx, &[withdraw([7u8; 32])], &[]).await;
+    assert_error(unpublished, MixerError::UnknownAssociationRoot);
+    let empty_slot = send(&mut ctx, &[withdraw([0u8; 32])], &[]).await;
+    assert_error(empty_slot, MixerError::UnknownAssociationRoot);
+
+    // The first root survives ASSOCIATION_ROOT_HISTORY - 1 newer ones...
+    publish(&mut ctx, &curator, [7u8; 32]).await;
+    for n in 1..ASSOCIATION_ROOT_HISTORY {
+        publish(&mut ctx, &curator, mixer::numbered(n)).await;
+    }
+    let registry: AssociationRootRegistry = fetch(
+        &mut ctx,
+        &association_registry_address(&pool_address(&PoolType::Sol1)),
+    )
+    .await;
+    assert!(registry.roots.contains(&[7u8; 32]));
+
+    // ...and is evicted by the next
+    publish(&mut ctx, &curator, mixer::numbered(ASSOCIATION_ROOT_HISTORY)).await;
+    let expired = send(&mut ctx, &[withdraw([7u8; 32])], &[]).await;
+    assert_error(expired, MixerError::UnknownAssociationRoot);
+    send(&mut ctx, &[withdraw(mixer::numbered(1))], &[])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn only_the_curator_publishes() {
+    let (mut ctx, _curator) = start().await;
+    let intruder = funded_keypair(&mut ctx, SOL).await;
+
+    let result = send(
+        &mut ctx,
+        &[mixer::publish_association_root(&intruder.pubkey(), PoolType::Sol1, [7u8; 32])],
+        &[&intruder],
+    )
+    .await;
+    assert_error(result, MixerError::Unauthorized);
+}
@@ -0,0 +1,141 @@
+// rift_governance addresses and instruction builders
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use rift_governance::{
+    ProposalAction, ProposalCategory, VoteType, GOVERNANCE_SEED, PROPOSAL_SEED, VOTE_SEED,
+};
+
+use super::instruction;
+
+pub fn governance_address() -> Pubkey {
+    Pubkey::find_program_address(&[GOVERNANCE_SEED], &rift_governance::ID).0
+}
+
+pub fn proposal_address(id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[PROPOSAL_SEED, &id.to_le_bytes()], &rift_governance::ID).0
+}
+
+pub fn vote_record_address(proposal_id: u64, voter: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[VOTE_SEED, proposal_id.to_le_bytes().as_ref(), voter.as_ref()],
+        &rift_governance::ID,
+    )
+    .0
+}
+
+pub fn initialize(authority: &Pubkey, total_supply: u64) -> Instruction {
+    instruction(
+        rift_governance::ID,
+        rift_governance::accounts::Initialize {
+            governance: governance_address(),
+            authority: *authority,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_governance::instruction::Initialize { total_supply },
+    )
+}
+
+/// Create proposal `id`, which must be `proposal_count + 1`
+pub fn create_proposal(proposer: &Pubkey, id: u64) -> Instruction {
+    instruction(
+        rift_governance::ID,
+        rift_governance::accounts::CreateProposal {
+            governance: governance_address(),
+            proposal: proposal_address(id),
+            proposer: *proposer,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_governance::instruction::CreateProposal {
+            title: format!("Proposal {}", id),
+            description: "Adjust a protocol parameter".to_string(),
+            category: ProposalCategory::Parameter,
+            actions: vec![ProposalAction {
+                target: rift_mixer::ID,
+                method: "set_parameter".to_string(),
+                data: Vec::new(),
+                value: 0,
+            }],
+        },
+    )
+}
+
+pub fn vote(voter: &Pubkey, proposal_id: u64, support: VoteType, weight: u64) -> Instruction {
+    instruction(
+        rift_governance::ID,
+        rift_governance::accounts::Vote {
+            governance: governance_address(),
+            proposal: proposal_address(proposal_id),
+            vote_record: vote_record_address(proposal_id, voter),
+            voter: *voter,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_governance::instruction::Vote { support, weight },
+    )
+}
+
+pub fn finalize_proposal(proposal_id: u64) -> Instruction {
+    instruction(
+        rift_governance::ID,
+        rift_governance::accounts::FinalizeProposal {
+            governance: governance_address(),
+            proposal: proposal_address(proposal_id),
+        },
+        rift_governance::instruction::FinalizeProposal {},
+    )
+}
+
+pub fn execute_proposal(executor: &Pubkey, proposal_id: u64) -> Instruction {
+    instruction(
+        rift_governance::ID,
+        rift_governance::accounts::ExecuteProposal {
+            proposal: proposal_address(proposal_id),
+            executor: *executor,
+        },
+        rift_governance::instruction::ExecuteProposal {},
+    )
+}
+
+pub fn cancel_proposal(canceller: &Pubkey, proposal_id: u64) -> Instruction {
+    instruction(
+        rift_governance::ID,
+        rift_governance::accounts::CancelProposal {
+            governance: governance_address(),
+            proposal: proposal_address(proposal_id),
+            canceller: *canceller,
+        },
+        rift_governance::instruction::CancelProposal {},
+    )
+}
+
+pub fn pause_governance(authority: &Pubkey) -> Instruction {
+    instruction(
+        rift_governance::ID,
+        rift_governance::accounts::PauseGovernance {
+            governance: governance_address(),
+            authority: *authority,
+        },
+        rift_governance::instruction::PauseGovernance {},
+    )
+}
+
+pub fn unpause_governance(authority: &Pubkey) -> Instruction {
+    instruction(
+        rift_governance::ID,
+        rift_governance::accounts::PauseGovernance {
+            governance: governance_address(),
+            authority: *authority,
+        },
+        rift_governance::instruction::UnpauseGovernance {},
+    )
+}
+
+pub fn get_proposal(proposal_id: u64) -> Instruction {
+    instruction(
+        rift_governance::ID,
+        rift_governance::accounts::GetProposal {
+            proposal: proposal_address(proposal_id),
+        },
+        rift_governance::instruction::GetProposal {},
+    )
+}
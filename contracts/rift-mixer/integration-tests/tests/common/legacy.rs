@@ -0,0 +1,167 @@
+// rift_mixer accounts as the first deployment (version 0) wrote them, for
+// tests and benchmarks of `migrate_pool`
+
+use anchor_lang::prelude::{borsh, Pubkey};
+use anchor_lang::{AnchorSerialize, Discriminator};
+use rift_mixer::{
+    get_deposit_amount, note_nullifier_hash, MerkleTree as CurrentMerkleTree,
+    NullifierRegistry as CurrentNullifierRegistry, Pool as CurrentPool, PoolType,
+    MERKLE_TREE_HEIGHT, POOL_SEED,
+};
+use solana_program_test::ProgramTest;
+use solana_sdk::account::Account;
+use solana_sdk::keccak;
+use solana_sdk::rent::Rent;
+
+use super::add_program_owned_account;
+use super::mixer::{legacy_tree_address, nullifier_registry_address, numbered, vault_address};
+
+#[derive(AnchorSerialize)]
+pub struct Pool {
+    pub authority: Pubkey,
+    pub pool_type: PoolType,
+    pub deposit_amount: u64,
+    pub total_deposits: u64,
+    pub total_withdrawals: u64,
+    pub merkle_root: [u8; 32],
+    pub next_index: u64,
+    pub bump: u8,
+}
+
+pub const POOL_SIZE: usize = 32 + 1 + 8 + 8 + 8 + 32 + 8 + 1 + 64;
+
+/// At `[MERKLE_SEED, &[pool_type]]`
+#[derive(AnchorSerialize)]
+pub struct MerkleTree {
+    pub leaves: Vec<[u8; 32]>,
+    pub filled_subtrees: [[u8; 32]; MERKLE_TREE_HEIGHT],
+}
+
+pub const MERKLE_TREE_SIZE: usize = 4 + (32 * 1024) + (32 * MERKLE_TREE_HEIGHT) + 64;
+
+#[derive(AnchorSerialize)]
+pub struct NullifierRegistry {
+    pub used_nullifiers: Vec<[u8; 32]>,
+}
+
+pub const NULLIFIER_REGISTRY_SIZE: usize = 4 + (32 * 10000) + 64;
+
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    keccak::hashv(&[left, right]).to_bytes()
+}
+
+/// Version 0's insert: a subtree was only recorded at the first level where
+/// the new leaf was a left child
+pub fn insert_leaf(tree: &mut MerkleTree, index: usize, leaf: [u8; 32]) {
+    tree.leaves.push(leaf);
+    let mut current_hash = leaf;
+    let mut current_index = index;
+    for level in 0..MERKLE_TREE_HEIGHT {
+        if current_index & 1 == 0 {
+            tree.filled_subtrees[level] = current_hash;
+            break;
+        }
+        current_hash = hash_pair(&tree.filled_subtrees[level], &current_hash);
+        current_index /= 2;
+    }
+}
+
+/// Version 0's root: missing right siblings were zero bytes rather than
+/// empty subtrees, and it stopped at the first level with a single node
+pub fn compute_merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.is_empty() {
+        return [0u8; 32];
+    }
+    let mut current_level = leaves.to_vec();
+    while current_level.len() > 1 {
+        current_level = current_level
+            .chunks(2)
+            .map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(&[0u8; 32])))
+            .collect();
+    }
+    current_level[0]
+}
+
+/// Pre-load `state` under `T`'s discriminator in an account of `space` bytes
+fn add_v0_account<T: Discriminator>(
+    program_test: &mut ProgramTest,
+    address: Pubkey,
+    space: usize,
+    state: &impl AnchorSerialize,
+) {
+    let mut data = T::discriminator().to_vec();
+    state.serialize(&mut data).unwrap();
+    assert!(data.len() <= space, "v0 state overflows its account");
+    data.resize(space, 0);
+    program_test.add_account(
+        address,
+        Account {
+            lamports: Rent::default().minimum_balance(space),
+            data,
+            owner: rift_mixer::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+/// A version 0 pool with `leaves` deposits (the notes of `numbered(i)`), the
+/// first one withdrawn. Its registry holds `nullifiers` spent nullifiers.
+pub fn preload_pool(
+    program_test: &mut ProgramTest,
+    pool_type: PoolType,
+    authority: Pubkey,
+    leaves: usize,
+    nullifiers: usize,
+) {
+    let (pool, bump) =
+        Pubkey::find_program_address(&[POOL_SEED, &[pool_type.to_u8()]], &rift_mixer::ID);
+    let deposit_amount = get_deposit_amount(&pool_type);
+
+    let mut tree = MerkleTree {
+        leaves: Vec::new(),
+        filled_subtrees: [[0u8; 32]; MERKLE_TREE_HEIGHT],
+    };
+    for index in 0..leaves {
+        insert_leaf(&mut tree, index, numbered(index));
+    }
+    let merkle_root = compute_merkle_root(&tree.leaves);
+    let outstanding = leaves as u64 - 1;
+
+    add_v0_account::<CurrentPool>(
+        program_test,
+        pool,
+        8 + POOL_SIZE,
+        &Pool {
+            authority,
+            pool_type: pool_type.clone(),
+            deposit_amount,
+            total_deposits: leaves as u64,
+            total_withdrawals: 1,
+            merkle_root,
+            next_index: leaves as u64,
+            bump,
+        },
+    );
+    add_v0_account::<CurrentMerkleTree>(
+        program_test,
+        legacy_tree_address(&pool_type),
+        8 + MERKLE_TREE_SIZE,
+        &tree,
+    );
+    let used_nullifiers = (0..nullifiers)
+        .map(|n| note_nullifier_hash(&numbered(usize::MAX - n)))
+        .collect();
+    add_v0_account::<CurrentNullifierRegistry>(
+        program_test,
+        nullifier_registry_address(&pool),
+        8 + NULLIFIER_REGISTRY_SIZE,
+        &NullifierRegistry { used_nullifiers },
+    );
+    add_program_owned_account(
+        program_test,
+        vault_address(&pool),
+        rift_mixer::ID,
+        outstanding * deposit_amount,
+    );
+}
@@ -0,0 +1,808 @@
+// rift_mixer addresses, instruction builders and fixtures
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use rift_mixer::{
+    emergency_claim_hash, get_deposit_amount, insert_leaf, zero_hashes, DepositActivity,
+    MerkleTree, NullifierRegistry, Pool, PoolType, RootEntry, RootHistory, WithdrawIntent,
+    ZkProof, ACTIVITY_BUCKETS, ASSOCIATION_SEED, DENIED_SEED, DENY_LIST_SEED,
+    DEPOSIT_ACTIVITY_SEED, EMERGENCY_CLAIM_SEED, MAX_DEPOSIT_BATCH, MERKLE_SEED,
+    MERKLE_TREE_HEIGHT, POOL_SEED, ROOT_HISTORY_SEED, ROOT_HISTORY_SIZE,
+};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::rent::Rent;
+
+use super::{add_anchor_account, add_program_owned_account, fetch, instruction};
+
+pub fn pool_address(pool_type: &PoolType) -> Pubkey {
+    Pubkey::find_program_address(&[POOL_SEED, &[pool_type.to_u8()]], &rift_mixer::ID).0
+}
+
+pub fn merkle_tree_address(pool: &Pubkey, tree_index: u32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[MERKLE_SEED, pool.as_ref(), &tree_index.to_le_bytes()],
+        &rift_mixer::ID,
+    )
+    .0
+}
+
+/// Where version 0 kept a pool's (only) tree
+pub fn legacy_tree_address(pool_type: &PoolType) -> Pubkey {
+    Pubkey::find_program_address(&[MERKLE_SEED, &[pool_type.to_u8()]], &rift_mixer::ID).0
+}
+
+pub fn vault_address(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"vault", pool.as_ref()], &rift_mixer::ID).0
+}
+
+pub fn nullifier_registry_address(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"nullifiers", pool.as_ref()], &rift_mixer::ID).0
+}
+
+pub fn root_history_address(pool: &Pubkey, tree_index: u32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[ROOT_HISTORY_SEED, pool.as_ref(), &tree_index.to_le_bytes()],
+        &rift_mixer::ID,
+    )
+    .0
+}
+
+pub fn deposit_activity_address(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[DEPOSIT_ACTIVITY_SEED, pool.as_ref()], &rift_mixer::ID).0
+}
+
+/// The current tree's latest root, which withdrawals in tests usually prove
+/// against
+pub async fn current_root(ctx: &mut ProgramTestContext, pool_type: &PoolType) -> [u8; 32] {
+    let pool: Pool = fetch(ctx, &pool_address(pool_type)).await;
+    pool.merkle_root
+}
+
+pub fn association_registry_address(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[ASSOCIATION_SEED, pool.as_ref()], &rift_mixer::ID).0
+}
+
+pub fn deny_list_address() -> Pubkey {
+    Pubkey::find_program_address(&[DENY_LIST_SEED], &rift_mixer::ID).0
+}
+
+pub fn denied_address(address: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[DENIED_SEED, address.as_ref()], &rift_mixer::ID).0
+}
+
+pub fn program_data_address() -> Pubkey {
+    Pubkey::find_program_address(&[rift_mixer::ID.as_ref()], &bpf_loader_upgradeable::ID).0
+}
+
+/// Pre-load the program data account an upgradeable deployment of the mixer
+/// would have, naming `upgrade_authority`. The builtin mixer has none.
+pub fn preload_program_data(program_test: &mut ProgramTest, upgrade_authority: Pubkey) {
+    let state = UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address: Some(upgrade_authority),
+    };
+    let account = Account::new_data(
+        Rent::default().minimum_balance(UpgradeableLoaderState::size_of_programdata_metadata()),
+        &state,
+        &bpf_loader_upgradeable::ID,
+    )
+    .unwrap();
+    program_test.add_account(program_data_address(), account);
+}
+
+/// Pre-load the state a working `initialize` would leave behind.
+///
+/// Today nothing creates the program-owned vault or the nullifier registry,
+/// so tests of `deposit` and `withdraw` start from this instead. The gaps
+/// themselves are covered by `known_holes.rs`.
+pub fn preload_pool(program_test: &mut ProgramTest, pool_type: PoolType, authority: Pubkey) {
+    preload_pool_with_state(program_test, pool_type, authority, 0, 0);
+}
+
+/// Leaves in each full tree of `preload_full_pool` and
+/// `preload_rolled_over_pool`. A tree is full once its account has no room
+/// for another leaf, so a small one stands in for 2^20 deposits.
+pub const FULL_TREE_LEAVES: usize = 4;
+
+/// `preload_pool` with `leaves` deposits already in the tree (and in the
+/// vault) and `nullifiers` of them already spent. Accounts grow past their
+/// declared `SIZE` when the state does not fit, keeping room for one more
+/// nullifier and for a deposit plus a full `deposit_batch`. Deposit caps are
+/// lifted so they never limit a fixture; tests of the caps set their own with
+/// `set_pool_caps`.
+pub fn preload_pool_with_state(
+    program_test: &mut ProgramTest,
+    pool_type: PoolType,
+    authority: Pubkey,
+    leaves: usize,
+    nullifiers: usize,
+) {
+    preload_trees(program_test, pool_type, authority, 1, Some(leaves), nullifiers);
+}
+
+/// `preload_pool` whose tree 0 is full, so deposits need `open_tree` and
+/// `grow_tree` first
+pub fn preload_full_pool(program_test: &mut ProgramTest, pool_type: PoolType, authority: Pubkey) {
+    preload_trees(program_test, pool_type, authority, 1, None, 0);
+}
+
+/// `preload_pool` that has already rolled over: tree 0 is full and deposits
+/// go into an empty tree 1
+pub fn preload_rolled_over_pool(
+    program_test: &mut ProgramTest,
+    pool_type: PoolType,
+    authority: Pubkey,
+) {
+    preload_trees(program_test, pool_type, authority, 2, Some(0), 0);
+}
+
+/// `preload_pool` with two full trees whose every deposit has been
+/// withdrawn, as a retired pool is before `close_pool`
+pub fn preload_drained_pool(
+    program_test: &mut ProgramTest,
+    pool_type: PoolType,
+    authority: Pubkey,
+) {
+    preload_trees(program_test, pool_type, authority, 2, None, 2 * FULL_TREE_LEAVES);
+}
+
+/// `trees` trees, all full but the last, which holds `open_leaves` and keeps
+/// room to deposit into (or is full too if `open_leaves` is `None`)
+fn preload_trees(
+    program_test: &mut ProgramTest,
+    pool_type: PoolType,
+    authority: Pubkey,
+    trees: u32,
+    open_leaves: Option<usize>,
+    nullifiers: usize,
+) {
+    let (pool, bump) =
+        Pubkey::find_program_address(&[POOL_SEED, &[pool_type.to_u8()]], &rift_mixer::ID);
+    let deposit_amount = get_deposit_amount(&pool_type);
+
+    let mut next_index = 0;
+    let mut merkle_root = zero_hashes(MERKLE_TREE_HEIGHT)[MERKLE_TREE_HEIGHT];
+    for tree_index in 0..trees {
+        let open = open_leaves.filter(|_| tree_index == trees - 1);
+        let leaves: Vec<[u8; 32]> = (next_index..next_index + open.unwrap_or(FULL_TREE_LEAVES))
+            .map(numbered)
+            .collect();
+        next_index += leaves.len();
+        let mut filled_subtrees = [[0u8; 32]; MERKLE_TREE_HEIGHT];
+        merkle_root = zero_hashes(MERKLE_TREE_HEIGHT)[MERKLE_TREE_HEIGHT];
+        for (index, leaf) in leaves.iter().enumerate() {
+            merkle_root = insert_leaf(&mut filled_subtrees, index, *leaf);
+        }
+
+        // The preloaded root counts as created at slot 0
+        let mut entries = [RootEntry::default(); ROOT_HISTORY_SIZE];
+        entries[0] = RootEntry {
+            root: merkle_root,
+            slot: 0,
+            leaf_count: next_index as u64,
+        };
+        add_anchor_account(
+            program_test,
+            root_history_address(&pool, tree_index),
+            rift_mixer::ID,
+            8 + RootHistory::SIZE,
+            &RootHistory {
+                entries,
+                current_index: 0,
+                version: RootHistory::VERSION,
+            },
+        );
+        let space = match open {
+            Some(_) => MerkleTree::SIZE
+                .max(MerkleTree::space_for(leaves.len() + 1 + MAX_DEPOSIT_BATCH)),
+            None => MerkleTree::space_for(leaves.len()),
+        };
+        add_anchor_account(
+            program_test,
+            merkle_tree_address(&pool, tree_index),
+            rift_mixer::ID,
+            8 + space,
+            &MerkleTree {
+                leaves,
+                filled_subtrees,
+                version: MerkleTree::VERSION,
+            },
+        );
+    }
+    let used_nullifiers: Vec<[u8; 32]> =
+        (0..nullifiers).map(|i| numbered(usize::MAX - i)).collect();
+    let outstanding = next_index.saturating_sub(nullifiers) as u64;
+
+    add_anchor_account(
+        program_test,
+        pool,
+        rift_mixer::ID,
+        8 + Pool::SIZE,
+        &Pool {
+            authority,
+            pool_type: pool_type.clone(),
+            deposit_amount,
+            total_deposits: next_index as u64,
+            total_withdrawals: used_nullifiers.len() as u64,
+            merkle_root,
+            next_index: next_index as u64,
+            bump,
+            max_tvl: u64::MAX,
+            max_deposits_per_epoch: u64::MAX,
+            current_epoch: 0,
+            epoch_deposits: 0,
+            min_leaf_age_slots: 0,
+            min_leaf_age_deposits: 0,
+            current_tree: trees - 1,
+            version: Pool::VERSION,
+            emergency: false,
+            retired: false,
+        },
+    );
+    // Preloaded deposits are older than the activity window
+    add_anchor_account(
+        program_test,
+        deposit_activity_address(&pool),
+        rift_mixer::ID,
+        8 + DepositActivity::SIZE,
+        &DepositActivity {
+            buckets: [0; ACTIVITY_BUCKETS],
+            last_bucket: 0,
+            version: DepositActivity::VERSION,
+        },
+    );
+    add_program_owned_account(
+        program_test,
+        vault_address(&pool),
+        rift_mixer::ID,
+        outstanding * deposit_amount,
+    );
+    add_anchor_account(
+        program_test,
+        nullifier_registry_address(&pool),
+        rift_mixer::ID,
+        8 + NullifierRegistry::SIZE.max(NullifierRegistry::space_for(used_nullifiers.len() + 1)),
+        &NullifierRegistry {
+            used_nullifiers,
+            version: NullifierRegistry::VERSION,
+        },
+    );
+}
+
+pub fn initialize(authority: &Pubkey, pool_type: PoolType) -> Instruction {
+    let pool = pool_address(&pool_type);
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::Initialize {
+            pool,
+            merkle_tree: merkle_tree_address(&pool, 0),
+            root_history: root_history_address(&pool, 0),
+            deposit_activity: deposit_activity_address(&pool),
+            pool_vault: vault_address(&pool),
+            authority: *authority,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_mixer::instruction::Initialize { pool_type },
+    )
+}
+
+pub fn deposit(depositor: &Pubkey, pool_type: PoolType, commitment: [u8; 32]) -> Instruction {
+    deposit_with_note(depositor, pool_type, commitment, None)
+}
+
+/// `deposit` into a pool whose current tree is `tree_index`
+pub fn deposit_into_tree(
+    depositor: &Pubkey,
+    pool_type: PoolType,
+    tree_index: u32,
+    commitment: [u8; 32],
+) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        deposit_accounts(depositor, &pool_type, tree_index),
+        rift_mixer::instruction::Deposit {
+            commitment,
+            encrypted_note: None,
+        },
+    )
+}
+
+pub fn deposit_with_note(
+    depositor: &Pubkey,
+    pool_type: PoolType,
+    commitment: [u8; 32],
+    encrypted_note: Option<Vec<u8>>,
+) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        deposit_accounts(depositor, &pool_type, 0),
+        rift_mixer::instruction::Deposit {
+            commitment,
+            encrypted_note,
+        },
+    )
+}
+
+pub fn deposit_batch(
+    depositor: &Pubkey,
+    pool_type: PoolType,
+    commitments: Vec<[u8; 32]>,
+) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        deposit_accounts(depositor, &pool_type, 0),
+        rift_mixer::instruction::DepositBatch { commitments },
+    )
+}
+
+fn deposit_accounts(
+    depositor: &Pubkey,
+    pool_type: &PoolType,
+    tree_index: u32,
+) -> rift_mixer::accounts::Deposit {
+    let pool = pool_address(pool_type);
+    rift_mixer::accounts::Deposit {
+        pool,
+        merkle_tree: merkle_tree_address(&pool, tree_index),
+        root_history: root_history_address(&pool, tree_index),
+        deposit_activity: deposit_activity_address(&pool),
+        pool_vault: vault_address(&pool),
+        depositor: *depositor,
+        denied_depositor: denied_address(depositor),
+        system_program: anchor_lang::system_program::ID,
+    }
+}
+
+fn withdraw_accounts(
+    pool_type: &PoolType,
+    tree_index: u32,
+    recipient: Pubkey,
+    relayer: Option<Pubkey>,
+) -> rift_mixer::accounts::Withdraw {
+    let pool = pool_address(pool_type);
+    rift_mixer::accounts::Withdraw {
+        pool,
+        nullifier_registry: nullifier_registry_address(&pool),
+        root_history: root_history_address(&pool, tree_index),
+        pool_vault: vault_address(&pool),
+        recipient,
+        relayer: relayer.unwrap_or(recipient),
+        system_program: anchor_lang::system_program::ID,
+    }
+}
+
+/// An intent that never expires
+pub fn withdraw_intent(
+    pool_type: PoolType,
+    root: [u8; 32],
+    nullifier_hash: [u8; 32],
+    recipient: Pubkey,
+    relayer: Option<Pubkey>,
+    fee: u64,
+) -> WithdrawIntent {
+    WithdrawIntent {
+        pool_type,
+        root,
+        nullifier_hash,
+        recipient,
+        relayer,
+        fee,
+        expiry_slot: u64::MAX,
+    }
+}
+
+/// `withdraw` of `intent` against tree `tree_index`, with accounts matching it
+pub fn withdraw_with_intent(
+    tree_index: u32,
+    proof: ZkProof,
+    intent: WithdrawIntent,
+) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        withdraw_accounts(&intent.pool_type, tree_index, intent.recipient, intent.relayer),
+        rift_mixer::instruction::Withdraw {
+            tree_index,
+            proof,
+            intent,
+        },
+    )
+}
+
+pub fn withdraw(
+    pool_type: PoolType,
+    proof: ZkProof,
+    root: [u8; 32],
+    nullifier_hash: [u8; 32],
+    recipient: Pubkey,
+    relayer: Option<Pubkey>,
+    fee: u64,
+) -> Instruction {
+    let intent = withdraw_intent(pool_type, root, nullifier_hash, recipient, relayer, fee);
+    withdraw_with_intent(0, proof, intent)
+}
+
+/// A relayer-less, fee-less `withdraw` proving against tree `tree_index`
+pub fn withdraw_from_tree(
+    pool_type: PoolType,
+    tree_index: u32,
+    root: [u8; 32],
+    nullifier_hash: [u8; 32],
+    recipient: Pubkey,
+) -> Instruction {
+    let intent = withdraw_intent(pool_type, root, nullifier_hash, recipient, None, 0);
+    withdraw_with_intent(tree_index, garbage_proof(), intent)
+}
+
+/// `swap_denomination` of a note in tree 0 of `from` into `commitments`
+/// in `to`'s tree 0
+pub fn swap_denomination(
+    from: PoolType,
+    to: PoolType,
+    root: [u8; 32],
+    nullifier_hash: [u8; 32],
+    commitments: Vec<[u8; 32]>,
+) -> Instruction {
+    let (source, target) = (pool_address(&from), pool_address(&to));
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::SwapDenomination {
+            source_pool: source,
+            source_nullifier_registry: nullifier_registry_address(&source),
+            source_root_history: root_history_address(&source, 0),
+            source_vault: vault_address(&source),
+            target_pool: target,
+            target_merkle_tree: merkle_tree_address(&target, 0),
+            target_root_history: root_history_address(&target, 0),
+            target_deposit_activity: deposit_activity_address(&target),
+            target_vault: vault_address(&target),
+        },
+        rift_mixer::instruction::SwapDenomination {
+            tree_index: 0,
+            proof: garbage_proof(),
+            root,
+            nullifier_hash,
+            commitments,
+        },
+    )
+}
+
+pub fn enter_emergency_mode(deployment_authority: &Pubkey, pool_type: PoolType) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::EnterEmergencyMode {
+            pool: pool_address(&pool_type),
+            deployment_authority: *deployment_authority,
+            program_data: program_data_address(),
+        },
+        rift_mixer::instruction::EnterEmergencyMode {},
+    )
+}
+
+pub fn retire_pool(authority: &Pubkey, pool_type: PoolType) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::SetPoolConfig {
+            pool: pool_address(&pool_type),
+            authority: *authority,
+        },
+        rift_mixer::instruction::RetirePool {},
+    )
+}
+
+/// `close_pool` for a pool whose current tree is `current_tree`
+pub fn close_pool(
+    authority: &Pubkey,
+    pool_type: PoolType,
+    current_tree: u32,
+    treasury: Pubkey,
+) -> Instruction {
+    let pool = pool_address(&pool_type);
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::ClosePool {
+            pool,
+            merkle_tree: merkle_tree_address(&pool, current_tree),
+            root_history: root_history_address(&pool, current_tree),
+            nullifier_registry: nullifier_registry_address(&pool),
+            deposit_activity: deposit_activity_address(&pool),
+            pool_vault: vault_address(&pool),
+            authority: *authority,
+            treasury,
+        },
+        rift_mixer::instruction::ClosePool {},
+    )
+}
+
+pub fn emergency_claim_address(pool: &Pubkey, claim_hash: &[u8; 32]) -> Pubkey {
+    Pubkey::find_program_address(
+        &[EMERGENCY_CLAIM_SEED, pool.as_ref(), claim_hash],
+        &rift_mixer::ID,
+    )
+    .0
+}
+
+/// `commit_emergency_withdraw` of the note `(nullifier, secret)` to `recipient`
+pub fn commit_emergency_withdraw(
+    claimant: &Pubkey,
+    pool_type: PoolType,
+    nullifier: [u8; 32],
+    secret: [u8; 32],
+    recipient: Pubkey,
+) -> Instruction {
+    let pool = pool_address(&pool_type);
+    let claim_hash = emergency_claim_hash(&nullifier, &secret, &recipient);
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::CommitEmergencyWithdraw {
+            pool,
+            claim: emergency_claim_address(&pool, &claim_hash),
+            claimant: *claimant,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_mixer::instruction::CommitEmergencyWithdraw { claim_hash },
+    )
+}
+
+/// `emergency_withdraw` of the note `(nullifier, secret)` from tree 0, under
+/// the claim `claimant` committed
+pub fn emergency_withdraw(
+    pool_type: PoolType,
+    nullifier: [u8; 32],
+    secret: [u8; 32],
+    recipient: Pubkey,
+    claimant: Pubkey,
+) -> Instruction {
+    let pool = pool_address(&pool_type);
+    let claim_hash = emergency_claim_hash(&nullifier, &secret, &recipient);
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::EmergencyWithdraw {
+            pool,
+            merkle_tree: merkle_tree_address(&pool, 0),
+            nullifier_registry: nullifier_registry_address(&pool),
+            pool_vault: vault_address(&pool),
+            recipient,
+            claim: emergency_claim_address(&pool, &claim_hash),
+            claimant,
+        },
+        rift_mixer::instruction::EmergencyWithdraw {
+            tree_index: 0,
+            nullifier,
+            secret,
+        },
+    )
+}
+
+/// `open_tree` for a pool whose current tree is `current_tree`
+pub fn open_tree(payer: &Pubkey, pool_type: PoolType, current_tree: u32) -> Instruction {
+    let pool = pool_address(&pool_type);
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::OpenTree {
+            pool,
+            current_tree: merkle_tree_address(&pool, current_tree),
+            next_tree: merkle_tree_address(&pool, current_tree + 1),
+            next_root_history: root_history_address(&pool, current_tree + 1),
+            payer: *payer,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_mixer::instruction::OpenTree {},
+    )
+}
+
+pub fn grow_tree(payer: &Pubkey, pool_type: PoolType, tree_index: u32) -> Instruction {
+    let pool = pool_address(&pool_type);
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::GrowTree {
+            pool,
+            merkle_tree: merkle_tree_address(&pool, tree_index),
+            payer: *payer,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_mixer::instruction::GrowTree { tree_index },
+    )
+}
+
+pub fn set_pool_caps(
+    authority: &Pubkey,
+    pool_type: PoolType,
+    max_tvl: u64,
+    max_deposits_per_epoch: u64,
+) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::SetPoolConfig {
+            pool: pool_address(&pool_type),
+            authority: *authority,
+        },
+        rift_mixer::instruction::SetPoolCaps {
+            max_tvl,
+            max_deposits_per_epoch,
+        },
+    )
+}
+
+pub fn set_min_leaf_age(
+    authority: &Pubkey,
+    pool_type: PoolType,
+    min_leaf_age_slots: u64,
+    min_leaf_age_deposits: u64,
+) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::SetPoolConfig {
+            pool: pool_address(&pool_type),
+            authority: *authority,
+        },
+        rift_mixer::instruction::SetMinLeafAge {
+            min_leaf_age_slots,
+            min_leaf_age_deposits,
+        },
+    )
+}
+
+/// One `migrate_pool` step for the pool
+pub fn migrate_pool(authority: &Pubkey, pool_type: PoolType) -> Instruction {
+    let pool = pool_address(&pool_type);
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::MigratePool {
+            pool,
+            legacy_tree: legacy_tree_address(&pool_type),
+            merkle_tree: merkle_tree_address(&pool, 0),
+            root_history: root_history_address(&pool, 0),
+            deposit_activity: deposit_activity_address(&pool),
+            nullifier_registry: nullifier_registry_address(&pool),
+            authority: *authority,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_mixer::instruction::MigratePool {},
+    )
+}
+
+pub fn initialize_deny_list(
+    deployment_authority: &Pubkey,
+    payer: &Pubkey,
+    authority: Pubkey,
+) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::InitializeDenyList {
+            deny_list: deny_list_address(),
+            deployment_authority: *deployment_authority,
+            program_data: program_data_address(),
+            payer: *payer,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_mixer::instruction::InitializeDenyList { authority },
+    )
+}
+
+pub fn set_deny_list_authority(authority: &Pubkey, new_authority: Pubkey) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::SetDenyListAuthority {
+            deny_list: deny_list_address(),
+            authority: *authority,
+        },
+        rift_mixer::instruction::SetDenyListAuthority { new_authority },
+    )
+}
+
+pub fn deny_address(authority: &Pubkey, payer: &Pubkey, address: Pubkey) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::DenyAddress {
+            deny_list: deny_list_address(),
+            denied_address: denied_address(&address),
+            authority: *authority,
+            payer: *payer,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_mixer::instruction::DenyAddress { address },
+    )
+}
+
+pub fn allow_address(authority: &Pubkey, payer: &Pubkey, address: Pubkey) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::AllowAddress {
+            deny_list: deny_list_address(),
+            denied_address: denied_address(&address),
+            authority: *authority,
+            payer: *payer,
+        },
+        rift_mixer::instruction::AllowAddress { address },
+    )
+}
+
+pub fn withdraw_with_association(
+    pool_type: PoolType,
+    proof: ZkProof,
+    root: [u8; 32],
+    nullifier_hash: [u8; 32],
+    association_root: [u8; 32],
+    recipient: Pubkey,
+    fee: u64,
+) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::WithdrawWithAssociation {
+            withdraw: withdraw_accounts(&pool_type, 0, recipient, None),
+            association_registry: association_registry_address(&pool_address(&pool_type)),
+        },
+        rift_mixer::instruction::WithdrawWithAssociation {
+            tree_index: 0,
+            proof,
+            association_root,
+            intent: withdraw_intent(pool_type, root, nullifier_hash, recipient, None, fee),
+        },
+    )
+}
+
+pub fn initialize_association_registry(
+    authority: &Pubkey,
+    pool_type: PoolType,
+    curator: Pubkey,
+) -> Instruction {
+    let pool = pool_address(&pool_type);
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::InitializeAssociationRegistry {
+            pool,
+            association_registry: association_registry_address(&pool),
+            authority: *authority,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_mixer::instruction::InitializeAssociationRegistry { curator },
+    )
+}
+
+pub fn publish_association_root(
+    curator: &Pubkey,
+    pool_type: PoolType,
+    association_root: [u8; 32],
+) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::PublishAssociationRoot {
+            association_registry: association_registry_address(&pool_address(&pool_type)),
+            curator: *curator,
+        },
+        rift_mixer::instruction::PublishAssociationRoot { association_root },
+    )
+}
+
+/// A proof that cannot verify against anything
+pub fn garbage_proof() -> ZkProof {
+    ZkProof {
+        a: [0u8; 64],
+        b: [0u8; 128],
+        c: [0u8; 64],
+    }
+}
+
+/// `get_pool_stats` for a pool whose current tree is `current_tree`
+pub fn get_pool_stats(pool_type: PoolType, current_tree: u32) -> Instruction {
+    let pool = pool_address(&pool_type);
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::GetPoolStats {
+            pool,
+            root_history: root_history_address(&pool, current_tree),
+            deposit_activity: deposit_activity_address(&pool),
+            pool_vault: vault_address(&pool),
+        },
+        rift_mixer::instruction::GetPoolStats {},
+    )
+}
+
+/// A distinct, non-zero 32-byte value for filler commitments and nullifiers
+pub fn numbered(n: usize) -> [u8; 32] {
+    let mut bytes = [0xffu8; 32];
+    bytes[..8].copy_from_slice(&(n as u64).to_le_bytes());
+    bytes
+}
@@ -0,0 +1,310 @@
+// Shared harness for the in-process integration tests.
+//
+// All three programs run as native builtins inside `solana-program-test`, so
+// no `anchor build` is needed before `cargo test`.
+
+#![allow(dead_code)]
+
+pub mod governance;
+pub mod legacy;
+pub mod mixer;
+pub mod shielded;
+pub mod staking;
+
+use std::sync::Once;
+
+use anchor_lang::prelude::{AccountInfo, Pubkey};
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_stubs::{self, SyscallStubs};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use anchor_lang::{AccountDeserialize, AccountSerialize, InstructionData, ToAccountMetas};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::{Transaction, TransactionError};
+
+// Anchor's generated `entry` ties the account slice and the `AccountInfo`
+// lifetimes together, which the builtin processor signature does not. Leaking
+// a copy of the slice is harmless for the lifetime of a test.
+macro_rules! anchor_processor {
+    ($entry:path) => {{
+        fn entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+            let accounts = Box::leak(Box::new(accounts.to_vec()));
+            $entry(program_id, accounts, data)
+        }
+        processor!(entry)
+    }};
+}
+
+pub fn program_test() -> ProgramTest {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "rift_mixer",
+        rift_mixer::ID,
+        anchor_processor!(rift_mixer::entry),
+    );
+    program_test.add_program(
+        "rift_staking",
+        rift_staking::ID,
+        anchor_processor!(rift_staking::entry),
+    );
+    program_test.add_program(
+        "rift_governance",
+        rift_governance::ID,
+        anchor_processor!(rift_governance::entry),
+    );
+    program_test
+}
+
+pub fn instruction(
+    program_id: Pubkey,
+    accounts: impl ToAccountMetas,
+    data: impl InstructionData,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Send `instructions` paid for by the context payer, plus any extra signers
+pub async fn send(
+    ctx: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let blockhash = ctx.get_new_latest_blockhash().await?;
+    let mut all_signers: Vec<&Keypair> = vec![&ctx.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&ctx.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    ctx.banks_client.process_transaction(transaction).await
+}
+
+// Builtins log `sol_log_data` (and so `emit!`) to stdout rather than to the
+// transaction. This routes it through `sol_log` instead, under a marker that
+// `send_with_logs` turns back into the "Program data: " line a validator
+// would have written. Everything else goes to program-test's own stubs.
+const PROGRAM_DATA_MARKER: &str = "Program log: \u{0}data: ";
+
+struct ProgramDataStubs(Box<dyn SyscallStubs>);
+
+impl SyscallStubs for ProgramDataStubs {
+    fn sol_log(&self, message: &str) {
+        self.0.sol_log(message)
+    }
+    fn sol_log_compute_units(&self) {
+        self.0.sol_log_compute_units()
+    }
+    fn sol_remaining_compute_units(&self) -> u64 {
+        self.0.sol_remaining_compute_units()
+    }
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        self.0.sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_clock_sysvar(var_addr)
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_schedule_sysvar(var_addr)
+    }
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_fees_sysvar(var_addr)
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_rent_sysvar(var_addr)
+    }
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_rewards_sysvar(var_addr)
+    }
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_last_restart_slot(var_addr)
+    }
+    unsafe fn sol_memcpy(&self, dst: *mut u8, src: *const u8, n: usize) {
+        self.0.sol_memcpy(dst, src, n)
+    }
+    unsafe fn sol_memmove(&self, dst: *mut u8, src: *const u8, n: usize) {
+        self.0.sol_memmove(dst, src, n)
+    }
+    unsafe fn sol_memcmp(&self, s1: *const u8, s2: *const u8, n: usize, result: *mut i32) {
+        self.0.sol_memcmp(s1, s2, n, result)
+    }
+    unsafe fn sol_memset(&self, s: *mut u8, c: u8, n: usize) {
+        self.0.sol_memset(s, c, n)
+    }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.0.sol_get_return_data()
+    }
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.0.sol_set_return_data(data)
+    }
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let fields: Vec<String> = fields.iter().map(|field| STANDARD.encode(field)).collect();
+        self.0.sol_log(&format!("\u{0}data: {}", fields.join(" ")));
+    }
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        self.0.sol_get_processed_sibling_instruction(index)
+    }
+    fn sol_get_stack_height(&self) -> u64 {
+        self.0.sol_get_stack_height()
+    }
+}
+
+struct PlaceholderStubs;
+
+impl SyscallStubs for PlaceholderStubs {}
+
+/// Install `ProgramDataStubs` over program-test's stubs. Those are only set
+/// once a bank has been started, so this is called with a context in hand.
+fn capture_program_data() {
+    static ONCE: Once = Once::new();
+    ONCE.call_once(|| {
+        let stubs = program_stubs::set_syscall_stubs(Box::new(PlaceholderStubs));
+        program_stubs::set_syscall_stubs(Box::new(ProgramDataStubs(stubs)));
+    });
+}
+
+/// `send`, also returning the transaction's log messages (where events are)
+pub async fn send_with_logs(
+    ctx: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> (Result<(), BanksClientError>, Vec<String>) {
+    capture_program_data();
+    let blockhash = ctx.get_new_latest_blockhash().await.unwrap();
+    let mut all_signers: Vec<&Keypair> = vec![&ctx.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&ctx.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    let outcome = ctx
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    let logs = outcome
+        .metadata
+        .map(|metadata| metadata.log_messages)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|line| match line.strip_prefix(PROGRAM_DATA_MARKER) {
+            Some(data) => format!("Program data: {}", data),
+            None => line,
+        })
+        .collect();
+    (outcome.result.map_err(BanksClientError::TransactionError), logs)
+}
+
+/// A fresh keypair holding `lamports`
+pub async fn funded_keypair(ctx: &mut ProgramTestContext, lamports: u64) -> Keypair {
+    let keypair = Keypair::new();
+    let transfer = system_instruction::transfer(&ctx.payer.pubkey(), &keypair.pubkey(), lamports);
+    send(ctx, &[transfer], &[]).await.unwrap();
+    keypair
+}
+
+pub async fn fetch<T: AccountDeserialize>(ctx: &mut ProgramTestContext, address: &Pubkey) -> T {
+    let account = ctx
+        .banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .unwrap_or_else(|| panic!("account {} does not exist", address));
+    T::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+pub async fn lamports(ctx: &mut ProgramTestContext, address: &Pubkey) -> u64 {
+    ctx.banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .map(|account| account.lamports)
+        .unwrap_or(0)
+}
+
+/// Pre-load a program-owned anchor account before the bank starts
+pub fn add_anchor_account<T: AccountSerialize>(
+    program_test: &mut ProgramTest,
+    address: Pubkey,
+    owner: Pubkey,
+    space: usize,
+    account: &T,
+) {
+    let mut data = Vec::with_capacity(space);
+    account.try_serialize(&mut data).unwrap();
+    data.resize(space, 0);
+    program_test.add_account(
+        address,
+        Account {
+            lamports: Rent::default().minimum_balance(space),
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+/// Pre-load an empty program-owned account (used for lamport vaults)
+pub fn add_program_owned_account(
+    program_test: &mut ProgramTest,
+    address: Pubkey,
+    owner: Pubkey,
+    lamports: u64,
+) {
+    program_test.add_account(
+        address,
+        Account {
+            lamports: Rent::default().minimum_balance(0) + lamports,
+            data: Vec::new(),
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+/// Custom program error code of a failed transaction, if any
+pub fn custom_error(result: Result<(), BanksClientError>) -> Option<u32> {
+    match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(code),
+        ))) => Some(code),
+        Err(BanksClientError::SimulationError {
+            err: TransactionError::InstructionError(_, InstructionError::Custom(code)),
+            ..
+        }) => Some(code),
+        _ => None,
+    }
+}
+
+pub fn assert_error(result: Result<(), BanksClientError>, expected: impl Into<u32>) {
+    let expected = expected.into();
+    let debug = format!("{:?}", result);
+    assert_eq!(
+        custom_error(result),
+        Some(expected),
+        "expected custom error {}, got {}",
+        expected,
+        debug
+    );
+}
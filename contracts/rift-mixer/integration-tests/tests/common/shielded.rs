@@ -0,0 +1,345 @@
+// rift_mixer shielded (join-split) pool addresses, instruction builders and
+// fixtures
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use rift_mixer::{
+    hash_ext_data, insert_leaf, zero_hashes, ExtData, MerkleTree, NullifierRegistry, RootEntry,
+    RootHistory, ShieldedPool, MERKLE_TREE_HEIGHT, ROOT_HISTORY_SIZE, SHIELDED_POOL_SEED,
+};
+use solana_program_test::ProgramTest;
+
+use super::mixer::{
+    denied_address, garbage_proof, merkle_tree_address, nullifier_registry_address,
+    program_data_address, root_history_address, vault_address,
+};
+use super::{add_anchor_account, add_program_owned_account, instruction};
+
+pub fn shielded_pool_address() -> Pubkey {
+    Pubkey::find_program_address(&[SHIELDED_POOL_SEED], &rift_mixer::ID).0
+}
+
+/// Pre-load a shielded pool as `initialize_shielded_pool` and earlier
+/// transactions would leave it, with `notes` already in the tree and
+/// `balance` lamports (above rent) in the vault. Deposit caps are lifted, as
+/// for fixed-pool fixtures.
+pub fn preload_shielded_pool(
+    program_test: &mut ProgramTest,
+    authority: Pubkey,
+    notes: &[[u8; 32]],
+    balance: u64,
+) {
+    preload(program_test, authority, notes, balance, MerkleTree::SIZE);
+}
+
+/// `preload_shielded_pool` whose tree 0 has no room left, so outputs need
+/// `open_shielded_tree` and `grow_shielded_tree` first
+pub fn preload_full_shielded_pool(
+    program_test: &mut ProgramTest,
+    authority: Pubkey,
+    notes: &[[u8; 32]],
+    balance: u64,
+) {
+    preload(program_test, authority, notes, balance, MerkleTree::space_for(notes.len()));
+}
+
+fn preload(
+    program_test: &mut ProgramTest,
+    authority: Pubkey,
+    notes: &[[u8; 32]],
+    balance: u64,
+    tree_space: usize,
+) {
+    let (pool, bump) = Pubkey::find_program_address(&[SHIELDED_POOL_SEED], &rift_mixer::ID);
+
+    let mut filled_subtrees = [[0u8; 32]; MERKLE_TREE_HEIGHT];
+    let mut merkle_root = zero_hashes(MERKLE_TREE_HEIGHT)[MERKLE_TREE_HEIGHT];
+    for (index, note) in notes.iter().enumerate() {
+        merkle_root = insert_leaf(&mut filled_subtrees, index, *note);
+    }
+
+    add_anchor_account(
+        program_test,
+        pool,
+        rift_mixer::ID,
+        8 + ShieldedPool::SIZE,
+        &ShieldedPool {
+            authority,
+            merkle_root,
+            next_index: notes.len() as u64,
+            total_transactions: 0,
+            bump,
+            max_tvl: u64::MAX,
+            max_deposits_per_epoch: u64::MAX,
+            current_epoch: 0,
+            epoch_deposits: 0,
+            min_leaf_age_slots: 0,
+            min_leaf_age_deposits: 0,
+            emergency: false,
+            retired: false,
+            current_tree: 0,
+            version: ShieldedPool::VERSION,
+        },
+    );
+    // The preloaded root counts as created at slot 0
+    let mut entries = [RootEntry::default(); ROOT_HISTORY_SIZE];
+    entries[0] = RootEntry {
+        root: merkle_root,
+        slot: 0,
+        leaf_count: notes.len() as u64,
+    };
+    add_anchor_account(
+        program_test,
+        root_history_address(&pool, 0),
+        rift_mixer::ID,
+        8 + RootHistory::SIZE,
+        &RootHistory {
+            entries,
+            current_index: 0,
+            version: RootHistory::VERSION,
+        },
+    );
+    add_anchor_account(
+        program_test,
+        merkle_tree_address(&pool, 0),
+        rift_mixer::ID,
+        8 + tree_space,
+        &MerkleTree {
+            leaves: notes.to_vec(),
+            filled_subtrees,
+            version: MerkleTree::VERSION,
+        },
+    );
+    add_program_owned_account(program_test, vault_address(&pool), rift_mixer::ID, balance);
+    add_anchor_account(
+        program_test,
+        nullifier_registry_address(&pool),
+        rift_mixer::ID,
+        8 + NullifierRegistry::SIZE,
+        &NullifierRegistry {
+            used_nullifiers: Vec::new(),
+            version: NullifierRegistry::VERSION,
+        },
+    );
+}
+
+pub fn initialize_shielded_pool(
+    deployment_authority: &Pubkey,
+    payer: &Pubkey,
+    authority: Pubkey,
+) -> Instruction {
+    let pool = shielded_pool_address();
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::InitializeShieldedPool {
+            shielded_pool: pool,
+            merkle_tree: merkle_tree_address(&pool, 0),
+            root_history: root_history_address(&pool, 0),
+            nullifier_registry: nullifier_registry_address(&pool),
+            pool_vault: vault_address(&pool),
+            deployment_authority: *deployment_authority,
+            program_data: program_data_address(),
+            payer: *payer,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_mixer::instruction::InitializeShieldedPool { authority },
+    )
+}
+
+/// `open_shielded_tree` for a pool whose current tree is `current_tree`
+pub fn open_shielded_tree(payer: &Pubkey, current_tree: u32) -> Instruction {
+    let pool = shielded_pool_address();
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::OpenShieldedTree {
+            shielded_pool: pool,
+            current_tree: merkle_tree_address(&pool, current_tree),
+            next_tree: merkle_tree_address(&pool, current_tree + 1),
+            next_root_history: root_history_address(&pool, current_tree + 1),
+            payer: *payer,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_mixer::instruction::OpenShieldedTree {},
+    )
+}
+
+pub fn grow_shielded_tree(payer: &Pubkey, tree_index: u32) -> Instruction {
+    let pool = shielded_pool_address();
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::GrowShieldedTree {
+            shielded_pool: pool,
+            merkle_tree: merkle_tree_address(&pool, tree_index),
+            payer: *payer,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_mixer::instruction::GrowShieldedTree { tree_index },
+    )
+}
+
+fn config(authority: &Pubkey) -> rift_mixer::accounts::SetShieldedPoolConfig {
+    rift_mixer::accounts::SetShieldedPoolConfig {
+        shielded_pool: shielded_pool_address(),
+        authority: *authority,
+    }
+}
+
+pub fn set_shielded_pool_caps(
+    authority: &Pubkey,
+    max_tvl: u64,
+    max_deposits_per_epoch: u64,
+) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        config(authority),
+        rift_mixer::instruction::SetShieldedPoolCaps {
+            max_tvl,
+            max_deposits_per_epoch,
+        },
+    )
+}
+
+pub fn set_shielded_min_leaf_age(
+    authority: &Pubkey,
+    min_leaf_age_slots: u64,
+    min_leaf_age_deposits: u64,
+) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        config(authority),
+        rift_mixer::instruction::SetShieldedMinLeafAge {
+            min_leaf_age_slots,
+            min_leaf_age_deposits,
+        },
+    )
+}
+
+pub fn enter_shielded_emergency_mode(authority: &Pubkey) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        config(authority),
+        rift_mixer::instruction::EnterShieldedEmergencyMode {},
+    )
+}
+
+pub fn retire_shielded_pool(authority: &Pubkey) -> Instruction {
+    instruction(
+        rift_mixer::ID,
+        config(authority),
+        rift_mixer::instruction::RetireShieldedPool {},
+    )
+}
+
+/// `ExtData` without encrypted outputs
+pub fn ext_data(recipient: Pubkey, ext_amount: i64, relayer: Pubkey, fee: u64) -> ExtData {
+    ExtData {
+        recipient,
+        ext_amount,
+        relayer,
+        fee,
+        encrypted_output1: Vec::new(),
+        encrypted_output2: Vec::new(),
+    }
+}
+
+/// `transact` on a pool with a single tree, with the public amount and hash
+/// that match `ext_data`
+pub fn transact(
+    sender: &Pubkey,
+    root: [u8; 32],
+    input_nullifiers: [[u8; 32]; 2],
+    output_commitments: [[u8; 32]; 2],
+    ext_data: ExtData,
+) -> Instruction {
+    transact_in_tree(sender, 0, 0, root, input_nullifiers, output_commitments, ext_data)
+}
+
+/// `transact` spending notes of tree `tree_index` in a pool whose current
+/// tree is `current_tree`
+pub fn transact_in_tree(
+    sender: &Pubkey,
+    tree_index: u32,
+    current_tree: u32,
+    root: [u8; 32],
+    input_nullifiers: [[u8; 32]; 2],
+    output_commitments: [[u8; 32]; 2],
+    ext_data: ExtData,
+) -> Instruction {
+    let public_amount = ext_data.ext_amount - ext_data.fee as i64;
+    let ext_data_hash = hash_ext_data(&ext_data);
+    transact_instruction(
+        sender,
+        (tree_index, current_tree),
+        root,
+        input_nullifiers,
+        output_commitments,
+        public_amount,
+        ext_data_hash,
+        ext_data,
+    )
+}
+
+/// `transact` with an explicit public amount and hash, for tests of their
+/// checks
+pub fn transact_with(
+    sender: &Pubkey,
+    root: [u8; 32],
+    input_nullifiers: [[u8; 32]; 2],
+    output_commitments: [[u8; 32]; 2],
+    public_amount: i64,
+    ext_data_hash: [u8; 32],
+    ext_data: ExtData,
+) -> Instruction {
+    transact_instruction(
+        sender,
+        (0, 0),
+        root,
+        input_nullifiers,
+        output_commitments,
+        public_amount,
+        ext_data_hash,
+        ext_data,
+    )
+}
+
+/// The `Transact` instruction, proving against tree `tree_index` with the
+/// outputs going into `current_tree`
+#[allow(clippy::too_many_arguments)]
+fn transact_instruction(
+    sender: &Pubkey,
+    (tree_index, current_tree): (u32, u32),
+    root: [u8; 32],
+    input_nullifiers: [[u8; 32]; 2],
+    output_commitments: [[u8; 32]; 2],
+    public_amount: i64,
+    ext_data_hash: [u8; 32],
+    ext_data: ExtData,
+) -> Instruction {
+    let pool = shielded_pool_address();
+    instruction(
+        rift_mixer::ID,
+        rift_mixer::accounts::Transact {
+            shielded_pool: pool,
+            merkle_tree: merkle_tree_address(&pool, current_tree),
+            root_history: root_history_address(&pool, current_tree),
+            proven_root_history: root_history_address(&pool, tree_index),
+            nullifier_registry: nullifier_registry_address(&pool),
+            pool_vault: vault_address(&pool),
+            sender: *sender,
+            denied_sender: denied_address(sender),
+            recipient: ext_data.recipient,
+            relayer: ext_data.relayer,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_mixer::instruction::Transact {
+            tree_index,
+            proof: garbage_proof(),
+            root,
+            input_nullifiers,
+            output_commitments,
+            public_amount,
+            ext_data_hash,
+            ext_data,
+        },
+    )
+}
@@ -0,0 +1,143 @@
+// rift_staking addresses and instruction builders
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use rift_staking::{POOL_SEED, RELAYER_SEED, STAKE_SEED};
+
+use super::instruction;
+
+pub fn staking_pool_address() -> Pubkey {
+    Pubkey::find_program_address(&[POOL_SEED], &rift_staking::ID).0
+}
+
+pub fn vault_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"vault"], &rift_staking::ID).0
+}
+
+pub fn stake_address(staker: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[STAKE_SEED, staker.as_ref()], &rift_staking::ID).0
+}
+
+pub fn relayer_address(owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[RELAYER_SEED, owner.as_ref()], &rift_staking::ID).0
+}
+
+pub fn initialize(authority: &Pubkey) -> Instruction {
+    instruction(
+        rift_staking::ID,
+        rift_staking::accounts::Initialize {
+            staking_pool: staking_pool_address(),
+            pool_vault: vault_address(),
+            authority: *authority,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_staking::instruction::Initialize {},
+    )
+}
+
+pub fn stake(staker: &Pubkey, amount: u64) -> Instruction {
+    instruction(
+        rift_staking::ID,
+        rift_staking::accounts::Stake {
+            staking_pool: staking_pool_address(),
+            stake_account: stake_address(staker),
+            pool_vault: vault_address(),
+            staker: *staker,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_staking::instruction::Stake { amount },
+    )
+}
+
+pub fn claim_rewards(staker: &Pubkey) -> Instruction {
+    instruction(
+        rift_staking::ID,
+        rift_staking::accounts::ClaimRewards {
+            staking_pool: staking_pool_address(),
+            stake_account: stake_address(staker),
+            pool_vault: vault_address(),
+            staker: *staker,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_staking::instruction::ClaimRewards {},
+    )
+}
+
+pub fn unstake(staker: &Pubkey) -> Instruction {
+    instruction(
+        rift_staking::ID,
+        rift_staking::accounts::Unstake {
+            staking_pool: staking_pool_address(),
+            stake_account: stake_address(staker),
+            pool_vault: vault_address(),
+            staker: *staker,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_staking::instruction::Unstake {},
+    )
+}
+
+pub fn register_relayer(staker: &Pubkey, fee: u64) -> Instruction {
+    instruction(
+        rift_staking::ID,
+        rift_staking::accounts::RegisterRelayer {
+            stake_account: stake_address(staker),
+            relayer_account: relayer_address(staker),
+            staker: *staker,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_staking::instruction::RegisterRelayer { fee },
+    )
+}
+
+pub fn slash_relayer(authority: &Pubkey, relayer: &Pubkey, treasury: &Pubkey) -> Instruction {
+    instruction(
+        rift_staking::ID,
+        rift_staking::accounts::SlashRelayer {
+            staking_pool: staking_pool_address(),
+            stake_account: stake_address(relayer),
+            relayer_account: relayer_address(relayer),
+            pool_vault: vault_address(),
+            treasury: *treasury,
+            authority: *authority,
+        },
+        rift_staking::instruction::SlashRelayer {
+            reason: "misbehaviour".to_string(),
+        },
+    )
+}
+
+pub fn record_relay(mixer_authority: &Pubkey, relayer: &Pubkey, success: bool) -> Instruction {
+    instruction(
+        rift_staking::ID,
+        rift_staking::accounts::RecordRelay {
+            relayer_account: relayer_address(relayer),
+            mixer_authority: *mixer_authority,
+        },
+        rift_staking::instruction::RecordRelay { success },
+    )
+}
+
+pub fn deposit_fees(depositor: &Pubkey, amount: u64) -> Instruction {
+    instruction(
+        rift_staking::ID,
+        rift_staking::accounts::DepositFees {
+            staking_pool: staking_pool_address(),
+            pool_vault: vault_address(),
+            depositor: *depositor,
+            system_program: anchor_lang::system_program::ID,
+        },
+        rift_staking::instruction::DepositFees { amount },
+    )
+}
+
+pub fn get_stake_info(staker: &Pubkey) -> Instruction {
+    instruction(
+        rift_staking::ID,
+        rift_staking::accounts::GetStakeInfo {
+            stake_account: stake_address(staker),
+            staker: *staker,
+        },
+        rift_staking::instruction::GetStakeInfo {},
+    )
+}
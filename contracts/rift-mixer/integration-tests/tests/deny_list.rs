@@ -0,0 +1,159 @@
+// Deposit screening against the per-address deny list
+
+mod common;
+
+use common::mixer::{self, deny_list_address, pool_address};
+use common::{assert_error, fetch, funded_keypair, program_test, send};
+use rift_mixer::{DenyList, MixerError, Pool, PoolType};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SOL: u64 = 1_000_000_000;
+
+/// A pool plus a deny list managed by the returned authority
+async fn start() -> (ProgramTestContext, Keypair) {
+    let deployer = Keypair::new();
+    let mut program_test = program_test();
+    mixer::preload_pool(&mut program_test, PoolType::Sol1, Pubkey::new_unique());
+    mixer::preload_program_data(&mut program_test, deployer.pubkey());
+    let mut ctx = program_test.start_with_context().await;
+    let authority = Keypair::new();
+    let payer = ctx.payer.pubkey();
+    send(
+        &mut ctx,
+        &[mixer::initialize_deny_list(&deployer.pubkey(), &payer, authority.pubkey())],
+        &[&deployer],
+    )
+    .await
+    .unwrap();
+    (ctx, authority)
+}
+
+#[tokio::test]
+async fn only_the_deployment_authority_creates_the_deny_list() {
+    let mut program_test = program_test();
+    mixer::preload_program_data(&mut program_test, Pubkey::new_unique());
+    let mut ctx = program_test.start_with_context().await;
+    let stranger = Keypair::new();
+    let payer = ctx.payer.pubkey();
+
+    let result = send(
+        &mut ctx,
+        &[mixer::initialize_deny_list(&stranger.pubkey(), &payer, stranger.pubkey())],
+        &[&stranger],
+    )
+    .await;
+    assert_error(result, MixerError::Unauthorized);
+    let deny_list = ctx.banks_client.get_account(deny_list_address()).await.unwrap();
+    assert!(deny_list.is_none());
+}
+
+#[tokio::test]
+async fn listed_depositor_is_blocked_until_allowed() {
+    let (mut ctx, authority) = start().await;
+    let payer = ctx.payer.pubkey();
+    let sanctioned = funded_keypair(&mut ctx, 5 * SOL).await;
+    let other = funded_keypair(&mut ctx, 5 * SOL).await;
+
+    send(
+        &mut ctx,
+        &[mixer::deny_address(&authority.pubkey(), &payer, sanctioned.pubkey())],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    let deny_list: DenyList = fetch(&mut ctx, &deny_list_address()).await;
+    assert_eq!(deny_list.entry_count, 1);
+
+    let blocked = send(
+        &mut ctx,
+        &[mixer::deposit(&sanctioned.pubkey(), PoolType::Sol1, [1u8; 32])],
+        &[&sanctioned],
+    )
+    .await;
+    assert_error(blocked, MixerError::DepositorBlocked);
+    send(
+        &mut ctx,
+        &[mixer::deposit(&other.pubkey(), PoolType::Sol1, [2u8; 32])],
+        &[&other],
+    )
+    .await
+    .unwrap();
+
+    send(
+        &mut ctx,
+        &[mixer::allow_address(&authority.pubkey(), &payer, sanctioned.pubkey())],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    send(
+        &mut ctx,
+        &[mixer::deposit(&sanctioned.pubkey(), PoolType::Sol1, [1u8; 32])],
+        &[&sanctioned],
+    )
+    .await
+    .unwrap();
+
+    let deny_list: DenyList = fetch(&mut ctx, &deny_list_address()).await;
+    assert_eq!(deny_list.entry_count, 0);
+    let pool: Pool = fetch(&mut ctx, &pool_address(&PoolType::Sol1)).await;
+    assert_eq!(pool.total_deposits, 2);
+}
+
+#[tokio::test]
+async fn only_the_authority_manages_the_list() {
+    let (mut ctx, authority) = start().await;
+    let payer = ctx.payer.pubkey();
+    let intruder = funded_keypair(&mut ctx, SOL).await;
+    let target = Pubkey::new_unique();
+
+    let denied = send(
+        &mut ctx,
+        &[mixer::deny_address(&intruder.pubkey(), &payer, target)],
+        &[&intruder],
+    )
+    .await;
+    assert_error(denied, MixerError::Unauthorized);
+
+    // Handing the list to governance moves the power with it
+    let governance = Keypair::new();
+    send(
+        &mut ctx,
+        &[mixer::set_deny_list_authority(&authority.pubkey(), governance.pubkey())],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    let stale = send(
+        &mut ctx,
+        &[mixer::deny_address(&authority.pubkey(), &payer, target)],
+        &[&authority],
+    )
+    .await;
+    assert_error(stale, MixerError::Unauthorized);
+    send(
+        &mut ctx,
+        &[mixer::deny_address(&governance.pubkey(), &payer, target)],
+        &[&governance],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn deposits_are_open_without_a_deny_list() {
+    let mut program_test = program_test();
+    mixer::preload_pool(&mut program_test, PoolType::Sol1, Pubkey::new_unique());
+    let mut ctx = program_test.start_with_context().await;
+    let depositor = funded_keypair(&mut ctx, 2 * SOL).await;
+
+    send(
+        &mut ctx,
+        &[mixer::deposit(&depositor.pubkey(), PoolType::Sol1, [1u8; 32])],
+        &[&depositor],
+    )
+    .await
+    .unwrap();
+}
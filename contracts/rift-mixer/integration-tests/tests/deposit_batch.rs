@@ -0,0 +1,124 @@
+// Several deposits in one instruction
+
+mod common;
+
+use common::mixer::{self, merkle_tree_address, pool_address, root_history_address, vault_address};
+use common::{assert_error, fetch, funded_keypair, lamports, program_test, send, send_with_logs};
+use rift_mixer::{
+    compute_merkle_root, MerkleTree, MixerError, Pool, PoolType, RootHistory, DEPOSIT_0_1_SOL,
+    MAX_DEPOSIT_BATCH, MERKLE_TREE_HEIGHT,
+};
+use rift_sdk::events::deposit_events;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SOL: u64 = 1_000_000_000;
+const POOL: PoolType = PoolType::Sol0_1;
+
+async fn start(authority: Pubkey) -> (ProgramTestContext, Keypair) {
+    let mut program_test = program_test();
+    mixer::preload_pool(&mut program_test, POOL, authority);
+    let mut ctx = program_test.start_with_context().await;
+    let depositor = funded_keypair(&mut ctx, 5 * SOL).await;
+    (ctx, depositor)
+}
+
+fn commitments(count: usize) -> Vec<[u8; 32]> {
+    (0..count).map(mixer::numbered).collect()
+}
+
+#[tokio::test]
+async fn batch_inserts_every_leaf_under_one_root() {
+    let (mut ctx, depositor) = start(Pubkey::new_unique()).await;
+    let vault = vault_address(&pool_address(&POOL));
+    let vault_before = lamports(&mut ctx, &vault).await;
+    let batch = commitments(3);
+
+    let (result, logs) = send_with_logs(
+        &mut ctx,
+        &[mixer::deposit_batch(&depositor.pubkey(), POOL, batch.clone())],
+        &[&depositor],
+    )
+    .await;
+    result.unwrap();
+
+    let pool: Pool = fetch(&mut ctx, &pool_address(&POOL)).await;
+    assert_eq!((pool.next_index, pool.total_deposits), (3, 3));
+    assert_eq!(pool.merkle_root, compute_merkle_root(&batch, MERKLE_TREE_HEIGHT));
+    let tree: MerkleTree = fetch(&mut ctx, &merkle_tree_address(&pool_address(&POOL), 0)).await;
+    assert_eq!(tree.leaves, batch);
+    assert_eq!(lamports(&mut ctx, &vault).await - vault_before, 3 * DEPOSIT_0_1_SOL);
+
+    let events = deposit_events(&logs);
+    let leaves: Vec<_> = events.iter().map(|e| (e.leaf_index, e.commitment)).collect();
+    assert_eq!(leaves, vec![(0, batch[0]), (1, batch[1]), (2, batch[2])]);
+
+    // Only the batch's final root is recorded
+    let history: RootHistory =
+        fetch(&mut ctx, &root_history_address(&pool_address(&POOL), 0)).await;
+    assert!(history.find(&pool.merkle_root).is_some());
+    let partial = compute_merkle_root(&batch[..1], MERKLE_TREE_HEIGHT);
+    assert!(history.find(&partial).is_none());
+}
+
+#[tokio::test]
+async fn batch_size_is_bounded() {
+    let (mut ctx, depositor) = start(Pubkey::new_unique()).await;
+    let batch = |count| mixer::deposit_batch(&depositor.pubkey(), POOL, commitments(count));
+
+    let empty = send(&mut ctx, &[batch(0)], &[&depositor]).await;
+    assert_error(empty, MixerError::InvalidBatchSize);
+    let over = send(&mut ctx, &[batch(MAX_DEPOSIT_BATCH + 1)], &[&depositor]).await;
+    assert_error(over, MixerError::InvalidBatchSize);
+
+    send(&mut ctx, &[batch(MAX_DEPOSIT_BATCH)], &[&depositor])
+        .await
+        .unwrap();
+    let pool: Pool = fetch(&mut ctx, &pool_address(&POOL)).await;
+    assert_eq!(pool.total_deposits, MAX_DEPOSIT_BATCH as u64);
+}
+
+#[tokio::test]
+async fn repeated_commitment_fails_the_whole_batch() {
+    let (mut ctx, depositor) = start(Pubkey::new_unique()).await;
+    let repeated = vec![[1u8; 32], [2u8; 32], [1u8; 32]];
+
+    let result = send(
+        &mut ctx,
+        &[mixer::deposit_batch(&depositor.pubkey(), POOL, repeated)],
+        &[&depositor],
+    )
+    .await;
+    assert_error(result, MixerError::CommitmentAlreadyUsed);
+    let pool: Pool = fetch(&mut ctx, &pool_address(&POOL)).await;
+    assert_eq!(pool.total_deposits, 0);
+}
+
+#[tokio::test]
+async fn caps_count_every_deposit_in_the_batch() {
+    let authority = Keypair::new();
+    let (mut ctx, depositor) = start(authority.pubkey()).await;
+    send(
+        &mut ctx,
+        &[mixer::set_pool_caps(&authority.pubkey(), POOL, u64::MAX, 2)],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+
+    let result = send(
+        &mut ctx,
+        &[mixer::deposit_batch(&depositor.pubkey(), POOL, commitments(3))],
+        &[&depositor],
+    )
+    .await;
+    assert_error(result, MixerError::EpochDepositCapExceeded);
+    send(
+        &mut ctx,
+        &[mixer::deposit_batch(&depositor.pubkey(), POOL, commitments(2))],
+        &[&depositor],
+    )
+    .await
+    .unwrap();
+}
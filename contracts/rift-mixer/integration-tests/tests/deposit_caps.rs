@@ -0,0 +1,128 @@
+// Per-pool TVL and per-epoch deposit caps
+
+mod common;
+
+use common::mixer::{self, garbage_proof, pool_address};
+use common::{assert_error, fetch, funded_keypair, program_test, send};
+use rift_mixer::{MixerError, Pool, PoolType, DEPOSIT_1_SOL, DEPOSIT_EPOCH_SECONDS};
+use rift_test_utils::clock::{now, warp_to};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SOL: u64 = 1_000_000_000;
+
+/// A pool whose caps are set to the given values by its authority, plus a
+/// funded depositor
+async fn capped(max_tvl: u64, max_deposits_per_epoch: u64) -> (ProgramTestContext, Keypair) {
+    let authority = Keypair::new();
+    let mut program_test = program_test();
+    mixer::preload_pool(&mut program_test, PoolType::Sol1, authority.pubkey());
+    let mut ctx = program_test.start_with_context().await;
+    send(
+        &mut ctx,
+        &[mixer::set_pool_caps(
+            &authority.pubkey(),
+            PoolType::Sol1,
+            max_tvl,
+            max_deposits_per_epoch,
+        )],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    let depositor = funded_keypair(&mut ctx, 10 * SOL).await;
+    (ctx, depositor)
+}
+
+async fn deposit(
+    ctx: &mut ProgramTestContext,
+    depositor: &Keypair,
+    n: usize,
+) -> Result<(), solana_program_test::BanksClientError> {
+    send(
+        ctx,
+        &[mixer::deposit(&depositor.pubkey(), PoolType::Sol1, mixer::numbered(n))],
+        &[depositor],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn tvl_cap_counts_outstanding_deposits() {
+    let (mut ctx, depositor) = capped(2 * DEPOSIT_1_SOL, u64::MAX).await;
+    deposit(&mut ctx, &depositor, 0).await.unwrap();
+    deposit(&mut ctx, &depositor, 1).await.unwrap();
+
+    let over = deposit(&mut ctx, &depositor, 2).await;
+    assert_error(over, MixerError::TvlCapExceeded);
+
+    // A withdrawal frees room under the cap
+    let root = mixer::current_root(&mut ctx, &PoolType::Sol1).await;
+    send(
+        &mut ctx,
+        &[mixer::withdraw(
+            PoolType::Sol1,
+            garbage_proof(),
+            root,
+            [9u8; 32],
+            Pubkey::new_unique(),
+            None,
+            0,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    deposit(&mut ctx, &depositor, 2).await.unwrap();
+}
+
+#[tokio::test]
+async fn epoch_cap_resets_at_the_next_epoch() {
+    let (mut ctx, depositor) = capped(u64::MAX, 2).await;
+    // Start mid-epoch so the first deposits share it
+    let epoch_start = now(&mut ctx).await / DEPOSIT_EPOCH_SECONDS * DEPOSIT_EPOCH_SECONDS;
+    warp_to(&mut ctx, epoch_start + DEPOSIT_EPOCH_SECONDS + 10).await;
+
+    deposit(&mut ctx, &depositor, 0).await.unwrap();
+    deposit(&mut ctx, &depositor, 1).await.unwrap();
+    let over = deposit(&mut ctx, &depositor, 2).await;
+    assert_error(over, MixerError::EpochDepositCapExceeded);
+
+    warp_to(&mut ctx, epoch_start + 2 * DEPOSIT_EPOCH_SECONDS - 1).await;
+    let still_over = deposit(&mut ctx, &depositor, 2).await;
+    assert_error(still_over, MixerError::EpochDepositCapExceeded);
+
+    warp_to(&mut ctx, epoch_start + 2 * DEPOSIT_EPOCH_SECONDS).await;
+    deposit(&mut ctx, &depositor, 2).await.unwrap();
+    let pool: Pool = fetch(&mut ctx, &pool_address(&PoolType::Sol1)).await;
+    assert_eq!(pool.epoch_deposits, 1);
+    assert_eq!(pool.total_deposits, 3);
+}
+
+#[tokio::test]
+async fn only_the_pool_authority_sets_caps() {
+    let (mut ctx, depositor) = capped(u64::MAX, u64::MAX).await;
+
+    let result = send(
+        &mut ctx,
+        &[mixer::set_pool_caps(&depositor.pubkey(), PoolType::Sol1, 0, 0)],
+        &[&depositor],
+    )
+    .await;
+    assert_error(result, MixerError::Unauthorized);
+}
+
+#[tokio::test]
+async fn more_withdrawals_than_deposits_count_as_empty() {
+    // The TVL check reads outstanding deposits the way `get_pool_stats`
+    // does, saturating at zero rather than failing
+    let mut program_test = program_test();
+    mixer::preload_pool_with_state(&mut program_test, PoolType::Sol1, Pubkey::new_unique(), 0, 1);
+    let mut ctx = program_test.start_with_context().await;
+    let depositor = funded_keypair(&mut ctx, 2 * SOL).await;
+
+    deposit(&mut ctx, &depositor, 0).await.unwrap();
+    let pool: Pool = fetch(&mut ctx, &pool_address(&PoolType::Sol1)).await;
+    assert_eq!((pool.total_deposits, pool.total_withdrawals), (1, 1));
+}
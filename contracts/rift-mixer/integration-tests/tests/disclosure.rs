@@ -0,0 +1,70 @@
+// Selective disclosure of one deposit-to-withdrawal link, from real events
+
+mod common;
+
+use common::mixer::{self, garbage_proof};
+use common::{funded_keypair, program_test, send_with_logs};
+use rift_mixer::PoolType;
+use rift_sdk::events::{deposit_events, withdraw_events};
+use rift_sdk::{Disclosure, Note};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+
+const SOL: u64 = 1_000_000_000;
+
+#[tokio::test]
+async fn disclosure_links_deposit_to_withdrawal() {
+    let mut program_test = program_test();
+    mixer::preload_pool(&mut program_test, PoolType::Sol1, Pubkey::new_unique());
+    let mut ctx = program_test.start_with_context().await;
+    let depositor = funded_keypair(&mut ctx, 5 * SOL).await;
+
+    // Someone else's deposit first, so the disclosed note is not at leaf 0
+    let (result, _) = send_with_logs(
+        &mut ctx,
+        &[mixer::deposit(&depositor.pubkey(), PoolType::Sol1, [5u8; 32])],
+        &[&depositor],
+    )
+    .await;
+    result.unwrap();
+
+    let note = Note::random(PoolType::Sol1);
+    let (result, deposit_logs) = send_with_logs(
+        &mut ctx,
+        &[mixer::deposit(&depositor.pubkey(), PoolType::Sol1, note.commitment())],
+        &[&depositor],
+    )
+    .await;
+    result.unwrap();
+
+    let recipient = Keypair::new();
+    let root = mixer::current_root(&mut ctx, &PoolType::Sol1).await;
+    let withdraw = mixer::withdraw(
+        PoolType::Sol1,
+        garbage_proof(),
+        root,
+        note.nullifier_hash(),
+        recipient.pubkey(),
+        None,
+        0,
+    );
+    let (result, withdraw_logs) = send_with_logs(&mut ctx, &[withdraw], &[]).await;
+    result.unwrap();
+
+    let deposit = &deposit_events(&deposit_logs)[0];
+    let withdrawal = &withdraw_events(&withdraw_logs)[0];
+    let disclosure = Disclosure::new(
+        note,
+        Signature::new_unique(),
+        deposit,
+        Signature::new_unique(),
+        withdrawal,
+        &recipient,
+    )
+    .unwrap();
+    assert_eq!((disclosure.tree_index, disclosure.leaf_index), (0, 1));
+
+    let received = Disclosure::from_json(&disclosure.to_json()).unwrap();
+    received.verify(deposit, withdrawal).unwrap();
+    received.verify_logs(&deposit_logs, &withdraw_logs).unwrap();
+}
@@ -0,0 +1,197 @@
+// Emergency mode: depositors reclaim funds by committing to, then revealing,
+// their notes
+
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use common::mixer::{self, nullifier_registry_address, pool_address, vault_address};
+use common::{assert_error, fetch, funded_keypair, lamports, program_test, send, send_with_logs};
+use rift_mixer::{
+    EmergencyWithdrawEvent, MixerError, NullifierRegistry, Pool, PoolType, DEPOSIT_1_SOL,
+    EMERGENCY_CLAIM_DELAY_SLOTS,
+};
+use rift_sdk::events::parse_events;
+use rift_sdk::Note;
+use rift_test_utils::clock::warp_slots;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SOL: u64 = 1_000_000_000;
+const POOL: PoolType = PoolType::Sol1;
+
+/// A pool holding `note`, plus its authority and the program's upgrade
+/// authority
+async fn start(note: &Note) -> (ProgramTestContext, Keypair, Keypair) {
+    let authority = Keypair::new();
+    let deployer = Keypair::new();
+    let mut program_test = program_test();
+    mixer::preload_pool(&mut program_test, POOL, authority.pubkey());
+    mixer::preload_program_data(&mut program_test, deployer.pubkey());
+    let mut ctx = program_test.start_with_context().await;
+    let depositor = funded_keypair(&mut ctx, 2 * SOL).await;
+    send(
+        &mut ctx,
+        &[mixer::deposit(&depositor.pubkey(), POOL, note.commitment())],
+        &[&depositor],
+    )
+    .await
+    .unwrap();
+    (ctx, authority, deployer)
+}
+
+async fn enter_emergency(ctx: &mut ProgramTestContext, deployer: &Keypair) {
+    let enter = mixer::enter_emergency_mode(&deployer.pubkey(), POOL);
+    send(ctx, &[enter], &[deployer]).await.unwrap();
+}
+
+/// Commit `claimant` to withdrawing `note` to `recipient`
+async fn commit(
+    ctx: &mut ProgramTestContext,
+    claimant: &Keypair,
+    note: &Note,
+    recipient: Pubkey,
+) {
+    let commit = mixer::commit_emergency_withdraw(
+        &claimant.pubkey(),
+        POOL,
+        note.nullifier,
+        note.secret,
+        recipient,
+    );
+    send(ctx, &[commit], &[claimant]).await.unwrap();
+}
+
+fn reveal(note: &Note, recipient: Pubkey, claimant: &Keypair) -> Instruction {
+    mixer::emergency_withdraw(POOL, note.nullifier, note.secret, recipient, claimant.pubkey())
+}
+
+#[tokio::test]
+async fn revealed_note_reclaims_its_deposit() {
+    let note = Note::random(POOL);
+    let (mut ctx, _authority, deployer) = start(&note).await;
+    enter_emergency(&mut ctx, &deployer).await;
+    let vault = vault_address(&pool_address(&POOL));
+    let vault_before = lamports(&mut ctx, &vault).await;
+    let claimant = funded_keypair(&mut ctx, SOL).await;
+    let recipient = Pubkey::new_unique();
+
+    commit(&mut ctx, &claimant, &note, recipient).await;
+    let committed = lamports(&mut ctx, &claimant.pubkey()).await;
+    warp_slots(&mut ctx, EMERGENCY_CLAIM_DELAY_SLOTS).await;
+    let (result, logs) = send_with_logs(&mut ctx, &[reveal(&note, recipient, &claimant)], &[]).await;
+    result.unwrap();
+
+    assert_eq!(lamports(&mut ctx, &recipient).await, DEPOSIT_1_SOL);
+    assert_eq!(vault_before - lamports(&mut ctx, &vault).await, DEPOSIT_1_SOL);
+    // The claim's rent goes back to the claimant
+    assert!(lamports(&mut ctx, &claimant.pubkey()).await > committed);
+    let pool: Pool = fetch(&mut ctx, &pool_address(&POOL)).await;
+    assert_eq!(pool.total_withdrawals, 1);
+    let registry: NullifierRegistry =
+        fetch(&mut ctx, &nullifier_registry_address(&pool_address(&POOL))).await;
+    assert_eq!(registry.used_nullifiers, vec![note.nullifier_hash()]);
+    let events: Vec<EmergencyWithdrawEvent> = parse_events(&logs);
+    assert_eq!((events[0].commitment, events[0].recipient), (note.commitment(), recipient));
+
+    commit(&mut ctx, &claimant, &note, recipient).await;
+    warp_slots(&mut ctx, EMERGENCY_CLAIM_DELAY_SLOTS).await;
+    let again = send(&mut ctx, &[reveal(&note, recipient, &claimant)], &[]).await;
+    assert_error(again, MixerError::NullifierAlreadyUsed);
+}
+
+#[tokio::test]
+async fn copied_reveal_pays_only_the_committed_recipient() {
+    let note = Note::random(POOL);
+    let (mut ctx, _authority, deployer) = start(&note).await;
+    enter_emergency(&mut ctx, &deployer).await;
+    let depositor = funded_keypair(&mut ctx, SOL).await;
+    let recipient = Pubkey::new_unique();
+    commit(&mut ctx, &depositor, &note, recipient).await;
+    warp_slots(&mut ctx, EMERGENCY_CLAIM_DELAY_SLOTS).await;
+
+    // A front-runner who copies the pending reveal with their own recipient
+    // has no claim for it, and a fresh one is too recent to reveal
+    let thief = funded_keypair(&mut ctx, SOL).await;
+    let stolen = send(&mut ctx, &[reveal(&note, thief.pubkey(), &thief)], &[]).await;
+    assert_error(stolen, ErrorCode::AccountNotInitialized);
+    commit(&mut ctx, &thief, &note, thief.pubkey()).await;
+    let stolen = send(&mut ctx, &[reveal(&note, thief.pubkey(), &thief)], &[]).await;
+    assert_error(stolen, MixerError::EmergencyClaimTooRecent);
+
+    send(&mut ctx, &[reveal(&note, recipient, &depositor)], &[]).await.unwrap();
+    assert_eq!(lamports(&mut ctx, &recipient).await, DEPOSIT_1_SOL);
+    warp_slots(&mut ctx, EMERGENCY_CLAIM_DELAY_SLOTS).await;
+    let stolen = send(&mut ctx, &[reveal(&note, thief.pubkey(), &thief)], &[]).await;
+    assert_error(stolen, MixerError::NullifierAlreadyUsed);
+}
+
+#[tokio::test]
+async fn only_the_deployment_authority_enters_emergency_mode() {
+    let note = Note::random(POOL);
+    let (mut ctx, authority, deployer) = start(&note).await;
+    let claimant = funded_keypair(&mut ctx, SOL).await;
+
+    let early = send(
+        &mut ctx,
+        &[mixer::commit_emergency_withdraw(
+            &claimant.pubkey(),
+            POOL,
+            note.nullifier,
+            note.secret,
+            Pubkey::new_unique(),
+        )],
+        &[&claimant],
+    )
+    .await;
+    assert_error(early, MixerError::NotInEmergency);
+
+    // Not even the pool's own authority
+    let result = send(
+        &mut ctx,
+        &[mixer::enter_emergency_mode(&authority.pubkey(), POOL)],
+        &[&authority],
+    )
+    .await;
+    assert_error(result, MixerError::Unauthorized);
+
+    enter_emergency(&mut ctx, &deployer).await;
+    let pool: Pool = fetch(&mut ctx, &pool_address(&POOL)).await;
+    assert!(pool.emergency);
+}
+
+#[tokio::test]
+async fn emergency_mode_stops_everything_else() {
+    let note = Note::random(POOL);
+    let (mut ctx, _authority, deployer) = start(&note).await;
+    enter_emergency(&mut ctx, &deployer).await;
+    let root = mixer::current_root(&mut ctx, &POOL).await;
+    let depositor = funded_keypair(&mut ctx, 2 * SOL).await;
+
+    let deposit = send(
+        &mut ctx,
+        &[mixer::deposit(&depositor.pubkey(), POOL, [1u8; 32])],
+        &[&depositor],
+    )
+    .await;
+    assert_error(deposit, MixerError::PoolInEmergency);
+    let withdraw = send(
+        &mut ctx,
+        &[mixer::withdraw_from_tree(POOL, 0, root, note.nullifier_hash(), Pubkey::new_unique())],
+        &[],
+    )
+    .await;
+    assert_error(withdraw, MixerError::PoolInEmergency);
+
+    // A note that was never deposited reclaims nothing
+    let stranger = Note {
+        nullifier: Note::random(POOL).nullifier,
+        ..note.clone()
+    };
+    let recipient = Pubkey::new_unique();
+    commit(&mut ctx, &depositor, &stranger, recipient).await;
+    warp_slots(&mut ctx, EMERGENCY_CLAIM_DELAY_SLOTS).await;
+    let result = send(&mut ctx, &[reveal(&stranger, recipient, &depositor)], &[]).await;
+    assert_error(result, MixerError::UnknownCommitment);
+}
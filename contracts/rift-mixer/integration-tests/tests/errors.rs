@@ -0,0 +1,120 @@
+// Withdrawal error codes and the context they log
+
+mod common;
+
+use common::mixer::{self, garbage_proof};
+use common::{assert_error, funded_keypair, lamports, program_test, send, send_with_logs};
+use rift_mixer::{MixerError, PoolType, DEPOSIT_1_SOL};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+const SOL: u64 = 1_000_000_000;
+const POOL: PoolType = PoolType::Sol1;
+
+/// A pool holding one deposit, and its root
+async fn start() -> (ProgramTestContext, [u8; 32]) {
+    let mut program_test = program_test();
+    mixer::preload_pool(&mut program_test, POOL, Pubkey::new_unique());
+    let mut ctx = program_test.start_with_context().await;
+    let depositor = funded_keypair(&mut ctx, 2 * SOL).await;
+    send(
+        &mut ctx,
+        &[mixer::deposit(&depositor.pubkey(), POOL, [1u8; 32])],
+        &[&depositor],
+    )
+    .await
+    .unwrap();
+    let root = mixer::current_root(&mut ctx, &POOL).await;
+    (ctx, root)
+}
+
+/// Send `instruction`, expecting it to fail with `expected` after logging
+/// `context`
+async fn assert_failure(
+    ctx: &mut ProgramTestContext,
+    instruction: Instruction,
+    expected: MixerError,
+    context: &str,
+) {
+    let (result, logs) = send_with_logs(ctx, &[instruction], &[]).await;
+    assert_error(result, expected);
+    let line = format!("Program log: {}: {}", expected.name(), context);
+    assert!(logs.contains(&line), "no {:?} in {:#?}", line, logs);
+}
+
+#[tokio::test]
+async fn malformed_proof_names_the_bad_coordinate() {
+    let (mut ctx, root) = start().await;
+    let intent = mixer::withdraw_intent(POOL, root, [9u8; 32], Pubkey::new_unique(), None, 0);
+    let mut proof = garbage_proof();
+    proof.b[32..64].copy_from_slice(&rift_mixer::BN254_BASE_FIELD_SIZE);
+
+    let withdraw = mixer::withdraw_with_intent(0, proof, intent);
+    let context = format!("point=b coordinate=1 value={}", hex(&rift_mixer::BN254_BASE_FIELD_SIZE));
+    assert_failure(&mut ctx, withdraw, MixerError::InvalidProofEncoding, &context).await;
+}
+
+#[tokio::test]
+async fn unknown_root_is_logged() {
+    let (mut ctx, _root) = start().await;
+    let intent = mixer::withdraw_intent(POOL, [7u8; 32], [9u8; 32], Pubkey::new_unique(), None, 0);
+
+    let withdraw = mixer::withdraw_with_intent(0, garbage_proof(), intent);
+    let context = format!("root={} tree_index=0", hex(&[7u8; 32]));
+    assert_failure(&mut ctx, withdraw, MixerError::UnknownRoot, &context).await;
+}
+
+#[tokio::test]
+async fn mismatched_accounts_log_both_keys() {
+    let (mut ctx, root) = start().await;
+    let recipient = Pubkey::new_unique();
+    let relayer = Pubkey::new_unique();
+    let intent = mixer::withdraw_intent(POOL, root, [9u8; 32], recipient, Some(relayer), 1);
+    let withdraw = mixer::withdraw_with_intent(0, garbage_proof(), intent);
+    let impostor = Pubkey::new_unique();
+
+    let mut redirected = withdraw.clone();
+    redirected.accounts[4].pubkey = impostor;
+    let context = format!("expected={} actual={}", recipient, impostor);
+    assert_failure(&mut ctx, redirected, MixerError::RecipientMismatch, &context).await;
+
+    let mut fee_taken = withdraw;
+    fee_taken.accounts[5].pubkey = impostor;
+    let context = format!("expected={} actual={}", relayer, impostor);
+    assert_failure(&mut ctx, fee_taken, MixerError::RelayerMismatch, &context).await;
+}
+
+#[tokio::test]
+async fn relayer_fee_is_capped_at_the_deposit() {
+    let (mut ctx, root) = start().await;
+    let relayer = funded_keypair(&mut ctx, SOL).await.pubkey();
+    let fee = DEPOSIT_1_SOL + 1;
+    let intent =
+        mixer::withdraw_intent(POOL, root, [9u8; 32], Pubkey::new_unique(), Some(relayer), fee);
+
+    let withdraw = mixer::withdraw_with_intent(0, garbage_proof(), intent);
+    let context = format!("fee={} cap={}", fee, DEPOSIT_1_SOL);
+    assert_failure(&mut ctx, withdraw, MixerError::FeeAboveCap, &context).await;
+}
+
+#[tokio::test]
+async fn payout_beyond_the_vault_is_refused() {
+    // One deposit, already withdrawn: the vault holds only its rent
+    let mut program_test = program_test();
+    mixer::preload_pool_with_state(&mut program_test, POOL, Pubkey::new_unique(), 1, 1);
+    let mut ctx = program_test.start_with_context().await;
+    let root = mixer::current_root(&mut ctx, &POOL).await;
+    let vault = mixer::vault_address(&mixer::pool_address(&POOL));
+    let balance = lamports(&mut ctx, &vault).await;
+    let intent = mixer::withdraw_intent(POOL, root, [9u8; 32], Pubkey::new_unique(), None, 0);
+
+    let withdraw = mixer::withdraw_with_intent(0, garbage_proof(), intent);
+    let context = format!("vault={} balance={} amount={}", vault, balance, DEPOSIT_1_SOL);
+    assert_failure(&mut ctx, withdraw, MixerError::VaultInsufficient, &context).await;
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,166 @@
+// rift_governance: proposal lifecycle
+
+mod common;
+
+use common::governance::{self, governance_address, proposal_address, vote_record_address};
+use common::{assert_error, fetch, funded_keypair, program_test, send};
+use rift_governance::{
+    Governance, GovernanceError, Proposal, ProposalStatus, VoteRecord, VoteType, TIMELOCK_DELAY,
+    VOTING_PERIOD,
+};
+use rift_test_utils::clock::warp_seconds;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SOL: u64 = 1_000_000_000;
+const TOTAL_SUPPLY: u64 = 1_000_000;
+
+async fn start() -> (ProgramTestContext, Keypair) {
+    let mut ctx = program_test().start_with_context().await;
+    let authority = ctx.payer.pubkey();
+    send(&mut ctx, &[governance::initialize(&authority, TOTAL_SUPPLY)], &[])
+        .await
+        .unwrap();
+
+    let proposer = funded_keypair(&mut ctx, SOL).await;
+    send(
+        &mut ctx,
+        &[governance::create_proposal(&proposer.pubkey(), 1)],
+        &[&proposer],
+    )
+    .await
+    .unwrap();
+    (ctx, proposer)
+}
+
+async fn cast(ctx: &mut ProgramTestContext, support: VoteType, weight: u64) -> Keypair {
+    let voter = funded_keypair(ctx, SOL).await;
+    send(
+        ctx,
+        &[governance::vote(&voter.pubkey(), 1, support, weight)],
+        &[&voter],
+    )
+    .await
+    .unwrap();
+    voter
+}
+
+#[tokio::test]
+async fn proposal_passes_and_executes_after_timelock() {
+    let (mut ctx, proposer) = start().await;
+
+    let governance: Governance = fetch(&mut ctx, &governance_address()).await;
+    assert_eq!(governance.proposal_count, 1);
+    assert_eq!(governance.quorum_votes, TOTAL_SUPPLY / 10);
+    let proposal: Proposal = fetch(&mut ctx, &proposal_address(1)).await;
+    assert_eq!(proposal.proposer, proposer.pubkey());
+    assert!(proposal.status == ProposalStatus::Active);
+
+    let voter = cast(&mut ctx, VoteType::For, 200_000).await;
+    cast(&mut ctx, VoteType::Against, 50_000).await;
+    let record: VoteRecord = fetch(&mut ctx, &vote_record_address(1, &voter.pubkey())).await;
+    assert_eq!(record.weight, 200_000);
+
+    let early = send(&mut ctx, &[governance::finalize_proposal(1)], &[]).await;
+    assert_error(early, GovernanceError::VotingNotEnded);
+
+    warp_seconds(&mut ctx, VOTING_PERIOD + 1).await;
+    send(&mut ctx, &[governance::finalize_proposal(1)], &[])
+        .await
+        .unwrap();
+    let proposal: Proposal = fetch(&mut ctx, &proposal_address(1)).await;
+    assert!(proposal.status == ProposalStatus::Passed);
+    assert_eq!(proposal.votes_for, 200_000);
+    assert_eq!(proposal.votes_against, 50_000);
+
+    let executor = ctx.payer.pubkey();
+    let locked = send(&mut ctx, &[governance::execute_proposal(&executor, 1)], &[]).await;
+    assert_error(locked, GovernanceError::TimelockNotPassed);
+
+    warp_seconds(&mut ctx, TIMELOCK_DELAY).await;
+    send(&mut ctx, &[governance::execute_proposal(&executor, 1)], &[])
+        .await
+        .unwrap();
+    let proposal: Proposal = fetch(&mut ctx, &proposal_address(1)).await;
+    assert!(proposal.status == ProposalStatus::Executed);
+    assert!(proposal.executed_at.is_some());
+}
+
+#[tokio::test]
+async fn proposal_without_quorum_fails() {
+    let (mut ctx, _) = start().await;
+    cast(&mut ctx, VoteType::For, TOTAL_SUPPLY / 20).await;
+
+    warp_seconds(&mut ctx, VOTING_PERIOD + 1).await;
+    send(&mut ctx, &[governance::finalize_proposal(1)], &[])
+        .await
+        .unwrap();
+
+    let proposal: Proposal = fetch(&mut ctx, &proposal_address(1)).await;
+    assert!(proposal.status == ProposalStatus::Failed);
+}
+
+#[tokio::test]
+async fn votes_are_rejected_after_voting_ends_and_twice() {
+    let (mut ctx, _) = start().await;
+    let voter = cast(&mut ctx, VoteType::For, 10).await;
+
+    let twice = send(
+        &mut ctx,
+        &[governance::vote(&voter.pubkey(), 1, VoteType::For, 10)],
+        &[&voter],
+    )
+    .await;
+    assert!(twice.is_err());
+
+    warp_seconds(&mut ctx, VOTING_PERIOD + 1).await;
+    let late_voter = funded_keypair(&mut ctx, SOL).await;
+    let late = send(
+        &mut ctx,
+        &[governance::vote(&late_voter.pubkey(), 1, VoteType::For, 10)],
+        &[&late_voter],
+    )
+    .await;
+    assert_error(late, GovernanceError::VotingEnded);
+}
+
+#[tokio::test]
+async fn only_proposer_or_authority_can_cancel() {
+    let (mut ctx, proposer) = start().await;
+
+    let stranger = funded_keypair(&mut ctx, SOL).await;
+    let denied = send(
+        &mut ctx,
+        &[governance::cancel_proposal(&stranger.pubkey(), 1)],
+        &[&stranger],
+    )
+    .await;
+    assert_error(denied, GovernanceError::Unauthorized);
+
+    send(
+        &mut ctx,
+        &[governance::cancel_proposal(&proposer.pubkey(), 1)],
+        &[&proposer],
+    )
+    .await
+    .unwrap();
+    let proposal: Proposal = fetch(&mut ctx, &proposal_address(1)).await;
+    assert!(proposal.status == ProposalStatus::Cancelled);
+}
+
+#[tokio::test]
+async fn paused_governance_rejects_proposals() {
+    let (mut ctx, proposer) = start().await;
+    let authority = ctx.payer.pubkey();
+    send(&mut ctx, &[governance::pause_governance(&authority)], &[])
+        .await
+        .unwrap();
+
+    let paused = send(
+        &mut ctx,
+        &[governance::create_proposal(&proposer.pubkey(), 2)],
+        &[&proposer],
+    )
+    .await;
+    assert_error(paused, GovernanceError::Paused);
+}
@@ -0,0 +1,138 @@
+// Known holes in the current programs.
+//
+// Every test here asserts the behaviour the protocol needs and FAILS against
+// today's code. They are deliberately not `#[ignore]`d: closing a hole should
+// turn its test green, and nothing should ship while any of them is red.
+
+mod common;
+
+use common::mixer::{self, garbage_proof, nullifier_registry_address, pool_address, vault_address};
+use common::staking;
+use common::{assert_error, funded_keypair, program_test, send};
+use rift_mixer::{MixerError, PoolType};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_instruction;
+
+const SOL: u64 = 1_000_000_000;
+
+/// `verify_proof` is a placeholder that returns `true` for any input
+#[tokio::test]
+async fn withdraw_rejects_unverified_proof() {
+    let mut program_test = program_test();
+    mixer::preload_pool(&mut program_test, PoolType::Sol1, Pubkey::new_unique());
+    let mut ctx = program_test.start_with_context().await;
+    let depositor = funded_keypair(&mut ctx, 2 * SOL).await;
+    send(
+        &mut ctx,
+        &[mixer::deposit(&depositor.pubkey(), PoolType::Sol1, [1u8; 32])],
+        &[&depositor],
+    )
+    .await
+    .unwrap();
+
+    let root = mixer::current_root(&mut ctx, &PoolType::Sol1).await;
+    let theft = send(
+        &mut ctx,
+        &[mixer::withdraw(
+            PoolType::Sol1,
+            garbage_proof(),
+            root,
+            [9u8; 32],
+            Pubkey::new_unique(),
+            None,
+            0,
+        )],
+        &[],
+    )
+    .await;
+    assert_error(theft, MixerError::InvalidProof);
+}
+
+/// `verify_association_proof` shares the placeholder verifier
+#[tokio::test]
+async fn withdraw_with_association_rejects_unverified_proof() {
+    let authority = Keypair::new();
+    let mut program_test = program_test();
+    mixer::preload_pool(&mut program_test, PoolType::Sol1, authority.pubkey());
+    let mut ctx = program_test.start_with_context().await;
+    let depositor = funded_keypair(&mut ctx, 3 * SOL).await;
+    send(
+        &mut ctx,
+        &[
+            mixer::deposit(&depositor.pubkey(), PoolType::Sol1, [1u8; 32]),
+            system_instruction::transfer(&depositor.pubkey(), &authority.pubkey(), SOL),
+            mixer::initialize_association_registry(
+                &authority.pubkey(),
+                PoolType::Sol1,
+                depositor.pubkey(),
+            ),
+            mixer::publish_association_root(&depositor.pubkey(), PoolType::Sol1, [7u8; 32]),
+        ],
+        &[&depositor, &authority],
+    )
+    .await
+    .unwrap();
+
+    let root = mixer::current_root(&mut ctx, &PoolType::Sol1).await;
+    let theft = send(
+        &mut ctx,
+        &[mixer::withdraw_with_association(
+            PoolType::Sol1,
+            garbage_proof(),
+            root,
+            [9u8; 32],
+            [7u8; 32],
+            Pubkey::new_unique(),
+            0,
+        )],
+        &[],
+    )
+    .await;
+    assert_error(theft, MixerError::InvalidProof);
+}
+
+/// `Initialize` only derives the vault address; deposits then create it as a
+/// system-owned account the mixer can never debit
+#[tokio::test]
+async fn mixer_initialize_creates_program_owned_vault() {
+    let mut ctx = program_test().start_with_context().await;
+    let authority = ctx.payer.pubkey();
+    let _ = send(&mut ctx, &[mixer::initialize(&authority, PoolType::Sol1)], &[]).await;
+
+    let vault = vault_address(&pool_address(&PoolType::Sol1));
+    let account = ctx.banks_client.get_account(vault).await.unwrap();
+    let owner = account.map(|account| account.owner);
+    assert_eq!(owner, Some(rift_mixer::ID), "mixer vault was not initialized");
+}
+
+/// No instruction creates the `NullifierRegistry` that `withdraw` requires
+#[tokio::test]
+async fn mixer_initialize_creates_nullifier_registry() {
+    let mut ctx = program_test().start_with_context().await;
+    let authority = ctx.payer.pubkey();
+    let _ = send(&mut ctx, &[mixer::initialize(&authority, PoolType::Sol1)], &[]).await;
+
+    let registry = nullifier_registry_address(&pool_address(&PoolType::Sol1));
+    let account = ctx.banks_client.get_account(registry).await.unwrap();
+    assert!(account.is_some(), "nullifier registry was not initialized");
+}
+
+/// Same vault gap in the staking program: stakes land in a system-owned
+/// account, so `claim_rewards` and `unstake` can never pay out
+#[tokio::test]
+async fn staking_initialize_creates_program_owned_vault() {
+    let mut ctx = program_test().start_with_context().await;
+    let authority = ctx.payer.pubkey();
+    send(&mut ctx, &[staking::initialize(&authority)], &[])
+        .await
+        .unwrap();
+
+    let account = ctx
+        .banks_client
+        .get_account(staking::vault_address())
+        .await
+        .unwrap();
+    let owner = account.map(|account| account.owner);
+    assert_eq!(owner, Some(rift_staking::ID), "staking vault was not initialized");
+}
@@ -0,0 +1,128 @@
+// Withdrawals name a recent root, which must have aged past the pool's minimum
+
+mod common;
+
+use common::mixer::{self, garbage_proof, pool_address, root_history_address};
+use common::{assert_error, fetch, funded_keypair, program_test, send};
+use rift_mixer::{MixerError, PoolType, RootHistory, ROOT_HISTORY_SIZE};
+use rift_test_utils::clock::warp_slots;
+use solana_program_test::{BanksClientError, ProgramTestContext};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SOL: u64 = 1_000_000_000;
+const POOL: PoolType = PoolType::Sol0_1;
+
+/// A pool with the given minimum leaf age, plus a funded depositor
+async fn aged(min_slots: u64, min_deposits: u64) -> (ProgramTestContext, Keypair) {
+    let authority = Keypair::new();
+    let mut program_test = program_test();
+    mixer::preload_pool(&mut program_test, POOL, authority.pubkey());
+    let mut ctx = program_test.start_with_context().await;
+    send(
+        &mut ctx,
+        &[mixer::set_min_leaf_age(&authority.pubkey(), POOL, min_slots, min_deposits)],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    let depositor = funded_keypair(&mut ctx, 10 * SOL).await;
+    (ctx, depositor)
+}
+
+async fn deposit(ctx: &mut ProgramTestContext, depositor: &Keypair, n: usize) {
+    send(
+        ctx,
+        &[mixer::deposit(&depositor.pubkey(), POOL, mixer::numbered(n))],
+        &[depositor],
+    )
+    .await
+    .unwrap();
+}
+
+async fn withdraw(ctx: &mut ProgramTestContext, root: [u8; 32]) -> Result<(), BanksClientError> {
+    send(
+        ctx,
+        &[mixer::withdraw(
+            POOL,
+            garbage_proof(),
+            root,
+            [9u8; 32],
+            Pubkey::new_unique(),
+            None,
+            0,
+        )],
+        &[],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn unknown_root_is_rejected() {
+    let (mut ctx, depositor) = aged(0, 0).await;
+    deposit(&mut ctx, &depositor, 0).await;
+
+    let result = withdraw(&mut ctx, [3u8; 32]).await;
+    assert_error(result, MixerError::UnknownRoot);
+}
+
+#[tokio::test]
+async fn root_must_age_in_slots() {
+    let (mut ctx, depositor) = aged(100, 0).await;
+    deposit(&mut ctx, &depositor, 0).await;
+    let root = mixer::current_root(&mut ctx, &POOL).await;
+
+    let early = withdraw(&mut ctx, root).await;
+    assert_error(early, MixerError::RootTooYoung);
+
+    warp_slots(&mut ctx, 100).await;
+    withdraw(&mut ctx, root).await.unwrap();
+}
+
+#[tokio::test]
+async fn root_must_age_in_deposits() {
+    let (mut ctx, depositor) = aged(0, 2).await;
+    deposit(&mut ctx, &depositor, 0).await;
+    let root = mixer::current_root(&mut ctx, &POOL).await;
+
+    deposit(&mut ctx, &depositor, 1).await;
+    let early = withdraw(&mut ctx, root).await;
+    assert_error(early, MixerError::RootTooYoung);
+
+    deposit(&mut ctx, &depositor, 2).await;
+    withdraw(&mut ctx, root).await.unwrap();
+}
+
+#[tokio::test]
+async fn old_roots_are_evicted() {
+    let (mut ctx, depositor) = aged(0, 0).await;
+    deposit(&mut ctx, &depositor, 0).await;
+    let root = mixer::current_root(&mut ctx, &POOL).await;
+
+    // The root survives ROOT_HISTORY_SIZE - 1 newer ones...
+    for n in 1..ROOT_HISTORY_SIZE {
+        deposit(&mut ctx, &depositor, n).await;
+    }
+    let history: RootHistory =
+        fetch(&mut ctx, &root_history_address(&pool_address(&POOL), 0)).await;
+    assert!(history.find(&root).is_some());
+
+    // ...and is evicted by the next
+    deposit(&mut ctx, &depositor, ROOT_HISTORY_SIZE).await;
+    let expired = withdraw(&mut ctx, root).await;
+    assert_error(expired, MixerError::UnknownRoot);
+}
+
+#[tokio::test]
+async fn only_the_authority_sets_the_minimum_age() {
+    let (mut ctx, _) = aged(0, 0).await;
+    let stranger = Keypair::new();
+
+    let result = send(
+        &mut ctx,
+        &[mixer::set_min_leaf_age(&stranger.pubkey(), POOL, 0, 0)],
+        &[&stranger],
+    )
+    .await;
+    assert_error(result, MixerError::Unauthorized);
+}
@@ -0,0 +1,67 @@
+// rift_mixer's incremental Merkle tree against the reference sparse tree
+//
+// Every property inserts a sequence of commitments one at a time, checks the
+// root returned by `insert_leaf` against the reference after each insert, and
+// checks `compute_merkle_root` and `rebuild_tree` over the whole sequence at
+// the end.
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+use rift_mixer::{compute_merkle_root, insert_leaf, rebuild_tree, zero_hashes, MERKLE_TREE_HEIGHT};
+use rift_test_utils::merkle::{Node, SparseMerkleTree};
+
+fn assert_roots_agree(height: usize, leaves: &[Node]) -> Result<(), TestCaseError> {
+    let mut filled_subtrees = vec![[0u8; 32]; height];
+    let mut reference = SparseMerkleTree::new(height);
+
+    for (index, leaf) in leaves.iter().enumerate() {
+        let root = insert_leaf(&mut filled_subtrees, index, *leaf);
+        reference.set(index as u64, *leaf);
+
+        prop_assert_eq!(root, reference.root(), "insert_leaf after {} leaves", index + 1);
+    }
+    prop_assert_eq!(compute_merkle_root(leaves, height), reference.root());
+    prop_assert_eq!(rebuild_tree(leaves, height), (filled_subtrees, reference.root()));
+    Ok(())
+}
+
+fn leaf() -> impl Strategy<Value = Node> {
+    any::<Node>()
+}
+
+#[test]
+fn empty_tree_root_matches_reference() {
+    for height in 0..=MERKLE_TREE_HEIGHT {
+        let reference = SparseMerkleTree::new(height).root();
+        assert_eq!(zero_hashes(height)[height], reference);
+        assert_eq!(compute_merkle_root(&[], height), reference);
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    /// Random sequences in the production-height tree, odd and even lengths
+    #[test]
+    fn random_sequences_match_reference(leaves in vec(leaf(), 1..200)) {
+        assert_roots_agree(MERKLE_TREE_HEIGHT, &leaves)?;
+    }
+
+    /// Lengths straddling a power of two, where a new level starts filling
+    #[test]
+    fn power_of_two_boundaries_match_reference(
+        k in 0u32..8,
+        delta in -1i64..=1,
+        seed in vec(leaf(), 257),
+    ) {
+        let len = ((1i64 << k) + delta).max(1) as usize;
+        assert_roots_agree(MERKLE_TREE_HEIGHT, &seed[..len])?;
+    }
+
+    /// Fill small trees to capacity; the production tree is the same code
+    /// with a longer `filled_subtrees`
+    #[test]
+    fn full_trees_match_reference(height in 0usize..=6, seed in vec(leaf(), 64)) {
+        assert_roots_agree(height, &seed[..1 << height])?;
+    }
+}
@@ -0,0 +1,153 @@
+// Upgrading pools created by the first deployment, before state carried a
+// version
+
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use common::mixer::{
+    self, deposit_activity_address, legacy_tree_address, merkle_tree_address,
+    nullifier_registry_address, pool_address, root_history_address,
+};
+use common::{assert_error, fetch, funded_keypair, legacy, program_test, send};
+use rift_mixer::{
+    compute_merkle_root, note_nullifier_hash, rebuild_tree, DepositActivity, MerkleTree,
+    MixerError, NullifierRegistry, Pool, PoolType, RootHistory, DEFAULT_MAX_DEPOSITS_PER_EPOCH,
+    DEFAULT_MAX_TVL_DEPOSITS, MERKLE_TREE_HEIGHT,
+};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_instruction;
+
+const SOL: u64 = 1_000_000_000;
+const POOL: PoolType = PoolType::Sol1;
+
+async fn start(leaves: usize, nullifiers: usize) -> (ProgramTestContext, Keypair) {
+    let authority = Keypair::new();
+    let mut program_test = program_test();
+    legacy::preload_pool(&mut program_test, POOL, authority.pubkey(), leaves, nullifiers);
+    let mut ctx = program_test.start_with_context().await;
+    // The authority pays for everything the migration creates and grows
+    let transfer = system_instruction::transfer(&ctx.payer.pubkey(), &authority.pubkey(), SOL);
+    send(&mut ctx, &[transfer], &[]).await.unwrap();
+    (ctx, authority)
+}
+
+/// Call `migrate_pool` until the pool reads the current version; returns
+/// how many calls it took
+async fn migrate(ctx: &mut ProgramTestContext, authority: &Keypair) -> usize {
+    let pool_key = pool_address(&POOL);
+    for calls in 1..=8 {
+        send(ctx, &[mixer::migrate_pool(&authority.pubkey(), POOL)], &[authority])
+            .await
+            .unwrap();
+        let pool: Pool = fetch(ctx, &pool_key).await;
+        if pool.version == Pool::VERSION {
+            return calls;
+        }
+    }
+    panic!("pool still not migrated");
+}
+
+async fn account(ctx: &mut ProgramTestContext, address: &Pubkey) -> Option<Account> {
+    ctx.banks_client.get_account(*address).await.unwrap()
+}
+
+#[tokio::test]
+async fn v0_pool_moves_to_the_current_layout() {
+    let (mut ctx, authority) = start(2, 1).await;
+    let pool_key = pool_address(&POOL);
+    let tree_key = merkle_tree_address(&pool_key, 0);
+    let before: Pool = fetch(&mut ctx, &pool_key).await;
+    assert_eq!((before.version, before.max_tvl), (0, 0));
+    let depositor = funded_keypair(&mut ctx, 2 * SOL).await;
+    let deposit = || mixer::deposit(&depositor.pubkey(), POOL, [1u8; 32]);
+
+    // Nothing lives at the current seeds yet
+    let result = send(&mut ctx, &[deposit()], &[&depositor]).await;
+    assert_error(result, ErrorCode::AccountNotInitialized);
+
+    // Only `MAX_PERMITTED_DATA_INCREASE` bytes of tree a call
+    assert_eq!(migrate(&mut ctx, &authority).await, 4);
+
+    // Version 0's root was not one a proof could be built against; the pool
+    // now has the root of the same leaves under the current tree
+    let leaves = vec![mixer::numbered(0), mixer::numbered(1)];
+    let root = compute_merkle_root(&leaves, MERKLE_TREE_HEIGHT);
+    assert_ne!(before.merkle_root, root);
+    let pool: Pool = fetch(&mut ctx, &pool_key).await;
+    assert_eq!(account(&mut ctx, &pool_key).await.unwrap().data.len(), 8 + Pool::SIZE);
+    assert_eq!(
+        (pool.total_deposits, pool.total_withdrawals, pool.merkle_root, pool.current_tree),
+        (2, 1, root, 0)
+    );
+    assert_eq!(
+        (pool.max_tvl, pool.max_deposits_per_epoch),
+        (pool.deposit_amount * DEFAULT_MAX_TVL_DEPOSITS, DEFAULT_MAX_DEPOSITS_PER_EPOCH)
+    );
+    let tree: MerkleTree = fetch(&mut ctx, &tree_key).await;
+    assert_eq!(tree.version, MerkleTree::VERSION);
+    assert_eq!(tree.leaves, leaves);
+    assert_eq!(tree.filled_subtrees.to_vec(), rebuild_tree(&leaves, MERKLE_TREE_HEIGHT).0);
+    assert_eq!(account(&mut ctx, &tree_key).await.unwrap().data.len(), 8 + MerkleTree::SIZE);
+    assert!(account(&mut ctx, &legacy_tree_address(&POOL)).await.is_none());
+    let history: RootHistory = fetch(&mut ctx, &root_history_address(&pool_key, 0)).await;
+    assert_eq!(history.version, RootHistory::VERSION);
+    assert_eq!(history.find(&root).unwrap().leaf_count, 2);
+    assert!(history.find(&before.merkle_root).is_none());
+    let activity: DepositActivity = fetch(&mut ctx, &deposit_activity_address(&pool_key)).await;
+    assert_eq!(activity.version, DepositActivity::VERSION);
+    let registry: NullifierRegistry =
+        fetch(&mut ctx, &nullifier_registry_address(&pool_key)).await;
+    assert_eq!((registry.version, registry.used_nullifiers.len()), (NullifierRegistry::VERSION, 1));
+
+    // A note deposited before the migration is still withdrawable, and new
+    // deposits extend the same tree
+    let nullifier_hash = note_nullifier_hash(&[2u8; 32]);
+    let withdraw =
+        mixer::withdraw_from_tree(POOL, 0, root, nullifier_hash, Pubkey::new_unique());
+    send(&mut ctx, &[withdraw], &[]).await.unwrap();
+    send(&mut ctx, &[deposit()], &[&depositor]).await.unwrap();
+    let leaves = [leaves, vec![[1u8; 32]]].concat();
+    let pool: Pool = fetch(&mut ctx, &pool_key).await;
+    assert_eq!(pool.merkle_root, compute_merkle_root(&leaves, MERKLE_TREE_HEIGHT));
+
+    // Migrating again changes nothing
+    let migrated = account(&mut ctx, &pool_key).await.unwrap().data;
+    assert_eq!(migrate(&mut ctx, &authority).await, 1);
+    assert_eq!(account(&mut ctx, &pool_key).await.unwrap().data, migrated);
+    let pool: Pool = fetch(&mut ctx, &pool_key).await;
+    assert_eq!((pool.total_deposits, pool.total_withdrawals), (3, 2));
+}
+
+#[tokio::test]
+async fn full_v0_registry_grows_by_its_version_byte() {
+    // 10,002 nullifiers fill the registry's padding to the last byte
+    let (mut ctx, authority) = start(2, 10_002).await;
+    let registry_key = nullifier_registry_address(&pool_address(&POOL));
+    let v0_len = account(&mut ctx, &registry_key).await.unwrap().data.len();
+
+    migrate(&mut ctx, &authority).await;
+
+    assert_eq!(account(&mut ctx, &registry_key).await.unwrap().data.len(), v0_len + 1);
+    let registry: NullifierRegistry = fetch(&mut ctx, &registry_key).await;
+    assert_eq!(
+        (registry.version, registry.used_nullifiers.len()),
+        (NullifierRegistry::VERSION, 10_002)
+    );
+}
+
+#[tokio::test]
+async fn only_the_authority_migrates() {
+    let (mut ctx, _authority) = start(2, 1).await;
+    let intruder = funded_keypair(&mut ctx, SOL).await;
+
+    let result = send(
+        &mut ctx,
+        &[mixer::migrate_pool(&intruder.pubkey(), POOL)],
+        &[&intruder],
+    )
+    .await;
+    assert_error(result, MixerError::Unauthorized);
+}
@@ -0,0 +1,173 @@
+// rift_mixer: initialize, deposit and withdraw
+
+mod common;
+
+use common::mixer::{self, garbage_proof, merkle_tree_address, pool_address, vault_address};
+use common::{assert_error, fetch, funded_keypair, lamports, program_test, send};
+use rift_mixer::{
+    compute_merkle_root, MerkleTree, MixerError, NullifierRegistry, Pool, PoolType,
+    WithdrawIntent, DEPOSIT_1_SOL, MERKLE_TREE_HEIGHT,
+};
+use rift_test_utils::clock::{clock, warp_slots};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+const SOL: u64 = 1_000_000_000;
+
+async fn start(pool_type: PoolType) -> ProgramTestContext {
+    let mut program_test = program_test();
+    mixer::preload_pool(&mut program_test, pool_type, Pubkey::new_unique());
+    program_test.start_with_context().await
+}
+
+#[tokio::test]
+async fn initialize_over_existing_pool_fails() {
+    let mut ctx = start(PoolType::Sol1).await;
+    let authority = ctx.payer.pubkey();
+
+    let again = send(&mut ctx, &[mixer::initialize(&authority, PoolType::Sol1)], &[]).await;
+    assert!(again.is_err());
+}
+
+#[tokio::test]
+async fn deposit_inserts_commitment_and_moves_funds() {
+    let mut ctx = start(PoolType::Sol1).await;
+    let depositor = funded_keypair(&mut ctx, 2 * SOL).await;
+    let vault = vault_address(&pool_address(&PoolType::Sol1));
+    let vault_before = lamports(&mut ctx, &vault).await;
+
+    send(
+        &mut ctx,
+        &[mixer::deposit(&depositor.pubkey(), PoolType::Sol1, [1u8; 32])],
+        &[&depositor],
+    )
+    .await
+    .unwrap();
+
+    let pool: Pool = fetch(&mut ctx, &pool_address(&PoolType::Sol1)).await;
+    assert_eq!(pool.next_index, 1);
+    assert_eq!(pool.total_deposits, 1);
+    assert_eq!(pool.merkle_root, compute_merkle_root(&[[1u8; 32]], MERKLE_TREE_HEIGHT));
+
+    let tree_address = merkle_tree_address(&pool_address(&PoolType::Sol1), 0);
+    let tree: MerkleTree = fetch(&mut ctx, &tree_address).await;
+    assert_eq!(tree.leaves, vec![[1u8; 32]]);
+    assert_eq!(lamports(&mut ctx, &vault).await - vault_before, DEPOSIT_1_SOL);
+}
+
+#[tokio::test]
+async fn duplicate_commitment_is_rejected() {
+    let mut ctx = start(PoolType::Sol0_1).await;
+    let depositor = funded_keypair(&mut ctx, SOL).await;
+    let deposit = mixer::deposit(&depositor.pubkey(), PoolType::Sol0_1, [5u8; 32]);
+    send(&mut ctx, std::slice::from_ref(&deposit), &[&depositor]).await.unwrap();
+
+    let again = send(&mut ctx, &[deposit], &[&depositor]).await;
+    assert_error(again, MixerError::CommitmentAlreadyUsed);
+}
+
+#[tokio::test]
+async fn withdraw_pays_recipient_and_relayer_once() {
+    let mut ctx = start(PoolType::Sol1).await;
+    let depositor = funded_keypair(&mut ctx, 2 * SOL).await;
+    send(
+        &mut ctx,
+        &[mixer::deposit(&depositor.pubkey(), PoolType::Sol1, [1u8; 32])],
+        &[&depositor],
+    )
+    .await
+    .unwrap();
+
+    let recipient = Pubkey::new_unique();
+    let relayer = funded_keypair(&mut ctx, SOL).await.pubkey();
+    let fee = DEPOSIT_1_SOL * rift_mixer::RELAYER_FEE_BPS / 10_000;
+    let relayer_before = lamports(&mut ctx, &relayer).await;
+    let root = mixer::current_root(&mut ctx, &PoolType::Sol1).await;
+    let withdraw = mixer::withdraw(
+        PoolType::Sol1,
+        garbage_proof(),
+        root,
+        [9u8; 32],
+        recipient,
+        Some(relayer),
+        fee,
+    );
+
+    send(&mut ctx, std::slice::from_ref(&withdraw), &[]).await.unwrap();
+
+    assert_eq!(lamports(&mut ctx, &recipient).await, DEPOSIT_1_SOL - fee);
+    assert_eq!(lamports(&mut ctx, &relayer).await - relayer_before, fee);
+    let pool: Pool = fetch(&mut ctx, &pool_address(&PoolType::Sol1)).await;
+    assert_eq!(pool.total_withdrawals, 1);
+    let registry: NullifierRegistry = fetch(
+        &mut ctx,
+        &mixer::nullifier_registry_address(&pool_address(&PoolType::Sol1)),
+    )
+    .await;
+    assert_eq!(registry.used_nullifiers, vec![[9u8; 32]]);
+
+    let again = send(&mut ctx, &[withdraw], &[]).await;
+    assert_error(again, MixerError::NullifierAlreadyUsed);
+}
+
+/// One deposit into the 1 SOL pool, returning the root it produced
+async fn deposit_one(ctx: &mut ProgramTestContext) -> [u8; 32] {
+    let depositor = funded_keypair(ctx, 2 * SOL).await;
+    send(
+        ctx,
+        &[mixer::deposit(&depositor.pubkey(), PoolType::Sol1, [1u8; 32])],
+        &[&depositor],
+    )
+    .await
+    .unwrap();
+    mixer::current_root(ctx, &PoolType::Sol1).await
+}
+
+#[tokio::test]
+async fn intent_expires_after_its_slot() {
+    let mut ctx = start(PoolType::Sol1).await;
+    let root = deposit_one(&mut ctx).await;
+    let slot = clock(&mut ctx).await.slot;
+    let intent = |expiry_slot| WithdrawIntent {
+        expiry_slot,
+        ..mixer::withdraw_intent(PoolType::Sol1, root, [9u8; 32], Pubkey::new_unique(), None, 0)
+    };
+
+    warp_slots(&mut ctx, 10).await;
+    let stale = mixer::withdraw_with_intent(0, garbage_proof(), intent(slot + 5));
+    assert_error(send(&mut ctx, &[stale], &[]).await, MixerError::IntentExpired);
+
+    let fresh = mixer::withdraw_with_intent(0, garbage_proof(), intent(slot + 10));
+    send(&mut ctx, &[fresh], &[]).await.unwrap();
+}
+
+#[tokio::test]
+async fn intent_binds_the_recipient_and_relayer_accounts() {
+    let mut ctx = start(PoolType::Sol1).await;
+    let root = deposit_one(&mut ctx).await;
+    let recipient = Pubkey::new_unique();
+    let relayer = funded_keypair(&mut ctx, SOL).await.pubkey();
+    let intent =
+        mixer::withdraw_intent(PoolType::Sol1, root, [9u8; 32], recipient, Some(relayer), 1);
+    let withdraw = mixer::withdraw_with_intent(0, garbage_proof(), intent.clone());
+
+    // A relayer paying the fee to itself instead of the named relayer
+    let mut fee_taken = withdraw.clone();
+    fee_taken.accounts[5].pubkey = Pubkey::new_unique();
+    assert_error(send(&mut ctx, &[fee_taken], &[]).await, MixerError::RelayerMismatch);
+
+    let mut redirected = withdraw.clone();
+    redirected.accounts[4].pubkey = Pubkey::new_unique();
+    assert_error(send(&mut ctx, &[redirected], &[]).await, MixerError::RecipientMismatch);
+
+    let other_pool = WithdrawIntent {
+        pool_type: PoolType::Sol10,
+        ..intent
+    };
+    let mut wrong_pool = mixer::withdraw_with_intent(0, garbage_proof(), other_pool);
+    wrong_pool.accounts = withdraw.accounts.clone();
+    assert_error(send(&mut ctx, &[wrong_pool], &[]).await, MixerError::InvalidExtData);
+
+    send(&mut ctx, &[withdraw], &[]).await.unwrap();
+}
@@ -0,0 +1,82 @@
+// Encrypted note backups: deposit with a backup, then restore from events
+
+mod common;
+
+use common::mixer::{self, pool_address};
+use common::{assert_error, fetch, funded_keypair, program_test, send, send_with_logs};
+use rift_mixer::{MixerError, Pool, PoolType, MAX_ENCRYPTED_NOTE_LEN};
+use rift_sdk::backup::encrypt_note;
+use rift_sdk::events::deposit_events;
+use rift_sdk::{recover_notes, Note, ViewingKey};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+const SOL: u64 = 1_000_000_000;
+const MNEMONIC: &str =
+    "legal winner thank year wave sausage worth useful legal winner thank yellow";
+
+async fn start() -> ProgramTestContext {
+    let mut program_test = program_test();
+    mixer::preload_pool(&mut program_test, PoolType::Sol1, Pubkey::new_unique());
+    program_test.start_with_context().await
+}
+
+#[tokio::test]
+async fn notes_are_restored_from_deposit_events() {
+    let mut ctx = start().await;
+    let depositor = funded_keypair(&mut ctx, 5 * SOL).await;
+    let wallet = ViewingKey::from_mnemonic(MNEMONIC, "").unwrap();
+    let stranger = ViewingKey::from_seed(&[7u8; 64]);
+
+    let mut events = Vec::new();
+    let mut mine = Vec::new();
+    for owner in [&wallet, &stranger, &wallet] {
+        let note = Note::random(PoolType::Sol1);
+        let deposit = mixer::deposit_with_note(
+            &depositor.pubkey(),
+            PoolType::Sol1,
+            note.commitment(),
+            Some(encrypt_note(&note, &owner.public_key())),
+        );
+        let (result, logs) = send_with_logs(&mut ctx, &[deposit], &[&depositor]).await;
+        result.unwrap();
+        events.extend(deposit_events(&logs));
+        if std::ptr::eq(owner, &wallet) {
+            mine.push(note);
+        }
+    }
+    // A deposit without a backup is simply skipped
+    send(
+        &mut ctx,
+        &[mixer::deposit(&depositor.pubkey(), PoolType::Sol1, [3u8; 32])],
+        &[&depositor],
+    )
+    .await
+    .unwrap();
+
+    let restored_wallet = ViewingKey::from_mnemonic(MNEMONIC, "").unwrap();
+    let recovered = recover_notes(&restored_wallet, &events);
+    let notes: Vec<Note> = recovered.iter().map(|r| r.note.clone()).collect();
+    assert_eq!(notes, mine);
+    let leaf_indexes: Vec<u64> = recovered.iter().map(|r| r.leaf_index).collect();
+    assert_eq!(leaf_indexes, vec![0, 2]);
+}
+
+#[tokio::test]
+async fn oversized_backup_is_rejected() {
+    let mut ctx = start().await;
+    let depositor = funded_keypair(&mut ctx, 2 * SOL).await;
+
+    let deposit = mixer::deposit_with_note(
+        &depositor.pubkey(),
+        PoolType::Sol1,
+        [1u8; 32],
+        Some(vec![0u8; MAX_ENCRYPTED_NOTE_LEN + 1]),
+    );
+    let result = send(&mut ctx, &[deposit], &[&depositor]).await;
+    assert_error(result, MixerError::EncryptedNoteTooLarge);
+
+    let pool: Pool = fetch(&mut ctx, &pool_address(&PoolType::Sol1)).await;
+    assert_eq!(pool.next_index, 0);
+}
@@ -0,0 +1,100 @@
+// is_spent and find_commitment, simulated and decoded through rift-sdk
+
+mod common;
+
+use common::mixer;
+use common::{funded_keypair, program_test, send};
+use rift_mixer::{LeafPosition, PoolType};
+use rift_sdk::status::{
+    decode_is_spent, decode_leaf_position, find_commitment_instruction, is_spent_instruction,
+};
+use rift_sdk::Note;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use solana_sdk::transaction::Transaction;
+
+const SOL: u64 = 1_000_000_000;
+const POOL: PoolType = PoolType::Sol1;
+
+/// A pool holding two deposits, `notes[1]` landing second
+async fn start(notes: &[Note; 2]) -> ProgramTestContext {
+    let mut program_test = program_test();
+    mixer::preload_pool(&mut program_test, POOL, Pubkey::new_unique());
+    let mut ctx = program_test.start_with_context().await;
+    let depositor = funded_keypair(&mut ctx, 3 * SOL).await;
+    for note in notes {
+        send(
+            &mut ctx,
+            &[mixer::deposit(&depositor.pubkey(), POOL, note.commitment())],
+            &[&depositor],
+        )
+        .await
+        .unwrap();
+    }
+    ctx
+}
+
+/// Return data of `instruction`, simulated like the SDK's `fetch_*` do
+async fn simulate(ctx: &mut ProgramTestContext, instruction: Instruction) -> Vec<u8> {
+    let blockhash = ctx.get_new_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&ctx.payer.pubkey()),
+        &[&ctx.payer],
+        blockhash,
+    );
+    let outcome = ctx
+        .banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap();
+    outcome.result.unwrap().unwrap();
+    let return_data = outcome.simulation_details.unwrap().return_data.unwrap();
+    assert_eq!(return_data.program_id, rift_mixer::ID);
+    return_data.data
+}
+
+async fn is_spent(ctx: &mut ProgramTestContext, note: &Note) -> bool {
+    let data = simulate(ctx, is_spent_instruction(&POOL, note.nullifier_hash())).await;
+    decode_is_spent(&data).unwrap()
+}
+
+async fn find(ctx: &mut ProgramTestContext, note: &Note) -> Option<LeafPosition> {
+    let data = simulate(ctx, find_commitment_instruction(&POOL, 0, note.commitment())).await;
+    decode_leaf_position(&data).unwrap()
+}
+
+#[tokio::test]
+async fn deposited_notes_are_found_in_their_tree() {
+    let notes = [Note::random(POOL), Note::random(POOL)];
+    let mut ctx = start(&notes).await;
+
+    let expected = |leaf_index| {
+        Some(LeafPosition {
+            tree_index: 0,
+            leaf_index,
+        })
+    };
+    assert_eq!(find(&mut ctx, &notes[0]).await, expected(0));
+    assert_eq!(find(&mut ctx, &notes[1]).await, expected(1));
+    assert_eq!(find(&mut ctx, &Note::random(POOL)).await, None);
+}
+
+#[tokio::test]
+async fn withdrawn_notes_read_as_spent() {
+    let notes = [Note::random(POOL), Note::random(POOL)];
+    let mut ctx = start(&notes).await;
+    assert!(!is_spent(&mut ctx, &notes[0]).await);
+
+    let root = mixer::current_root(&mut ctx, &POOL).await;
+    let withdraw =
+        mixer::withdraw_from_tree(POOL, 0, root, notes[0].nullifier_hash(), Pubkey::new_unique());
+    send(&mut ctx, &[withdraw], &[]).await.unwrap();
+
+    assert!(is_spent(&mut ctx, &notes[0]).await);
+    assert!(!is_spent(&mut ctx, &notes[1]).await);
+    // Spending does not remove the leaf
+    assert!(find(&mut ctx, &notes[0]).await.is_some());
+}
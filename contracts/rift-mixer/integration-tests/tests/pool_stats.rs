@@ -0,0 +1,106 @@
+// get_pool_stats return data, decoded the way clients do through rift-sdk
+
+mod common;
+
+use common::mixer::{self, pool_address, vault_address};
+use common::{funded_keypair, lamports, program_test, send};
+use rift_mixer::{PoolStats, PoolType, DEPOSIT_1_SOL, POOL_STATS_VERSION};
+use rift_test_utils::clock::{warp_days, warp_seconds};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+
+const SOL: u64 = 1_000_000_000;
+
+async fn start(leaves: usize, nullifiers: usize) -> ProgramTestContext {
+    let mut program_test = program_test();
+    mixer::preload_pool_with_state(
+        &mut program_test,
+        PoolType::Sol1,
+        Pubkey::new_unique(),
+        leaves,
+        nullifiers,
+    );
+    program_test.start_with_context().await
+}
+
+/// Simulated, like `rift_sdk::fetch_pool_stats` does
+async fn pool_stats(ctx: &mut ProgramTestContext) -> PoolStats {
+    let blockhash = ctx.get_new_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[mixer::get_pool_stats(PoolType::Sol1, 0)],
+        Some(&ctx.payer.pubkey()),
+        &[&ctx.payer],
+        blockhash,
+    );
+    let outcome = ctx
+        .banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap();
+    outcome.result.unwrap().unwrap();
+    let return_data = outcome.simulation_details.unwrap().return_data.unwrap();
+    assert_eq!(return_data.program_id, rift_mixer::ID);
+    rift_sdk::decode_pool_stats(&return_data.data).unwrap()
+}
+
+async fn deposit(ctx: &mut ProgramTestContext, depositor: &Keypair, n: usize) {
+    send(
+        ctx,
+        &[mixer::deposit(&depositor.pubkey(), PoolType::Sol1, mixer::numbered(1_000 + n))],
+        &[depositor],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn stats_track_the_vault_and_anonymity_set() {
+    // Three deposits, one already withdrawn
+    let mut ctx = start(3, 1).await;
+    let stats = pool_stats(&mut ctx).await;
+    assert_eq!(stats.version, POOL_STATS_VERSION);
+    assert_eq!(stats.anonymity_set_size, 2);
+    let vault = vault_address(&pool_address(&PoolType::Sol1));
+    assert_eq!(stats.vault_lamports, lamports(&mut ctx, &vault).await);
+    assert!(stats.vault_lamports >= 2 * DEPOSIT_1_SOL);
+    assert_eq!(stats.root_history_depth, 1);
+
+    let depositor = funded_keypair(&mut ctx, 2 * SOL).await;
+    deposit(&mut ctx, &depositor, 0).await;
+    let before = stats.vault_lamports;
+    let stats = pool_stats(&mut ctx).await;
+    assert_eq!(stats.total_deposits, 4);
+    assert_eq!(stats.anonymity_set_size, 3);
+    assert_eq!(stats.vault_lamports, before + DEPOSIT_1_SOL);
+    assert_eq!(stats.root_history_depth, 2);
+    assert_eq!(stats.merkle_root, mixer::current_root(&mut ctx, &PoolType::Sol1).await);
+}
+
+#[tokio::test]
+async fn more_withdrawals_than_deposits_do_not_underflow() {
+    let mut ctx = start(0, 2).await;
+    let stats = pool_stats(&mut ctx).await;
+    assert_eq!(stats.total_withdrawals, 2);
+    assert_eq!(stats.anonymity_set_size, 0);
+    assert_eq!(stats.root_history_depth, 0);
+}
+
+#[tokio::test]
+async fn recent_deposits_age_out_of_the_windows() {
+    let mut ctx = start(0, 0).await;
+    let depositor = funded_keypair(&mut ctx, 5 * SOL).await;
+    deposit(&mut ctx, &depositor, 0).await;
+    deposit(&mut ctx, &depositor, 1).await;
+
+    warp_seconds(&mut ctx, 25 * 60 * 60).await;
+    deposit(&mut ctx, &depositor, 2).await;
+    let stats = pool_stats(&mut ctx).await;
+    assert_eq!((stats.deposits_last_24h, stats.deposits_last_7d), (1, 3));
+
+    warp_days(&mut ctx, 8).await;
+    let stats = pool_stats(&mut ctx).await;
+    assert_eq!((stats.deposits_last_24h, stats.deposits_last_7d), (0, 0));
+    assert_eq!(stats.total_deposits, 3);
+}
@@ -0,0 +1,152 @@
+// Retiring a pool and closing its accounts into a treasury
+
+mod common;
+
+use common::mixer::{
+    self, deposit_activity_address, merkle_tree_address, nullifier_registry_address, pool_address,
+    root_history_address, vault_address,
+};
+use common::{assert_error, fetch, funded_keypair, lamports, program_test, send};
+use rift_mixer::{MixerError, Pool, PoolType, RETIRED_ROOT_WINDOW_SLOTS};
+use rift_sdk::Note;
+use rift_test_utils::clock::warp_slots;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SOL: u64 = 1_000_000_000;
+const POOL: PoolType = PoolType::Sol1;
+
+async fn retire(ctx: &mut ProgramTestContext, authority: &Keypair) {
+    send(ctx, &[mixer::retire_pool(&authority.pubkey(), POOL)], &[authority])
+        .await
+        .unwrap();
+}
+
+/// A retired `preload_drained_pool` whose roots are past the window
+async fn start_drained() -> (ProgramTestContext, Keypair) {
+    let authority = Keypair::new();
+    let mut program_test = program_test();
+    mixer::preload_drained_pool(&mut program_test, POOL, authority.pubkey());
+    let mut ctx = program_test.start_with_context().await;
+    retire(&mut ctx, &authority).await;
+    warp_slots(&mut ctx, RETIRED_ROOT_WINDOW_SLOTS).await;
+    (ctx, authority)
+}
+
+#[tokio::test]
+async fn retired_pool_refuses_deposits_but_pays_withdrawals() {
+    let authority = Keypair::new();
+    let mut program_test = program_test();
+    mixer::preload_pool(&mut program_test, POOL, authority.pubkey());
+    let mut ctx = program_test.start_with_context().await;
+    let depositor = funded_keypair(&mut ctx, 3 * SOL).await;
+    let note = Note::random(POOL);
+    send(
+        &mut ctx,
+        &[mixer::deposit(&depositor.pubkey(), POOL, note.commitment())],
+        &[&depositor],
+    )
+    .await
+    .unwrap();
+    retire(&mut ctx, &authority).await;
+
+    let deposit = send(
+        &mut ctx,
+        &[mixer::deposit(&depositor.pubkey(), POOL, [1u8; 32])],
+        &[&depositor],
+    )
+    .await;
+    assert_error(deposit, MixerError::PoolRetired);
+
+    let root = mixer::current_root(&mut ctx, &POOL).await;
+    let recipient = Pubkey::new_unique();
+    let withdraw = mixer::withdraw_from_tree(POOL, 0, root, note.nullifier_hash(), recipient);
+    send(&mut ctx, &[withdraw], &[]).await.unwrap();
+    assert!(lamports(&mut ctx, &recipient).await > 0);
+}
+
+#[tokio::test]
+async fn only_a_retired_drained_pool_with_old_roots_closes() {
+    let authority = Keypair::new();
+    let mut program_test = program_test();
+    // Two deposits, one of them withdrawn
+    mixer::preload_pool_with_state(&mut program_test, POOL, authority.pubkey(), 2, 1);
+    let mut ctx = program_test.start_with_context().await;
+    let close = || mixer::close_pool(&authority.pubkey(), POOL, 0, Pubkey::new_unique());
+
+    let result = send(&mut ctx, &[close()], &[&authority]).await;
+    assert_error(result, MixerError::PoolNotRetired);
+
+    retire(&mut ctx, &authority).await;
+    let result = send(&mut ctx, &[close()], &[&authority]).await;
+    assert_error(result, MixerError::PoolNotDrained);
+}
+
+#[tokio::test]
+async fn recent_roots_keep_a_tree_open() {
+    let authority = Keypair::new();
+    let mut program_test = program_test();
+    mixer::preload_drained_pool(&mut program_test, POOL, authority.pubkey());
+    let mut ctx = program_test.start_with_context().await;
+    retire(&mut ctx, &authority).await;
+    let close = || mixer::close_pool(&authority.pubkey(), POOL, 1, Pubkey::new_unique());
+
+    warp_slots(&mut ctx, RETIRED_ROOT_WINDOW_SLOTS / 2).await;
+    let result = send(&mut ctx, &[close()], &[&authority]).await;
+    assert_error(result, MixerError::RootsStillRecent);
+
+    warp_slots(&mut ctx, RETIRED_ROOT_WINDOW_SLOTS / 2).await;
+    send(&mut ctx, &[close()], &[&authority]).await.unwrap();
+}
+
+#[tokio::test]
+async fn closing_every_tree_returns_all_rent_to_the_treasury() {
+    let (mut ctx, authority) = start_drained().await;
+    let pool = pool_address(&POOL);
+    let treasury = Pubkey::new_unique();
+    let tree_accounts = |tree_index| {
+        [merkle_tree_address(&pool, tree_index), root_history_address(&pool, tree_index)]
+    };
+    let pool_accounts = [
+        pool,
+        nullifier_registry_address(&pool),
+        deposit_activity_address(&pool),
+        vault_address(&pool),
+    ];
+    let mut reclaimable = 0;
+    for address in tree_accounts(0).iter().chain(&tree_accounts(1)).chain(&pool_accounts) {
+        reclaimable += lamports(&mut ctx, address).await;
+    }
+
+    send(&mut ctx, &[mixer::close_pool(&authority.pubkey(), POOL, 1, treasury)], &[&authority])
+        .await
+        .unwrap();
+    for address in tree_accounts(1) {
+        assert_eq!(lamports(&mut ctx, &address).await, 0);
+    }
+    let remaining: Pool = fetch(&mut ctx, &pool).await;
+    assert_eq!(remaining.current_tree, 0);
+
+    send(&mut ctx, &[mixer::close_pool(&authority.pubkey(), POOL, 0, treasury)], &[&authority])
+        .await
+        .unwrap();
+    for address in tree_accounts(0).iter().chain(&pool_accounts) {
+        assert_eq!(lamports(&mut ctx, address).await, 0);
+    }
+    assert_eq!(lamports(&mut ctx, &treasury).await, reclaimable);
+}
+
+#[tokio::test]
+async fn only_the_authority_closes_a_pool() {
+    let (mut ctx, _authority) = start_drained().await;
+    let intruder = Keypair::new();
+
+    let result = send(
+        &mut ctx,
+        &[mixer::close_pool(&intruder.pubkey(), POOL, 1, intruder.pubkey())],
+        &[&intruder],
+    )
+    .await;
+    assert_error(result, MixerError::Unauthorized);
+}
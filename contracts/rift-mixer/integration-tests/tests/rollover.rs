@@ -0,0 +1,217 @@
+// Pools move on to a new Merkle tree when one fills; notes in earlier trees
+// stay withdrawable against those trees' roots
+
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use common::mixer::{self, merkle_tree_address, pool_address, FULL_TREE_LEAVES};
+use common::{assert_error, fetch, funded_keypair, program_test, send, send_with_logs};
+use rift_mixer::{
+    compute_merkle_root, MerkleTree, MixerError, Pool, PoolType, TreeOpened, MERKLE_TREE_HEIGHT,
+};
+use rift_sdk::events::{deposit_events, parse_events};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SOL: u64 = 1_000_000_000;
+const POOL: PoolType = PoolType::Sol0_1;
+
+async fn start(preload: fn(&mut ProgramTest, PoolType, Pubkey)) -> (ProgramTestContext, Keypair) {
+    let mut program_test = program_test();
+    preload(&mut program_test, POOL, Pubkey::new_unique());
+    let mut ctx = program_test.start_with_context().await;
+    let depositor = funded_keypair(&mut ctx, 5 * SOL).await;
+    (ctx, depositor)
+}
+
+/// Root of the fixture's full tree 0
+fn first_tree_root() -> [u8; 32] {
+    let leaves: Vec<_> = (0..FULL_TREE_LEAVES).map(mixer::numbered).collect();
+    compute_merkle_root(&leaves, MERKLE_TREE_HEIGHT)
+}
+
+#[tokio::test]
+async fn full_tree_rejects_deposits() {
+    let (mut ctx, depositor) = start(mixer::preload_full_pool).await;
+
+    let single = send(
+        &mut ctx,
+        &[mixer::deposit(&depositor.pubkey(), POOL, [1u8; 32])],
+        &[&depositor],
+    )
+    .await;
+    assert_error(single, MixerError::PoolFull);
+    let batch = send(
+        &mut ctx,
+        &[mixer::deposit_batch(&depositor.pubkey(), POOL, vec![[1u8; 32], [2u8; 32]])],
+        &[&depositor],
+    )
+    .await;
+    assert_error(batch, MixerError::PoolFull);
+}
+
+#[tokio::test]
+async fn deposits_go_into_the_current_tree() {
+    let (mut ctx, depositor) = start(mixer::preload_rolled_over_pool).await;
+    let pool_key = pool_address(&POOL);
+
+    // Tree 0 is no longer the one deposits name
+    let stale = send(
+        &mut ctx,
+        &[mixer::deposit(&depositor.pubkey(), POOL, [1u8; 32])],
+        &[&depositor],
+    )
+    .await;
+    assert_error(stale, ErrorCode::ConstraintSeeds);
+
+    let (result, logs) = send_with_logs(
+        &mut ctx,
+        &[mixer::deposit_into_tree(&depositor.pubkey(), POOL, 1, [1u8; 32])],
+        &[&depositor],
+    )
+    .await;
+    result.unwrap();
+
+    let events = deposit_events(&logs);
+    assert_eq!((events[0].tree_index, events[0].leaf_index), (1, 0));
+    let pool: Pool = fetch(&mut ctx, &pool_key).await;
+    assert_eq!(pool.current_tree, 1);
+    assert_eq!(pool.next_index, FULL_TREE_LEAVES as u64 + 1);
+    assert_eq!(pool.merkle_root, compute_merkle_root(&[[1u8; 32]], MERKLE_TREE_HEIGHT));
+    let tree: MerkleTree = fetch(&mut ctx, &merkle_tree_address(&pool_key, 1)).await;
+    assert_eq!(tree.leaves, vec![[1u8; 32]]);
+}
+
+#[tokio::test]
+async fn withdrawals_name_the_tree_they_prove_against() {
+    let (mut ctx, depositor) = start(mixer::preload_rolled_over_pool).await;
+    send(
+        &mut ctx,
+        &[mixer::deposit_into_tree(&depositor.pubkey(), POOL, 1, [1u8; 32])],
+        &[&depositor],
+    )
+    .await
+    .unwrap();
+    let second_root = mixer::current_root(&mut ctx, &POOL).await;
+
+    // Each root is only known to its own tree's history
+    let wrong_tree = send(
+        &mut ctx,
+        &[mixer::withdraw_from_tree(POOL, 1, first_tree_root(), [8u8; 32], Pubkey::new_unique())],
+        &[],
+    )
+    .await;
+    assert_error(wrong_tree, MixerError::UnknownRoot);
+
+    send(
+        &mut ctx,
+        &[
+            mixer::withdraw_from_tree(POOL, 0, first_tree_root(), [8u8; 32], Pubkey::new_unique()),
+            mixer::withdraw_from_tree(POOL, 1, second_root, [9u8; 32], Pubkey::new_unique()),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+    let pool: Pool = fetch(&mut ctx, &pool_address(&POOL)).await;
+    assert_eq!(pool.total_withdrawals, 2);
+}
+
+#[tokio::test]
+async fn full_pool_moves_on_once_the_next_tree_has_grown() {
+    let (mut ctx, depositor) = start(mixer::preload_full_pool).await;
+    let payer = ctx.payer.pubkey();
+    let next_tree = merkle_tree_address(&pool_address(&POOL), 1);
+
+    send(&mut ctx, &[mixer::open_tree(&payer, POOL, 0)], &[]).await.unwrap();
+    assert_eq!(account_len(&mut ctx, &next_tree).await, MAX_PERMITTED_DATA_INCREASE);
+
+    // Deposits stay refused until the new tree reaches full size
+    let full_len = 8 + MerkleTree::SIZE;
+    let grows = full_len.div_ceil(MAX_PERMITTED_DATA_INCREASE) - 1;
+    let growing = vec![mixer::grow_tree(&payer, POOL, 1); grows - 1];
+    send(&mut ctx, &growing, &[]).await.unwrap();
+    let pool: Pool = fetch(&mut ctx, &pool_address(&POOL)).await;
+    assert_eq!(pool.current_tree, 0);
+    let refused = send(
+        &mut ctx,
+        &[mixer::deposit(&depositor.pubkey(), POOL, [1u8; 32])],
+        &[&depositor],
+    )
+    .await;
+    assert_error(refused, MixerError::PoolFull);
+
+    let (result, logs) = send_with_logs(&mut ctx, &[mixer::grow_tree(&payer, POOL, 1)], &[]).await;
+    result.unwrap();
+    assert_eq!(parse_events::<TreeOpened>(&logs)[0].tree_index, 1);
+    assert_eq!(account_len(&mut ctx, &next_tree).await, full_len);
+    let pool: Pool = fetch(&mut ctx, &pool_address(&POOL)).await;
+    assert_eq!(pool.current_tree, 1);
+
+    send(
+        &mut ctx,
+        &[mixer::deposit_into_tree(&depositor.pubkey(), POOL, 1, [1u8; 32])],
+        &[&depositor],
+    )
+    .await
+    .unwrap();
+    let tree: MerkleTree = fetch(&mut ctx, &next_tree).await;
+    assert_eq!(tree.leaves, vec![[1u8; 32]]);
+}
+
+#[tokio::test]
+async fn fresh_pool_grows_its_first_tree_in_place() {
+    let mut ctx = program_test().start_with_context().await;
+    let payer = ctx.payer.pubkey();
+    let first_tree = merkle_tree_address(&pool_address(&POOL), 0);
+    let depositor = funded_keypair(&mut ctx, 5 * SOL).await;
+    let deposit = |commitment| mixer::deposit(&depositor.pubkey(), POOL, commitment);
+
+    send(&mut ctx, &[mixer::initialize(&payer, POOL)], &[]).await.unwrap();
+    assert_eq!(account_len(&mut ctx, &first_tree).await, MAX_PERMITTED_DATA_INCREASE);
+    send(&mut ctx, &[deposit([1u8; 32])], &[&depositor]).await.unwrap();
+
+    let full_len = 8 + MerkleTree::SIZE;
+    let grows = full_len.div_ceil(MAX_PERMITTED_DATA_INCREASE) - 1;
+    let (result, logs) = send_with_logs(
+        &mut ctx,
+        &vec![mixer::grow_tree(&payer, POOL, 0); grows + 1],
+        &[],
+    )
+    .await;
+    result.unwrap();
+    assert!(parse_events::<TreeOpened>(&logs).is_empty());
+    assert_eq!(account_len(&mut ctx, &first_tree).await, full_len);
+    let pool: Pool = fetch(&mut ctx, &pool_address(&POOL)).await;
+    assert_eq!(pool.current_tree, 0);
+
+    send(&mut ctx, &[deposit([2u8; 32])], &[&depositor]).await.unwrap();
+    let tree: MerkleTree = fetch(&mut ctx, &first_tree).await;
+    assert_eq!(tree.leaves, vec![[1u8; 32], [2u8; 32]]);
+}
+
+#[tokio::test]
+async fn open_tree_needs_a_full_tree_in_a_live_pool() {
+    let authority = Keypair::new();
+    let mut program_test = program_test();
+    mixer::preload_full_pool(&mut program_test, POOL, authority.pubkey());
+    mixer::preload_pool(&mut program_test, PoolType::Sol1, Pubkey::new_unique());
+    let mut ctx = program_test.start_with_context().await;
+    let payer = ctx.payer.pubkey();
+
+    let not_full = send(&mut ctx, &[mixer::open_tree(&payer, PoolType::Sol1, 0)], &[]).await;
+    assert_error(not_full, MixerError::TreeNotFull);
+
+    send(&mut ctx, &[mixer::retire_pool(&authority.pubkey(), POOL)], &[&authority])
+        .await
+        .unwrap();
+    let retired = send(&mut ctx, &[mixer::open_tree(&payer, POOL, 0)], &[]).await;
+    assert_error(retired, MixerError::PoolRetired);
+}
+
+async fn account_len(ctx: &mut ProgramTestContext, address: &Pubkey) -> usize {
+    let account = ctx.banks_client.get_account(*address).await.unwrap();
+    account.expect("account exists").data.len()
+}
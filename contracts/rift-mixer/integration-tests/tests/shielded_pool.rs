@@ -0,0 +1,500 @@
+// Join-split transactions of any amount on the shielded pool
+
+mod common;
+
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use common::mixer::{
+    self, merkle_tree_address, nullifier_registry_address, numbered, root_history_address,
+    vault_address,
+};
+use common::shielded::{self, ext_data, shielded_pool_address};
+use common::{assert_error, fetch, funded_keypair, lamports, program_test, send, send_with_logs};
+use rift_mixer::{
+    compute_merkle_root, hash_ext_data, zero_hashes, MerkleTree, MixerError, NullifierRegistry,
+    RootHistory, ShieldedPool, DEFAULT_SHIELDED_MAX_TVL, MERKLE_TREE_HEIGHT,
+};
+use rift_sdk::events::transact_events;
+use rift_sdk::{receive_notes, ShieldedNote, ViewingKey};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SOL: i64 = 1_000_000_000;
+
+/// Notes already in the pool's tree, backed by 3 SOL in the vault
+const NOTES: [[u8; 32]; 2] = [[1u8; 32], [2u8; 32]];
+
+async fn start() -> (ProgramTestContext, Keypair, [u8; 32]) {
+    let (ctx, sender, root, _authority) = start_managed().await;
+    (ctx, sender, root)
+}
+
+/// `start`, also returning the pool's authority
+async fn start_managed() -> (ProgramTestContext, Keypair, [u8; 32], Keypair) {
+    let mut program_test = program_test();
+    let authority = Keypair::new();
+    shielded::preload_shielded_pool(&mut program_test, authority.pubkey(), &NOTES, 3 * SOL as u64);
+    let mut ctx = program_test.start_with_context().await;
+    let sender = funded_keypair(&mut ctx, 5 * SOL as u64).await;
+    (ctx, sender, compute_merkle_root(&NOTES, MERKLE_TREE_HEIGHT), authority)
+}
+
+async fn vault_balance(ctx: &mut ProgramTestContext) -> u64 {
+    lamports(ctx, &vault_address(&shielded_pool_address())).await
+}
+
+#[tokio::test]
+async fn deposit_of_any_amount() {
+    let (mut ctx, sender, root) = start().await;
+    let vault_before = vault_balance(&mut ctx).await;
+    let outputs = [numbered(10), numbered(11)];
+
+    send(
+        &mut ctx,
+        &[shielded::transact(
+            &sender.pubkey(),
+            root,
+            [numbered(0), numbered(1)],
+            outputs,
+            ext_data(sender.pubkey(), 1_250_000_000, sender.pubkey(), 0),
+        )],
+        &[&sender],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(vault_balance(&mut ctx).await - vault_before, 1_250_000_000);
+    let pool_key = shielded_pool_address();
+    let pool: ShieldedPool = fetch(&mut ctx, &pool_key).await;
+    let leaves = [NOTES[0], NOTES[1], outputs[0], outputs[1]];
+    assert_eq!(pool.next_index, 4);
+    assert_eq!(pool.merkle_root, compute_merkle_root(&leaves, MERKLE_TREE_HEIGHT));
+    let tree: MerkleTree = fetch(&mut ctx, &merkle_tree_address(&pool_key, 0)).await;
+    assert_eq!(tree.leaves, leaves);
+    let history: RootHistory = fetch(&mut ctx, &root_history_address(&pool_key, 0)).await;
+    assert!(history.find(&pool.merkle_root).is_some());
+    let registry: NullifierRegistry =
+        fetch(&mut ctx, &nullifier_registry_address(&pool_key)).await;
+    assert_eq!(registry.used_nullifiers, vec![numbered(0), numbered(1)]);
+}
+
+#[tokio::test]
+async fn withdrawal_pays_the_recipient_and_relayer() {
+    let (mut ctx, sender, root) = start().await;
+    let vault_before = vault_balance(&mut ctx).await;
+    let (recipient, relayer) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    send(
+        &mut ctx,
+        &[shielded::transact(
+            &sender.pubkey(),
+            root,
+            [numbered(0), numbered(1)],
+            [numbered(10), numbered(11)],
+            ext_data(recipient, -2 * SOL, relayer, 10_000_000),
+        )],
+        &[&sender],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(lamports(&mut ctx, &recipient).await, 2 * SOL as u64);
+    assert_eq!(lamports(&mut ctx, &relayer).await, 10_000_000);
+    assert_eq!(vault_before - vault_balance(&mut ctx).await, 2_010_000_000);
+}
+
+#[tokio::test]
+async fn transfer_inside_the_pool_moves_no_sol() {
+    let (mut ctx, sender, root) = start().await;
+    let vault_before = vault_balance(&mut ctx).await;
+
+    send(
+        &mut ctx,
+        &[shielded::transact(
+            &sender.pubkey(),
+            root,
+            [numbered(0), numbered(1)],
+            [numbered(10), numbered(11)],
+            ext_data(sender.pubkey(), 0, sender.pubkey(), 0),
+        )],
+        &[&sender],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(vault_balance(&mut ctx).await, vault_before);
+    let pool: ShieldedPool = fetch(&mut ctx, &shielded_pool_address()).await;
+    assert_eq!((pool.next_index, pool.total_transactions), (4, 1));
+}
+
+#[tokio::test]
+async fn private_transfer_is_readable_by_its_recipient() {
+    let (mut ctx, sender, root) = start().await;
+    let vault_before = vault_balance(&mut ctx).await;
+    let alice = ViewingKey::from_seed(b"alice");
+    let bob = ViewingKey::from_seed(b"bob");
+    let relayer = Pubkey::new_unique();
+    // Alice pays Bob and keeps the change; the relayer's fee is the only SOL
+    // that moves
+    let outputs = [
+        ShieldedNote::new(700_000_000, bob.public_key()),
+        ShieldedNote::new(290_000_000, alice.public_key()),
+    ];
+
+    let (result, logs) = send_with_logs(
+        &mut ctx,
+        &[shielded::transact(
+            &sender.pubkey(),
+            root,
+            [numbered(0), numbered(1)],
+            [outputs[0].commitment(), outputs[1].commitment()],
+            rift_sdk::shielded::ext_data(sender.pubkey(), 0, relayer, 10_000_000, &outputs),
+        )],
+        &[&sender],
+    )
+    .await;
+    result.unwrap();
+
+    assert_eq!(vault_before - vault_balance(&mut ctx).await, 10_000_000);
+    assert_eq!(lamports(&mut ctx, &relayer).await, 10_000_000);
+    let events = transact_events(&logs);
+    let received = receive_notes(&bob, &events);
+    assert_eq!(received.len(), 1);
+    assert_eq!((&received[0].note, received[0].leaf_index), (&outputs[0], 2));
+    let change = receive_notes(&alice, &events);
+    assert_eq!((&change[0].note, change[0].leaf_index), (&outputs[1], 3));
+    assert!(receive_notes(&ViewingKey::from_seed(b"eve"), &events).is_empty());
+}
+
+#[tokio::test]
+async fn inputs_are_spent_once() {
+    let (mut ctx, sender, root) = start().await;
+    let transfer = |nullifiers, outputs| {
+        shielded::transact(
+            &sender.pubkey(),
+            root,
+            nullifiers,
+            outputs,
+            ext_data(sender.pubkey(), 0, sender.pubkey(), 0),
+        )
+    };
+
+    let same_twice = transfer([numbered(0), numbered(0)], [numbered(10), numbered(11)]);
+    let result = send(&mut ctx, &[same_twice], &[&sender]).await;
+    assert_error(result, MixerError::NullifierAlreadyUsed);
+
+    let first = transfer([numbered(0), numbered(1)], [numbered(10), numbered(11)]);
+    send(&mut ctx, &[first], &[&sender]).await.unwrap();
+    let again = transfer([numbered(1), numbered(2)], [numbered(12), numbered(13)]);
+    let result = send(&mut ctx, &[again], &[&sender]).await;
+    assert_error(result, MixerError::NullifierAlreadyUsed);
+
+    let repeated_output = transfer([numbered(2), numbered(3)], [numbered(10), numbered(14)]);
+    let result = send(&mut ctx, &[repeated_output], &[&sender]).await;
+    assert_error(result, MixerError::CommitmentAlreadyUsed);
+}
+
+#[tokio::test]
+async fn ext_data_is_bound_to_the_proof() {
+    let (mut ctx, sender, root) = start().await;
+    let nullifiers = [numbered(0), numbered(1)];
+    let outputs = [numbered(10), numbered(11)];
+    let relayer = Pubkey::new_unique();
+    let signed = ext_data(Pubkey::new_unique(), -SOL, relayer, 0);
+
+    // A relayer redirecting the withdrawal no longer matches the hash
+    let redirected = shielded::transact_with(
+        &sender.pubkey(),
+        root,
+        nullifiers,
+        outputs,
+        -SOL,
+        hash_ext_data(&signed),
+        ext_data(sender.pubkey(), -SOL, relayer, 0),
+    );
+    let result = send(&mut ctx, &[redirected], &[&sender]).await;
+    assert_error(result, MixerError::InvalidExtData);
+
+    // The public amount must be ext_amount - fee
+    let overpaid = shielded::transact_with(
+        &sender.pubkey(),
+        root,
+        nullifiers,
+        outputs,
+        0,
+        hash_ext_data(&signed),
+        signed.clone(),
+    );
+    let result = send(&mut ctx, &[overpaid], &[&sender]).await;
+    assert_error(result, MixerError::InvalidPublicAmount);
+    let unbalanced = ext_data(sender.pubkey(), -SOL, relayer, u64::MAX);
+    let overpaid = shielded::transact_with(
+        &sender.pubkey(),
+        root,
+        nullifiers,
+        outputs,
+        -SOL,
+        hash_ext_data(&unbalanced),
+        unbalanced,
+    );
+    let result = send(&mut ctx, &[overpaid], &[&sender]).await;
+    assert_error(result, MixerError::InvalidPublicAmount);
+
+    let unknown_root = shielded::transact(&sender.pubkey(), [7u8; 32], nullifiers, outputs, signed);
+    let result = send(&mut ctx, &[unknown_root], &[&sender]).await;
+    assert_error(result, MixerError::UnknownRoot);
+}
+
+#[tokio::test]
+async fn only_the_deployment_authority_creates_the_pool() {
+    let deployer = Keypair::new();
+    let mut program_test = program_test();
+    mixer::preload_program_data(&mut program_test, deployer.pubkey());
+    let mut ctx = program_test.start_with_context().await;
+    let payer = ctx.payer.pubkey();
+    let authority = Pubkey::new_unique();
+    let stranger = Keypair::new();
+
+    let result = send(
+        &mut ctx,
+        &[shielded::initialize_shielded_pool(&stranger.pubkey(), &payer, stranger.pubkey())],
+        &[&stranger],
+    )
+    .await;
+    assert_error(result, MixerError::Unauthorized);
+
+    send(
+        &mut ctx,
+        &[shielded::initialize_shielded_pool(&deployer.pubkey(), &payer, authority)],
+        &[&deployer],
+    )
+    .await
+    .unwrap();
+    let pool: ShieldedPool = fetch(&mut ctx, &shielded_pool_address()).await;
+    assert_eq!((pool.authority, pool.max_tvl), (authority, DEFAULT_SHIELDED_MAX_TVL));
+
+    // The tree starts at what one instruction may allocate and grows from there
+    let tree = merkle_tree_address(&shielded_pool_address(), 0);
+    assert_eq!(account_len(&mut ctx, &tree).await, MAX_PERMITTED_DATA_INCREASE);
+    let full_len = 8 + MerkleTree::SIZE;
+    let grows = vec![shielded::grow_shielded_tree(&payer, 0); full_len / MAX_PERMITTED_DATA_INCREASE];
+    send(&mut ctx, &grows, &[]).await.unwrap();
+    assert_eq!(account_len(&mut ctx, &tree).await, full_len);
+}
+
+#[tokio::test]
+async fn created_pool_takes_transactions() {
+    let deployer = Keypair::new();
+    let mut program_test = program_test();
+    mixer::preload_program_data(&mut program_test, deployer.pubkey());
+    let mut ctx = program_test.start_with_context().await;
+    let payer = ctx.payer.pubkey();
+    let sender = funded_keypair(&mut ctx, 5 * SOL as u64).await;
+    send(
+        &mut ctx,
+        &[shielded::initialize_shielded_pool(&deployer.pubkey(), &payer, Pubkey::new_unique())],
+        &[&deployer],
+    )
+    .await
+    .unwrap();
+    let pool_key = shielded_pool_address();
+    let registry = nullifier_registry_address(&pool_key);
+    let vault = ctx.banks_client.get_account(vault_address(&pool_key)).await.unwrap();
+    assert_eq!(vault.map(|vault| vault.owner), Some(rift_mixer::ID));
+    assert_eq!(account_len(&mut ctx, &registry).await, 8 + NullifierRegistry::space_for(0));
+
+    // The first deposit spends dummy notes against the empty tree, and the
+    // first withdrawal spends what it created
+    let empty_root = zero_hashes(MERKLE_TREE_HEIGHT)[MERKLE_TREE_HEIGHT];
+    let deposit = shielded::transact(
+        &sender.pubkey(),
+        empty_root,
+        [numbered(0), numbered(1)],
+        [numbered(10), numbered(11)],
+        ext_data(sender.pubkey(), 2 * SOL, sender.pubkey(), 0),
+    );
+    send(&mut ctx, &[deposit], &[&sender]).await.unwrap();
+    let pool: ShieldedPool = fetch(&mut ctx, &pool_key).await;
+    let recipient = Pubkey::new_unique();
+    let withdrawal = shielded::transact(
+        &sender.pubkey(),
+        pool.merkle_root,
+        [numbered(10), numbered(11)],
+        [numbered(12), numbered(13)],
+        ext_data(recipient, -2 * SOL, sender.pubkey(), 0),
+    );
+    send(&mut ctx, &[withdrawal], &[&sender]).await.unwrap();
+
+    assert_eq!(lamports(&mut ctx, &recipient).await, 2 * SOL as u64);
+    assert_eq!(account_len(&mut ctx, &registry).await, 8 + NullifierRegistry::space_for(4));
+    let registry: NullifierRegistry = fetch(&mut ctx, &registry).await;
+    assert_eq!(registry.used_nullifiers.len(), 4);
+}
+
+#[tokio::test]
+async fn full_pool_moves_on_to_its_next_tree() {
+    let mut program_test = program_test();
+    shielded::preload_full_shielded_pool(
+        &mut program_test,
+        Pubkey::new_unique(),
+        &NOTES,
+        3 * SOL as u64,
+    );
+    let mut ctx = program_test.start_with_context().await;
+    let payer = ctx.payer.pubkey();
+    let sender = funded_keypair(&mut ctx, 5 * SOL as u64).await;
+    let pool_key = shielded_pool_address();
+    let first_root = compute_merkle_root(&NOTES, MERKLE_TREE_HEIGHT);
+    let outputs = [numbered(10), numbered(11)];
+    let transfer = |tree_index, current_tree, root| {
+        shielded::transact_in_tree(
+            &sender.pubkey(),
+            tree_index,
+            current_tree,
+            root,
+            [numbered(0), numbered(1)],
+            outputs,
+            ext_data(sender.pubkey(), 0, sender.pubkey(), 0),
+        )
+    };
+
+    let full = send(&mut ctx, &[transfer(0, 0, first_root)], &[&sender]).await;
+    assert_error(full, MixerError::PoolFull);
+
+    // Outputs stay refused until the next tree reaches full size
+    send(&mut ctx, &[shielded::open_shielded_tree(&payer, 0)], &[]).await.unwrap();
+    let full_len = 8 + MerkleTree::SIZE;
+    let grows = full_len.div_ceil(MAX_PERMITTED_DATA_INCREASE) - 1;
+    send(&mut ctx, &vec![shielded::grow_shielded_tree(&payer, 1); grows - 1], &[])
+        .await
+        .unwrap();
+    let full = send(&mut ctx, &[transfer(0, 0, first_root)], &[&sender]).await;
+    assert_error(full, MixerError::PoolFull);
+    send(&mut ctx, &[shielded::grow_shielded_tree(&payer, 1)], &[]).await.unwrap();
+    let pool: ShieldedPool = fetch(&mut ctx, &pool_key).await;
+    assert_eq!(pool.current_tree, 1);
+
+    // Tree 0's root proves only for tree 0
+    let wrong_tree = send(&mut ctx, &[transfer(1, 1, first_root)], &[&sender]).await;
+    assert_error(wrong_tree, MixerError::UnknownRoot);
+
+    // Notes of the full tree are spent into the new one
+    let (result, logs) = send_with_logs(&mut ctx, &[transfer(0, 1, first_root)], &[&sender]).await;
+    result.unwrap();
+    let events = transact_events(&logs);
+    assert_eq!((events[0].tree_index, events[0].first_leaf_index), (1, 0));
+    let tree: MerkleTree = fetch(&mut ctx, &merkle_tree_address(&pool_key, 1)).await;
+    assert_eq!(tree.leaves, outputs);
+    let pool: ShieldedPool = fetch(&mut ctx, &pool_key).await;
+    assert_eq!(pool.merkle_root, compute_merkle_root(&outputs, MERKLE_TREE_HEIGHT));
+    let history: RootHistory = fetch(&mut ctx, &root_history_address(&pool_key, 1)).await;
+    assert!(history.find(&pool.merkle_root).is_some());
+
+    let not_full = send(&mut ctx, &[shielded::open_shielded_tree(&payer, 1)], &[]).await;
+    assert_error(not_full, MixerError::TreeNotFull);
+}
+
+#[tokio::test]
+async fn deposits_are_capped_like_fixed_pools() {
+    let (mut ctx, sender, root, authority) = start_managed().await;
+    let vault = vault_balance(&mut ctx).await;
+    let deposit = |n: usize, amount| {
+        shielded::transact(
+            &sender.pubkey(),
+            root,
+            [numbered(n), numbered(n + 1)],
+            [numbered(n + 10), numbered(n + 11)],
+            ext_data(sender.pubkey(), amount, sender.pubkey(), 0),
+        )
+    };
+
+    let result = send(
+        &mut ctx,
+        &[shielded::set_shielded_pool_caps(&sender.pubkey(), u64::MAX, u64::MAX)],
+        &[&sender],
+    )
+    .await;
+    assert_error(result, MixerError::Unauthorized);
+    send(
+        &mut ctx,
+        &[shielded::set_shielded_pool_caps(&authority.pubkey(), vault + 2 * SOL as u64, 1)],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+
+    let over_tvl = send(&mut ctx, &[deposit(0, 2 * SOL + 1)], &[&sender]).await;
+    assert_error(over_tvl, MixerError::TvlCapExceeded);
+    send(&mut ctx, &[deposit(0, SOL)], &[&sender]).await.unwrap();
+    let over_epoch = send(&mut ctx, &[deposit(2, 1)], &[&sender]).await;
+    assert_error(over_epoch, MixerError::EpochDepositCapExceeded);
+
+    // Transfers and withdrawals are not deposits
+    send(&mut ctx, &[deposit(2, 0)], &[&sender]).await.unwrap();
+    send(&mut ctx, &[deposit(4, -SOL)], &[&sender]).await.unwrap();
+}
+
+#[tokio::test]
+async fn retirement_stops_deposits_and_emergency_stops_everything() {
+    let (mut ctx, sender, root, authority) = start_managed().await;
+    let transact = |n: usize, ext_amount| {
+        shielded::transact(
+            &sender.pubkey(),
+            root,
+            [numbered(n), numbered(n + 1)],
+            [numbered(n + 10), numbered(n + 11)],
+            ext_data(sender.pubkey(), ext_amount, sender.pubkey(), 0),
+        )
+    };
+
+    send(&mut ctx, &[shielded::retire_shielded_pool(&authority.pubkey())], &[&authority])
+        .await
+        .unwrap();
+    let deposit = send(&mut ctx, &[transact(0, SOL)], &[&sender]).await;
+    assert_error(deposit, MixerError::PoolRetired);
+    send(&mut ctx, &[transact(0, -SOL)], &[&sender]).await.unwrap();
+
+    send(
+        &mut ctx,
+        &[shielded::enter_shielded_emergency_mode(&authority.pubkey())],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    let withdrawal = send(&mut ctx, &[transact(2, -SOL)], &[&sender]).await;
+    assert_error(withdrawal, MixerError::PoolInEmergency);
+    let transfer = send(&mut ctx, &[transact(2, 0)], &[&sender]).await;
+    assert_error(transfer, MixerError::PoolInEmergency);
+}
+
+#[tokio::test]
+async fn proofs_wait_out_the_min_leaf_age() {
+    let (mut ctx, sender, root, authority) = start_managed().await;
+    let transfer = || {
+        shielded::transact(
+            &sender.pubkey(),
+            root,
+            [numbered(0), numbered(1)],
+            [numbered(10), numbered(11)],
+            ext_data(sender.pubkey(), 0, sender.pubkey(), 0),
+        )
+    };
+    send(
+        &mut ctx,
+        &[shielded::set_shielded_min_leaf_age(&authority.pubkey(), 100, 0)],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+
+    // The preloaded root was recorded at slot 0
+    let early = send(&mut ctx, &[transfer()], &[&sender]).await;
+    assert_error(early, MixerError::RootTooYoung);
+    ctx.warp_to_slot(100).unwrap();
+    send(&mut ctx, &[transfer()], &[&sender]).await.unwrap();
+}
+
+async fn account_len(ctx: &mut ProgramTestContext, address: &Pubkey) -> usize {
+    let account = ctx.banks_client.get_account(*address).await.unwrap();
+    account.expect("account exists").data.len()
+}
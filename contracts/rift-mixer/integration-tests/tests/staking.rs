@@ -0,0 +1,166 @@
+// rift_staking: stake, claim, unstake and relayer registration
+
+mod common;
+
+use common::staking::{self, relayer_address, stake_address, staking_pool_address, vault_address};
+use common::{
+    add_program_owned_account, assert_error, fetch, funded_keypair, lamports, program_test, send,
+};
+use rift_staking::{
+    RelayerAccount, StakeAccount, StakingError, StakingPool, StakingTier, LOCKUP_DURATION,
+};
+use rift_test_utils::clock::warp_seconds;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+const SOL: u64 = 1_000_000_000;
+
+/// Reward budget pre-loaded into the vault
+const VAULT_RESERVE: u64 = 1_000 * SOL;
+
+/// `initialize` does not create the vault (see `known_holes.rs`), so these
+/// tests pre-load it as a program-owned account holding a reward reserve.
+async fn start() -> ProgramTestContext {
+    let mut program_test = program_test();
+    add_program_owned_account(
+        &mut program_test,
+        vault_address(),
+        rift_staking::ID,
+        VAULT_RESERVE,
+    );
+    let mut ctx = program_test.start_with_context().await;
+    let authority = ctx.payer.pubkey();
+    send(&mut ctx, &[staking::initialize(&authority)], &[])
+        .await
+        .unwrap();
+    ctx
+}
+
+#[tokio::test]
+async fn stake_records_position_and_tier() {
+    let mut ctx = start().await;
+    let staker = funded_keypair(&mut ctx, 20 * SOL).await;
+
+    send(&mut ctx, &[staking::stake(&staker.pubkey(), 10 * SOL)], &[&staker])
+        .await
+        .unwrap();
+
+    let stake: StakeAccount = fetch(&mut ctx, &stake_address(&staker.pubkey())).await;
+    assert_eq!(stake.owner, staker.pubkey());
+    assert_eq!(stake.amount, 10 * SOL);
+    assert!(stake.tier == StakingTier::Bronze);
+    assert_eq!(stake.lockup_ends_at - stake.staked_at, LOCKUP_DURATION);
+
+    let pool: StakingPool = fetch(&mut ctx, &staking_pool_address()).await;
+    assert_eq!(pool.total_staked, 10 * SOL);
+    assert_eq!(pool.total_stakers, 1);
+}
+
+#[tokio::test]
+async fn stake_amount_limits_are_enforced() {
+    let mut ctx = start().await;
+    let staker = funded_keypair(&mut ctx, 2_000 * SOL).await;
+
+    let too_low = send(&mut ctx, &[staking::stake(&staker.pubkey(), SOL / 100)], &[&staker]).await;
+    assert_error(too_low, StakingError::AmountTooLow);
+
+    let too_high = send(
+        &mut ctx,
+        &[staking::stake(&staker.pubkey(), 1_001 * SOL)],
+        &[&staker],
+    )
+    .await;
+    assert_error(too_high, StakingError::AmountTooHigh);
+}
+
+#[tokio::test]
+async fn staking_lifecycle() {
+    let mut ctx = start().await;
+    let staker = funded_keypair(&mut ctx, 20 * SOL).await;
+    send(&mut ctx, &[staking::stake(&staker.pubkey(), 10 * SOL)], &[&staker])
+        .await
+        .unwrap();
+
+    let claim = send(&mut ctx, &[staking::claim_rewards(&staker.pubkey())], &[&staker]).await;
+    assert_error(claim, StakingError::NoRewardsToClaim);
+    let unstake = send(&mut ctx, &[staking::unstake(&staker.pubkey())], &[&staker]).await;
+    assert_error(unstake, StakingError::StillLocked);
+
+    warp_seconds(&mut ctx, LOCKUP_DURATION).await;
+
+    let before_claim = lamports(&mut ctx, &staker.pubkey()).await;
+    send(&mut ctx, &[staking::claim_rewards(&staker.pubkey())], &[&staker])
+        .await
+        .unwrap();
+    let stake: StakeAccount = fetch(&mut ctx, &stake_address(&staker.pubkey())).await;
+    assert!(stake.total_claimed > 0);
+    assert!(lamports(&mut ctx, &staker.pubkey()).await > before_claim);
+
+    let before_unstake = lamports(&mut ctx, &staker.pubkey()).await;
+    send(&mut ctx, &[staking::unstake(&staker.pubkey())], &[&staker])
+        .await
+        .unwrap();
+    assert!(lamports(&mut ctx, &staker.pubkey()).await >= before_unstake + 10 * SOL - SOL / 100);
+
+    let stake: StakeAccount = fetch(&mut ctx, &stake_address(&staker.pubkey())).await;
+    assert_eq!(stake.amount, 0);
+    let pool: StakingPool = fetch(&mut ctx, &staking_pool_address()).await;
+    assert_eq!(pool.total_staked, 0);
+    assert_eq!(pool.total_stakers, 0);
+}
+
+#[tokio::test]
+async fn relayer_registration_and_slashing() {
+    let mut ctx = start().await;
+    let staker = funded_keypair(&mut ctx, 150 * SOL).await;
+
+    send(&mut ctx, &[staking::stake(&staker.pubkey(), 50 * SOL)], &[&staker])
+        .await
+        .unwrap();
+    let too_small = send(
+        &mut ctx,
+        &[staking::register_relayer(&staker.pubkey(), 30)],
+        &[&staker],
+    )
+    .await;
+    assert_error(too_small, StakingError::InsufficientStakeForRelayer);
+
+    send(&mut ctx, &[staking::stake(&staker.pubkey(), 50 * SOL)], &[&staker])
+        .await
+        .unwrap();
+    let fee_too_high = send(
+        &mut ctx,
+        &[staking::register_relayer(&staker.pubkey(), 1_001)],
+        &[&staker],
+    )
+    .await;
+    assert_error(fee_too_high, StakingError::FeeTooHigh);
+
+    send(
+        &mut ctx,
+        &[staking::register_relayer(&staker.pubkey(), 30)],
+        &[&staker],
+    )
+    .await
+    .unwrap();
+    let relayer: RelayerAccount = fetch(&mut ctx, &relayer_address(&staker.pubkey())).await;
+    assert_eq!(relayer.fee, 30);
+    assert!(relayer.is_active);
+
+    let treasury = Pubkey::new_unique();
+    let authority = ctx.payer.pubkey();
+    send(
+        &mut ctx,
+        &[staking::slash_relayer(&authority, &staker.pubkey(), &treasury)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let relayer: RelayerAccount = fetch(&mut ctx, &relayer_address(&staker.pubkey())).await;
+    assert!(!relayer.is_active);
+    let stake: StakeAccount = fetch(&mut ctx, &stake_address(&staker.pubkey())).await;
+    assert_eq!(stake.amount, 95 * SOL);
+    assert_eq!(lamports(&mut ctx, &treasury).await, 5 * SOL);
+}
@@ -0,0 +1,113 @@
+// Spending a note of one pool into notes of another, vault to vault
+
+mod common;
+
+use common::mixer::{
+    self, merkle_tree_address, nullifier_registry_address, pool_address, vault_address,
+};
+use common::{assert_error, fetch, lamports, program_test, send, send_with_logs};
+use rift_mixer::{
+    compute_merkle_root, MerkleTree, MixerError, NullifierRegistry, Pool, PoolType, SwapEvent,
+    MAX_DEPOSIT_BATCH, MERKLE_TREE_HEIGHT,
+};
+use rift_sdk::events::{deposit_events, parse_events};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::pubkey::Pubkey;
+
+const SOL: u64 = 1_000_000_000;
+
+/// Two 10 SOL notes to swap from, and an empty 1 SOL pool
+async fn start() -> (ProgramTestContext, [u8; 32]) {
+    let mut program_test = program_test();
+    mixer::preload_pool_with_state(&mut program_test, PoolType::Sol10, Pubkey::new_unique(), 2, 0);
+    mixer::preload_pool(&mut program_test, PoolType::Sol1, Pubkey::new_unique());
+    let ctx = program_test.start_with_context().await;
+    let root = compute_merkle_root(&[mixer::numbered(0), mixer::numbered(1)], MERKLE_TREE_HEIGHT);
+    (ctx, root)
+}
+
+fn commitments(count: usize) -> Vec<[u8; 32]> {
+    (100..100 + count).map(mixer::numbered).collect()
+}
+
+#[tokio::test]
+async fn one_large_note_becomes_ten_small_ones() {
+    let (mut ctx, root) = start().await;
+    let (source, target) = (pool_address(&PoolType::Sol10), pool_address(&PoolType::Sol1));
+    let source_vault_before = lamports(&mut ctx, &vault_address(&source)).await;
+    let target_vault_before = lamports(&mut ctx, &vault_address(&target)).await;
+    let notes = commitments(10);
+
+    let (result, logs) = send_with_logs(
+        &mut ctx,
+        &[mixer::swap_denomination(
+            PoolType::Sol10,
+            PoolType::Sol1,
+            root,
+            [9u8; 32],
+            notes.clone(),
+        )],
+        &[],
+    )
+    .await;
+    result.unwrap();
+
+    let source_vault = lamports(&mut ctx, &vault_address(&source)).await;
+    let target_vault = lamports(&mut ctx, &vault_address(&target)).await;
+    assert_eq!(source_vault_before - source_vault, 10 * SOL);
+    assert_eq!(target_vault - target_vault_before, 10 * SOL);
+
+    let from: Pool = fetch(&mut ctx, &source).await;
+    assert_eq!(from.total_withdrawals, 1);
+    let registry: NullifierRegistry = fetch(&mut ctx, &nullifier_registry_address(&source)).await;
+    assert_eq!(registry.used_nullifiers, vec![[9u8; 32]]);
+
+    let to: Pool = fetch(&mut ctx, &target).await;
+    assert_eq!((to.total_deposits, to.next_index), (10, 10));
+    assert_eq!(to.merkle_root, compute_merkle_root(&notes, MERKLE_TREE_HEIGHT));
+    let tree: MerkleTree = fetch(&mut ctx, &merkle_tree_address(&target, 0)).await;
+    assert_eq!(tree.leaves, notes);
+
+    let swap: Vec<SwapEvent> = parse_events(&logs);
+    assert_eq!((swap[0].first_leaf_index, swap[0].count), (0, 10));
+    assert_eq!(swap[0].to_pool_type, PoolType::Sol1);
+    let deposits = deposit_events(&logs);
+    assert_eq!(deposits.len(), 10);
+    assert!(deposits.iter().all(|event| event.pool_type == PoolType::Sol1));
+}
+
+#[tokio::test]
+async fn swap_must_re_deposit_exactly_the_spent_amount() {
+    let (mut ctx, root) = start().await;
+    let swap = |to, notes| mixer::swap_denomination(PoolType::Sol10, to, root, [9u8; 32], notes);
+
+    let short = send(&mut ctx, &[swap(PoolType::Sol1, commitments(9))], &[]).await;
+    assert_error(short, MixerError::InvalidSwap);
+    let same_pool = send(&mut ctx, &[swap(PoolType::Sol10, commitments(1))], &[]).await;
+    assert_error(same_pool, MixerError::InvalidSwap);
+    let too_many = swap(PoolType::Sol1, commitments(MAX_DEPOSIT_BATCH + 1));
+    let result = send(&mut ctx, &[too_many], &[]).await;
+    assert_error(result, MixerError::InvalidBatchSize);
+}
+
+#[tokio::test]
+async fn swapped_note_is_spent() {
+    let (mut ctx, root) = start().await;
+    let swap = |root, notes| {
+        mixer::swap_denomination(PoolType::Sol10, PoolType::Sol1, root, [9u8; 32], notes)
+    };
+
+    let unknown_root = send(&mut ctx, &[swap([7u8; 32], commitments(10))], &[]).await;
+    assert_error(unknown_root, MixerError::UnknownRoot);
+
+    send(&mut ctx, &[swap(root, commitments(10))], &[]).await.unwrap();
+    let again = send(&mut ctx, &[swap(root, (200..210).map(mixer::numbered).collect())], &[]).await;
+    assert_error(again, MixerError::NullifierAlreadyUsed);
+    let withdraw = send(
+        &mut ctx,
+        &[mixer::withdraw_from_tree(PoolType::Sol10, 0, root, [9u8; 32], Pubkey::new_unique())],
+        &[],
+    )
+    .await;
+    assert_error(withdraw, MixerError::NullifierAlreadyUsed);
+}
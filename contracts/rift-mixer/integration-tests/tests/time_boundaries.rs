@@ -0,0 +1,123 @@
+// Time-gated instructions, checked one second either side of each boundary
+
+mod common;
+
+use common::governance::{self, proposal_address};
+use common::staking::{self, stake_address, vault_address};
+use common::{add_program_owned_account, assert_error, fetch, funded_keypair, program_test, send};
+use rift_governance::{GovernanceError, Proposal, VoteType, TIMELOCK_DELAY};
+use rift_staking::{StakeAccount, StakingError};
+use rift_test_utils::clock::{warp_to, warp_to_just_before, SECONDS_PER_DAY};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SOL: u64 = 1_000_000_000;
+
+async fn staked() -> (ProgramTestContext, Keypair, StakeAccount) {
+    let mut program_test = program_test();
+    add_program_owned_account(&mut program_test, vault_address(), rift_staking::ID, 1_000 * SOL);
+    let mut ctx = program_test.start_with_context().await;
+    let authority = ctx.payer.pubkey();
+    send(&mut ctx, &[staking::initialize(&authority)], &[])
+        .await
+        .unwrap();
+
+    let staker = funded_keypair(&mut ctx, 20 * SOL).await;
+    send(&mut ctx, &[staking::stake(&staker.pubkey(), 10 * SOL)], &[&staker])
+        .await
+        .unwrap();
+    let stake = fetch(&mut ctx, &stake_address(&staker.pubkey())).await;
+    (ctx, staker, stake)
+}
+
+async fn proposed() -> (ProgramTestContext, Proposal) {
+    let mut ctx = program_test().start_with_context().await;
+    let authority = ctx.payer.pubkey();
+    send(&mut ctx, &[governance::initialize(&authority, 1_000_000)], &[])
+        .await
+        .unwrap();
+
+    let proposer = funded_keypair(&mut ctx, SOL).await;
+    send(
+        &mut ctx,
+        &[governance::create_proposal(&proposer.pubkey(), 1)],
+        &[&proposer],
+    )
+    .await
+    .unwrap();
+    let voter = funded_keypair(&mut ctx, SOL).await;
+    send(
+        &mut ctx,
+        &[governance::vote(&voter.pubkey(), 1, VoteType::For, 200_000)],
+        &[&voter],
+    )
+    .await
+    .unwrap();
+    let proposal = fetch(&mut ctx, &proposal_address(1)).await;
+    (ctx, proposal)
+}
+
+#[tokio::test]
+async fn unstake_opens_exactly_at_lockup_end() {
+    let (mut ctx, staker, stake) = staked().await;
+
+    warp_to_just_before(&mut ctx, stake.lockup_ends_at).await;
+    let early = send(&mut ctx, &[staking::unstake(&staker.pubkey())], &[&staker]).await;
+    assert_error(early, StakingError::StillLocked);
+
+    warp_to(&mut ctx, stake.lockup_ends_at).await;
+    send(&mut ctx, &[staking::unstake(&staker.pubkey())], &[&staker])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn rewards_accrue_per_whole_day() {
+    let (mut ctx, staker, stake) = staked().await;
+    let first_day = stake.last_claim_at + SECONDS_PER_DAY;
+
+    warp_to_just_before(&mut ctx, first_day).await;
+    let early = send(&mut ctx, &[staking::claim_rewards(&staker.pubkey())], &[&staker]).await;
+    assert_error(early, StakingError::NoRewardsToClaim);
+
+    warp_to(&mut ctx, first_day).await;
+    send(&mut ctx, &[staking::claim_rewards(&staker.pubkey())], &[&staker])
+        .await
+        .unwrap();
+    let stake: StakeAccount = fetch(&mut ctx, &stake_address(&staker.pubkey())).await;
+    assert!(stake.total_claimed > 0);
+}
+
+#[tokio::test]
+async fn finalize_opens_one_second_after_voting_ends() {
+    let (mut ctx, proposal) = proposed().await;
+
+    warp_to(&mut ctx, proposal.voting_ends_at).await;
+    let early = send(&mut ctx, &[governance::finalize_proposal(1)], &[]).await;
+    assert_error(early, GovernanceError::VotingNotEnded);
+
+    warp_to(&mut ctx, proposal.voting_ends_at + 1).await;
+    send(&mut ctx, &[governance::finalize_proposal(1)], &[])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn execute_opens_exactly_at_timelock_end() {
+    let (mut ctx, proposal) = proposed().await;
+    warp_to(&mut ctx, proposal.voting_ends_at + 1).await;
+    send(&mut ctx, &[governance::finalize_proposal(1)], &[])
+        .await
+        .unwrap();
+
+    let executor = ctx.payer.pubkey();
+    let unlocks_at = proposal.voting_ends_at + TIMELOCK_DELAY;
+    warp_to_just_before(&mut ctx, unlocks_at).await;
+    let early = send(&mut ctx, &[governance::execute_proposal(&executor, 1)], &[]).await;
+    assert_error(early, GovernanceError::TimelockNotPassed);
+
+    warp_to(&mut ctx, unlocks_at).await;
+    send(&mut ctx, &[governance::execute_proposal(&executor, 1)], &[])
+        .await
+        .unwrap();
+}
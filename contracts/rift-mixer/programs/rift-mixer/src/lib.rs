@@ -2,6 +2,8 @@
 // Deploy this using Solana Playground (beta.solpg.io) or Anchor CLI
 
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
 use anchor_lang::solana_program::keccak;
 
 declare_id!("RiFTMixer111111111111111111111111111111111"); // Replace after deployment
@@ -14,6 +16,19 @@ pub const MERKLE_TREE_HEIGHT: usize = 20;
 pub const MAX_DEPOSITS: usize = 1_048_576; // 2^20
 pub const POOL_SEED: &[u8] = b"rift_pool";
 pub const MERKLE_SEED: &[u8] = b"merkle_tree";
+pub const DENY_LIST_SEED: &[u8] = b"deny_list";
+pub const DENIED_SEED: &[u8] = b"denied";
+pub const ASSOCIATION_SEED: &[u8] = b"association";
+pub const ROOT_HISTORY_SEED: &[u8] = b"root_history";
+pub const DEPOSIT_ACTIVITY_SEED: &[u8] = b"deposit_activity";
+pub const SHIELDED_POOL_SEED: &[u8] = b"shielded_pool";
+pub const EMERGENCY_CLAIM_SEED: &[u8] = b"emergency_claim";
+
+// Recent Merkle roots a withdrawal may prove against
+pub const ROOT_HISTORY_SIZE: usize = 30;
+
+// Association roots a withdrawal may still prove against
+pub const ASSOCIATION_ROOT_HISTORY: usize = 32;
 
 // Fixed deposit amounts in lamports (1 SOL = 1_000_000_000 lamports)
 pub const DEPOSIT_0_1_SOL: u64 = 100_000_000;   // 0.1 SOL
@@ -24,6 +39,50 @@ pub const DEPOSIT_100_SOL: u64 = 100_000_000_000; // 100 SOL
 // Relayer fee (0.3%)
 pub const RELAYER_FEE_BPS: u64 = 30;
 
+// Deposit caps for a freshly initialized pool, until the authority raises them
+pub const DEFAULT_MAX_TVL_DEPOSITS: u64 = 100; // in deposits of the pool's amount
+pub const DEFAULT_MAX_DEPOSITS_PER_EPOCH: u64 = 50;
+pub const DEFAULT_SHIELDED_MAX_TVL: u64 = 1_000_000_000_000; // 1,000 SOL
+pub const DEPOSIT_EPOCH_SECONDS: i64 = 24 * 60 * 60;
+
+// Largest encrypted note a deposit or transact output may carry (opaque to
+// the program)
+pub const MAX_ENCRYPTED_NOTE_LEN: usize = 256;
+
+// Most commitments one `deposit_batch` may insert. Each is a full tree
+// insertion (MERKLE_TREE_HEIGHT hashes), a commitment scan and an event, so
+// this keeps a batch inside the default compute budget (see rift-bench).
+pub const MAX_DEPOSIT_BATCH: usize = 10;
+
+// Deposits are counted per hour over the last week for `get_pool_stats`
+pub const ACTIVITY_BUCKET_SECONDS: i64 = 60 * 60;
+pub const ACTIVITY_BUCKETS: usize = 7 * 24;
+
+// How old every root of a retired pool's tree must be before `close_pool`
+// closes it (about two days of slots), so withdrawals proven against those
+// roots have long landed or expired
+pub const RETIRED_ROOT_WINDOW_SLOTS: u64 = 432_000;
+
+// How old an emergency claim must be before its note is revealed (about a
+// minute of slots). A front-runner who copies a reveal has to commit their
+// own claim and then keep the original reveal from landing for this long.
+pub const EMERGENCY_CLAIM_DELAY_SLOTS: u64 = 150;
+
+// Layout version of `PoolStats`; fields are only ever appended
+pub const POOL_STATS_VERSION: u8 = 2;
+
+// Scalar field of BN254, the curve the circuits are over (big-endian)
+pub const BN254_FIELD_SIZE: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x28, 0x33, 0xe8, 0x48, 0x79, 0xb9, 0x70, 0x91, 0x43, 0xe1, 0xf5, 0x93, 0xf0, 0x00, 0x00, 0x01,
+];
+
+// Base field of BN254, which proof point coordinates are in (big-endian)
+pub const BN254_BASE_FIELD_SIZE: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x97, 0x81, 0x6a, 0x91, 0x68, 0x71, 0xca, 0x8d, 0x3c, 0x20, 0x8c, 0x16, 0xd8, 0x7c, 0xfd, 0x47,
+];
+
 // ============================================================================
 // PROGRAM
 // ============================================================================
@@ -40,22 +99,65 @@ pub mod rift_mixer {
         pool.deposit_amount = get_deposit_amount(&pool_type);
         pool.total_deposits = 0;
         pool.total_withdrawals = 0;
-        pool.merkle_root = [0u8; 32];
+        pool.merkle_root = zero_hashes(MERKLE_TREE_HEIGHT)[MERKLE_TREE_HEIGHT];
         pool.next_index = 0;
         pool.bump = ctx.bumps.pool;
+        pool.max_tvl = pool.deposit_amount * DEFAULT_MAX_TVL_DEPOSITS;
+        pool.max_deposits_per_epoch = DEFAULT_MAX_DEPOSITS_PER_EPOCH;
+        pool.current_epoch = 0;
+        pool.epoch_deposits = 0;
+        pool.min_leaf_age_slots = 0;
+        pool.min_leaf_age_deposits = 0;
+        pool.current_tree = 0;
+        pool.version = Pool::VERSION;
+        pool.emergency = false;
+        pool.retired = false;
+        ctx.accounts.merkle_tree.version = MerkleTree::VERSION;
+        
+        let root_history = &mut ctx.accounts.root_history;
+        root_history.entries = [RootEntry::default(); ROOT_HISTORY_SIZE];
+        root_history.current_index = 0;
+        root_history.version = RootHistory::VERSION;
+        
+        let deposit_activity = &mut ctx.accounts.deposit_activity;
+        deposit_activity.buckets = [0; ACTIVITY_BUCKETS];
+        deposit_activity.last_bucket = 0;
+        deposit_activity.version = DepositActivity::VERSION;
         
         msg!("RIFT Mixer Pool initialized: {:?}", pool_type);
         Ok(())
     }
 
     /// Deposit SOL into the mixer pool
-    /// User provides a commitment (hash of nullifier + secret)
-    pub fn deposit(ctx: Context<Deposit>, commitment: [u8; 32]) -> Result<()> {
+    /// User provides a commitment (hash of nullifier + secret) and optionally
+    /// the note encrypted to their viewing key, so a wallet can recover it
+    /// from `DepositEvent`s later
+    pub fn deposit(
+        ctx: Context<Deposit>,
+        commitment: [u8; 32],
+        encrypted_note: Option<Vec<u8>>,
+    ) -> Result<()> {
         let pool = &mut ctx.accounts.pool;
         let merkle_tree = &mut ctx.accounts.merkle_tree;
         
-        // Verify pool is not full
-        require!(pool.next_index < MAX_DEPOSITS as u64, MixerError::PoolFull);
+        // Screen the depositor: an entry only exists if the deny list
+        // authority added one, so deployments without a list are unaffected
+        require!(
+            !is_denied(&ctx.accounts.denied_depositor, ctx.program_id),
+            MixerError::DepositorBlocked
+        );
+        
+        // Verify the current tree has room and the TVL and per-epoch caps
+        // allow it
+        require!(tree_has_room(merkle_tree, 1), MixerError::PoolFull);
+        check_deposit_limits(pool, 1)?;
+        
+        if let Some(encrypted_note) = &encrypted_note {
+            require!(
+                encrypted_note.len() <= MAX_ENCRYPTED_NOTE_LEN,
+                MixerError::EncryptedNoteTooLarge
+            );
+        }
         
         // Check commitment hasn't been used
         require!(
@@ -79,14 +181,23 @@ pub mod rift_mixer {
             ],
         )?;
         
-        // Add commitment to Merkle tree
-        let leaf_index = pool.next_index as usize;
-        insert_leaf(merkle_tree, leaf_index, commitment)?;
+        // Add commitment to the current Merkle tree
+        let leaf_index = merkle_tree.leaves.len();
+        merkle_tree.leaves.push(commitment);
+        let new_root = insert_leaf(&mut merkle_tree.filled_subtrees, leaf_index, commitment);
         
         // Update pool state
         pool.next_index += 1;
         pool.total_deposits += 1;
-        pool.merkle_root = compute_merkle_root(merkle_tree)?;
+        pool.epoch_deposits += 1;
+        pool.merkle_root = new_root;
+        record_root(
+            &mut ctx.accounts.root_history,
+            new_root,
+            Clock::get()?.slot,
+            pool.next_index,
+        );
+        ctx.accounts.deposit_activity.record(Clock::get()?.unix_timestamp, 1);
         
         // Emit deposit event
         emit!(DepositEvent {
@@ -94,203 +205,1975 @@ pub mod rift_mixer {
             leaf_index: leaf_index as u64,
             timestamp: Clock::get()?.unix_timestamp,
             pool_type: pool.pool_type.clone(),
+            encrypted_note,
+            tree_index: pool.current_tree,
         });
         
         msg!("Deposit successful. Leaf index: {}", leaf_index);
         Ok(())
     }
 
+    /// Deposit several notes of the pool's amount at once: one transfer of
+    /// `commitments.len() * deposit_amount`, one root update, and a
+    /// `DepositEvent` per leaf. Batched notes carry no encrypted backup.
+    pub fn deposit_batch(ctx: Context<Deposit>, commitments: Vec<[u8; 32]>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let merkle_tree = &mut ctx.accounts.merkle_tree;
+        
+        require!(
+            !commitments.is_empty() && commitments.len() <= MAX_DEPOSIT_BATCH,
+            MixerError::InvalidBatchSize
+        );
+        require!(
+            !is_denied(&ctx.accounts.denied_depositor, ctx.program_id),
+            MixerError::DepositorBlocked
+        );
+        // The whole batch goes into the current tree
+        require!(tree_has_room(merkle_tree, commitments.len()), MixerError::PoolFull);
+        let count = commitments.len() as u64;
+        check_deposit_limits(pool, count)?;
+        
+        // One transfer for the whole batch
+        let amount = pool.deposit_amount
+            .checked_mul(count)
+            .ok_or(MixerError::ArithmeticError)?;
+        let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
+            &ctx.accounts.depositor.key(),
+            &ctx.accounts.pool_vault.key(),
+            amount,
+        );
+        
+        anchor_lang::solana_program::program::invoke(
+            &transfer_ix,
+            &[
+                ctx.accounts.depositor.to_account_info(),
+                ctx.accounts.pool_vault.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+        
+        insert_deposits(
+            pool,
+            merkle_tree,
+            &mut ctx.accounts.root_history,
+            &mut ctx.accounts.deposit_activity,
+            commitments,
+        )?;
+        
+        msg!("Batch deposit of {} notes", count);
+        Ok(())
+    }
+
+    /// Start the pool's next Merkle tree once the current one is full. An
+    /// instruction can only allocate `MAX_PERMITTED_DATA_INCREASE` bytes, so
+    /// the tree starts at that size and `grow_tree` takes it the rest of the
+    /// way. Anyone may call either and pays the rent. Notes in earlier trees
+    /// stay withdrawable against those trees' root histories.
+    pub fn open_tree(ctx: Context<OpenTree>) -> Result<()> {
+        let pool = &ctx.accounts.pool;
+        require!(!pool.retired, MixerError::PoolRetired);
+        require!(!tree_has_room(&ctx.accounts.current_tree, 1), MixerError::TreeNotFull);
+        
+        ctx.accounts.next_tree.version = MerkleTree::VERSION;
+        
+        let root_history = &mut ctx.accounts.next_root_history;
+        root_history.entries = [RootEntry::default(); ROOT_HISTORY_SIZE];
+        root_history.current_index = 0;
+        root_history.version = RootHistory::VERSION;
+        
+        msg!("Tree {} created, grow it to move the pool on", pool.current_tree + 1);
+        Ok(())
+    }
+
+    /// Grow tree `tree_index` by up to `MAX_PERMITTED_DATA_INCREASE` bytes.
+    /// `initialize` leaves tree 0 at that size, so the pool's first tree
+    /// grows in place. Once the tree `open_tree` created reaches
+    /// `MerkleTree::SIZE` the pool moves on to it and deposits go there.
+    pub fn grow_tree(ctx: Context<GrowTree>, tree_index: u32) -> Result<()> {
+        require!(!ctx.accounts.pool.retired, MixerError::PoolRetired);
+        
+        let tree = ctx.accounts.merkle_tree.to_account_info();
+        let full_len = 8 + MerkleTree::SIZE;
+        let len = full_len.min(tree.data_len() + MAX_PERMITTED_DATA_INCREASE);
+        grow_account(
+            &tree,
+            len,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        if len < full_len {
+            msg!("Tree {} grown to {} of {} bytes", tree_index, len, full_len);
+            return Ok(());
+        }
+        
+        let pool = &mut ctx.accounts.pool;
+        if tree_index != pool.current_tree + 1 {
+            msg!("Tree {} at its full {} bytes", tree_index, full_len);
+            return Ok(());
+        }
+        pool.current_tree = pool.current_tree
+            .checked_add(1)
+            .ok_or(MixerError::ArithmeticError)?;
+        pool.merkle_root = zero_hashes(MERKLE_TREE_HEIGHT)[MERKLE_TREE_HEIGHT];
+        
+        emit!(TreeOpened {
+            pool_type: pool.pool_type.clone(),
+            tree_index: pool.current_tree,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
     /// Withdraw SOL from the mixer pool
-    /// Requires ZK proof that the nullifier corresponds to a valid commitment
+    /// Requires ZK proof that the nullifier corresponds to a valid commitment.
+    /// The proof commits to `intent`, so whoever submits it (usually a
+    /// relayer) can change nothing about the withdrawal.
     pub fn withdraw(
         ctx: Context<Withdraw>,
+        tree_index: u32,
         proof: ZkProof,
-        nullifier_hash: [u8; 32],
-        recipient: Pubkey,
-        relayer: Option<Pubkey>,
-        fee: u64,
+        intent: WithdrawIntent,
     ) -> Result<()> {
-        let pool = &mut ctx.accounts.pool;
-        let nullifier_registry = &mut ctx.accounts.nullifier_registry;
+        let accounts = &mut *ctx.accounts;
+        check_intent(accounts, &intent)?;
         
         // Check nullifier hasn't been used (prevents double-spend)
         require!(
-            !is_nullifier_used(nullifier_registry, &nullifier_hash),
+            !is_nullifier_used(&accounts.nullifier_registry, &intent.nullifier_hash),
             MixerError::NullifierAlreadyUsed
         );
         
+        // The proven root must be a recent one of tree `tree_index`, and old
+        // enough (anonymity delay)
+        check_root(&accounts.pool, tree_index, &accounts.root_history, &intent.root)?;
+        
         // Verify the ZK proof
+        check_proof_encoding(&proof)?;
+        let ext_data_hash = hash_withdraw_intent(&intent);
+        if !verify_proof(&proof, &intent.root, &intent.nullifier_hash, &ext_data_hash) {
+            return Err(proof_rejected(&intent.root, &intent.nullifier_hash));
+        }
+        
+        pay_out(accounts, &intent, None)
+    }
+
+    /// Withdraw while also proving the deposit is in a curated association
+    /// set, without revealing which deposit it is
+    pub fn withdraw_with_association(
+        ctx: Context<WithdrawWithAssociation>,
+        tree_index: u32,
+        proof: ZkProof,
+        association_root: [u8; 32],
+        intent: WithdrawIntent,
+    ) -> Result<()> {
+        let accounts = &mut ctx.accounts.withdraw;
+        check_intent(accounts, &intent)?;
+        
         require!(
-            verify_proof(&proof, &pool.merkle_root, &nullifier_hash, &recipient, fee),
-            MixerError::InvalidProof
+            !is_nullifier_used(&accounts.nullifier_registry, &intent.nullifier_hash),
+            MixerError::NullifierAlreadyUsed
         );
         
-        // Mark nullifier as used
-        mark_nullifier_used(nullifier_registry, nullifier_hash)?;
+        check_root(&accounts.pool, tree_index, &accounts.root_history, &intent.root)?;
         
-        // Calculate amounts
-        let withdrawal_amount = pool.deposit_amount;
-        let relayer_fee = if relayer.is_some() { fee } else { 0 };
-        let recipient_amount = withdrawal_amount.checked_sub(relayer_fee)
-            .ok_or(MixerError::ArithmeticError)?;
+        // The set must be one the curator published recently
+        require!(
+            is_known_association_root(&ctx.accounts.association_registry, &association_root),
+            MixerError::UnknownAssociationRoot
+        );
         
-        // Transfer to recipient
-        let pool_seeds = &[
-            POOL_SEED,
-            &[pool.pool_type.to_u8()],
-            &[pool.bump],
-        ];
-        let signer_seeds = &[&pool_seeds[..]];
-        
-        // Transfer to recipient
-        **ctx.accounts.pool_vault.try_borrow_mut_lamports()? -= recipient_amount;
-        **ctx.accounts.recipient.try_borrow_mut_lamports()? += recipient_amount;
-        
-        // Transfer fee to relayer if applicable
-        if let Some(_relayer_key) = relayer {
-            if relayer_fee > 0 {
-                **ctx.accounts.pool_vault.try_borrow_mut_lamports()? -= relayer_fee;
-                **ctx.accounts.relayer.try_borrow_mut_lamports()? += relayer_fee;
-            }
+        check_proof_encoding(&proof)?;
+        if !verify_association_proof(
+            &proof,
+            &intent.root,
+            &association_root,
+            &intent.nullifier_hash,
+            &hash_withdraw_intent(&intent),
+        ) {
+            return Err(proof_rejected(&intent.root, &intent.nullifier_hash));
         }
         
-        // Update pool state
+        pay_out(accounts, &intent, Some(association_root))
+    }
+
+    /// Spend a note of one pool and re-deposit its value as `commitments`
+    /// notes of another, e.g. one 10 SOL note into ten 1 SOL notes. The
+    /// lamports move vault to vault, so nothing leaves the mixer. The proof
+    /// commits to the new notes (see `swap_public_inputs`).
+    pub fn swap_denomination(
+        ctx: Context<SwapDenomination>,
+        tree_index: u32,
+        proof: ZkProof,
+        root: [u8; 32],
+        nullifier_hash: [u8; 32],
+        commitments: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        let source = &mut accounts.source_pool;
+        let target = &mut accounts.target_pool;
+        
+        require!(
+            !commitments.is_empty() && commitments.len() <= MAX_DEPOSIT_BATCH,
+            MixerError::InvalidBatchSize
+        );
+        // The new notes must add up to exactly the spent one
+        let count = commitments.len() as u64;
+        require!(
+            source.pool_type != target.pool_type
+                && target.deposit_amount.checked_mul(count) == Some(source.deposit_amount),
+            MixerError::InvalidSwap
+        );
+        
+        require!(
+            !is_nullifier_used(&accounts.source_nullifier_registry, &nullifier_hash),
+            MixerError::NullifierAlreadyUsed
+        );
+        check_root(source, tree_index, &accounts.source_root_history, &root)?;
+        check_proof_encoding(&proof)?;
+        if !verify_swap_proof(&proof, &root, &nullifier_hash, &target.pool_type, &commitments) {
+            return Err(proof_rejected(&root, &nullifier_hash));
+        }
+        
+        require!(
+            tree_has_room(&accounts.target_merkle_tree, commitments.len()),
+            MixerError::PoolFull
+        );
+        check_deposit_limits(target, count)?;
+        
+        mark_nullifier_used(&mut accounts.source_nullifier_registry, nullifier_hash)?;
+        source.total_withdrawals += 1;
+        move_lamports(&accounts.source_vault, &accounts.target_vault, source.deposit_amount)?;
+        
+        emit!(SwapEvent {
+            nullifier_hash,
+            from_pool_type: source.pool_type.clone(),
+            to_pool_type: target.pool_type.clone(),
+            first_leaf_index: accounts.target_merkle_tree.leaves.len() as u64,
+            count: count as u32,
+            tree_index: target.current_tree,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        insert_deposits(
+            target,
+            &mut accounts.target_merkle_tree,
+            &mut accounts.target_root_history,
+            &mut accounts.target_deposit_activity,
+            commitments,
+        )
+    }
+
+    /// Put the pool into emergency mode, for when its verifier can no longer
+    /// be trusted. Deposits and proof-based withdrawals stop for good, and
+    /// depositors reclaim their funds with `emergency_withdraw` instead. Only
+    /// the deployment authority may do this, not the pool authority.
+    pub fn enter_emergency_mode(ctx: Context<EnterEmergencyMode>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.emergency = true;
+        
+        emit!(EmergencyModeEntered {
+            pool_type: pool.pool_type.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Commit to an emergency withdrawal without revealing the note.
+    /// `claim_hash` is `emergency_claim_hash(nullifier, secret, recipient)`;
+    /// the claimant pays the claim's rent and gets it back on the reveal.
+    pub fn commit_emergency_withdraw(
+        ctx: Context<CommitEmergencyWithdraw>,
+        claim_hash: [u8; 32],
+    ) -> Result<()> {
+        require!(ctx.accounts.pool.emergency, MixerError::NotInEmergency);
+        
+        let claim = &mut ctx.accounts.claim;
+        claim.claimant = ctx.accounts.claimant.key();
+        claim.slot = Clock::get()?.slot;
+        claim.bump = ctx.bumps.claim;
+        claim.version = EmergencyClaim::VERSION;
+        
+        msg!("Emergency claim committed: {}", hex(&claim_hash));
+        Ok(())
+    }
+
+    /// Reclaim a deposit in emergency mode by revealing its note. The
+    /// commitment is recomputed and must be a leaf of tree `tree_index`.
+    /// This needs no proof, and it links the deposit to the recipient. The
+    /// note, with `recipient`, must have been committed to by a claim at least
+    /// `EMERGENCY_CLAIM_DELAY_SLOTS` old, so a copied reveal pays no one else.
+    pub fn emergency_withdraw(
+        ctx: Context<EmergencyWithdraw>,
+        tree_index: u32,
+        nullifier: [u8; 32],
+        secret: [u8; 32],
+    ) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        require!(accounts.pool.emergency, MixerError::NotInEmergency);
+        let slot = Clock::get()?.slot;
+        let ready_at = accounts.claim.slot.saturating_add(EMERGENCY_CLAIM_DELAY_SLOTS);
+        if slot < ready_at {
+            let context = format_args!("slot={} ready_at={}", slot, ready_at);
+            return Err(fail(MixerError::EmergencyClaimTooRecent, context));
+        }
+        
+        let commitment = note_commitment(&nullifier, &secret);
+        require!(
+            is_commitment_used(&accounts.merkle_tree, &commitment),
+            MixerError::UnknownCommitment
+        );
+        // Notes already withdrawn, normally or not, have their nullifier spent
+        let nullifier_hash = note_nullifier_hash(&nullifier);
+        require!(
+            !is_nullifier_used(&accounts.nullifier_registry, &nullifier_hash),
+            MixerError::NullifierAlreadyUsed
+        );
+        
+        mark_nullifier_used(&mut accounts.nullifier_registry, nullifier_hash)?;
+        let pool = &mut accounts.pool;
         pool.total_withdrawals += 1;
+        move_lamports(&accounts.pool_vault, &accounts.recipient, pool.deposit_amount)?;
         
-        // Emit withdrawal event
-        emit!(WithdrawEvent {
+        emit!(EmergencyWithdrawEvent {
+            commitment,
             nullifier_hash,
-            recipient,
-            relayer,
-            fee: relayer_fee,
+            recipient: accounts.recipient.key(),
+            tree_index,
             timestamp: Clock::get()?.unix_timestamp,
         });
+        Ok(())
+    }
+
+    /// Retire the pool: it takes no more deposits, while its notes stay
+    /// withdrawable. Once every note is withdrawn, `close_pool` returns the
+    /// rent of its accounts.
+    pub fn retire_pool(ctx: Context<SetPoolConfig>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.retired = true;
         
-        msg!("Withdrawal successful to: {}", recipient);
+        emit!(PoolRetirement {
+            pool_type: pool.pool_type.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
 
-    /// Get pool statistics
+    /// Close a retired, fully withdrawn pool's current tree and its root
+    /// history, sending their rent to `treasury`. Every root of that tree
+    /// must be `RETIRED_ROOT_WINDOW_SLOTS` old. Trees close newest first;
+    /// closing tree 0 also closes the nullifier registry, deposit activity,
+    /// vault and the pool itself, so spent nullifiers are only forgotten
+    /// together with every root they could be replayed against.
+    pub fn close_pool(ctx: Context<ClosePool>) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        let pool = &mut accounts.pool;
+        require!(pool.retired, MixerError::PoolNotRetired);
+        require!(
+            pool.total_withdrawals >= pool.total_deposits,
+            MixerError::PoolNotDrained
+        );
+        let slot = Clock::get()?.slot;
+        require!(
+            accounts.root_history.entries.iter().all(|entry| {
+                entry.leaf_count == 0
+                    || slot.saturating_sub(entry.slot) >= RETIRED_ROOT_WINDOW_SLOTS
+            }),
+            MixerError::RootsStillRecent
+        );
+        
+        emit!(TreeClosed {
+            pool_type: pool.pool_type.clone(),
+            tree_index: pool.current_tree,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        if pool.current_tree > 0 {
+            pool.current_tree -= 1;
+            return Ok(());
+        }
+        
+        let treasury = accounts.treasury.to_account_info();
+        accounts.nullifier_registry.close(treasury.clone())?;
+        accounts.deposit_activity.close(treasury.clone())?;
+        move_lamports(&accounts.pool_vault, &treasury, accounts.pool_vault.lamports())?;
+        accounts.pool.close(treasury)
+    }
+
+    /// Get pool statistics, returned as return data (see `PoolStats`)
     pub fn get_pool_stats(ctx: Context<GetPoolStats>) -> Result<PoolStats> {
         let pool = &ctx.accounts.pool;
+        let activity = &ctx.accounts.deposit_activity;
+        let now = Clock::get()?.unix_timestamp;
         
         Ok(PoolStats {
+            version: POOL_STATS_VERSION,
             pool_type: pool.pool_type.clone(),
             deposit_amount: pool.deposit_amount,
             total_deposits: pool.total_deposits,
             total_withdrawals: pool.total_withdrawals,
-            current_balance: pool.total_deposits - pool.total_withdrawals,
+            anonymity_set_size: pool.total_deposits.saturating_sub(pool.total_withdrawals),
+            vault_lamports: ctx.accounts.pool_vault.lamports(),
+            deposits_last_24h: activity.deposits_in_last(now, 24),
+            deposits_last_7d: activity.deposits_in_last(now, 7 * 24),
             merkle_root: pool.merkle_root,
+            root_history_depth: ctx.accounts.root_history.depth(),
+            min_leaf_age_slots: pool.min_leaf_age_slots,
+            min_leaf_age_deposits: pool.min_leaf_age_deposits,
+            current_tree: pool.current_tree,
         })
     }
-}
 
-// ============================================================================
-// ACCOUNTS
-// ============================================================================
+    /// Whether `nullifier_hash` has been spent in this pool, returned as
+    /// return data. Changes nothing, so clients simulate it.
+    pub fn is_spent(ctx: Context<IsSpent>, nullifier_hash: [u8; 32]) -> Result<bool> {
+        Ok(is_nullifier_used(&ctx.accounts.nullifier_registry, &nullifier_hash))
+    }
 
-#[derive(Accounts)]
-#[instruction(pool_type: PoolType)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Pool::SIZE,
-        seeds = [POOL_SEED, &[pool_type.to_u8()]],
-        bump
-    )]
-    pub pool: Account<'info, Pool>,
-    
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + MerkleTree::SIZE,
-        seeds = [MERKLE_SEED, &[pool_type.to_u8()]],
-        bump
-    )]
-    pub merkle_tree: Account<'info, MerkleTree>,
-    
-    /// CHECK: Pool vault for holding SOL
-    #[account(
-        seeds = [b"vault", pool.key().as_ref()],
-        bump
-    )]
-    pub pool_vault: AccountInfo<'info>,
-    
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+    /// Where `commitment` sits in tree `tree_index`, returned as return data
+    /// (`None` if it is not a leaf of that tree). Simulated like `is_spent`.
+    pub fn find_commitment(
+        ctx: Context<FindCommitment>,
+        tree_index: u32,
+        commitment: [u8; 32],
+    ) -> Result<Option<LeafPosition>> {
+        let leaves = &ctx.accounts.merkle_tree.leaves;
+        Ok(leaves
+            .iter()
+            .position(|leaf| *leaf == commitment)
+            .map(|leaf_index| LeafPosition {
+                tree_index,
+                leaf_index: leaf_index as u64,
+            }))
+    }
 
-#[derive(Accounts)]
-pub struct Deposit<'info> {
-    #[account(
-        mut,
-        seeds = [POOL_SEED, &[pool.pool_type.to_u8()]],
-        bump = pool.bump
-    )]
-    pub pool: Account<'info, Pool>,
-    
-    #[account(
-        mut,
-        seeds = [MERKLE_SEED, &[pool.pool_type.to_u8()]],
-        bump
-    )]
-    pub merkle_tree: Account<'info, MerkleTree>,
-    
-    /// CHECK: Pool vault
-    #[account(
-        mut,
-        seeds = [b"vault", pool.key().as_ref()],
-        bump
-    )]
-    pub pool_vault: AccountInfo<'info>,
-    
-    #[account(mut)]
-    pub depositor: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+    /// Set the pool's deposit caps. `max_tvl` is in lamports; `u64::MAX`
+    /// lifts a cap entirely.
+    pub fn set_pool_caps(
+        ctx: Context<SetPoolConfig>,
+        max_tvl: u64,
+        max_deposits_per_epoch: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.max_tvl = max_tvl;
+        pool.max_deposits_per_epoch = max_deposits_per_epoch;
+        
+        emit!(PoolCapsUpdated {
+            pool_type: pool.pool_type.clone(),
+            max_tvl,
+            max_deposits_per_epoch,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct Withdraw<'info> {
-    #[account(
-        mut,
-        seeds = [POOL_SEED, &[pool.pool_type.to_u8()]],
-        bump = pool.bump
-    )]
-    pub pool: Account<'info, Pool>,
-    
-    #[account(
-        mut,
-        seeds = [b"nullifiers", pool.key().as_ref()],
-        bump
-    )]
-    pub nullifier_registry: Account<'info, NullifierRegistry>,
-    
-    /// CHECK: Pool vault
-    #[account(
-        mut,
-        seeds = [b"vault", pool.key().as_ref()],
-        bump
-    )]
-    pub pool_vault: AccountInfo<'info>,
-    
-    /// CHECK: Recipient of the withdrawal
-    #[account(mut)]
-    pub recipient: AccountInfo<'info>,
-    
-    /// CHECK: Optional relayer
+    /// Set the anonymity delay: withdrawals must prove against a root at
+    /// least this many slots and this many deposits old. Zero disables either.
+    pub fn set_min_leaf_age(
+        ctx: Context<SetPoolConfig>,
+        min_leaf_age_slots: u64,
+        min_leaf_age_deposits: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.min_leaf_age_slots = min_leaf_age_slots;
+        pool.min_leaf_age_deposits = min_leaf_age_deposits;
+        
+        msg!(
+            "Min leaf age set to {} slots, {} deposits",
+            min_leaf_age_slots,
+            min_leaf_age_deposits
+        );
+        Ok(())
+    }
+
+    /// Bring a pool created by the first deployment (version 0) up to the
+    /// current layout: its tree moves from version 0's per-type seeds to tree
+    /// 0's, the root history and deposit activity accounts it never had are
+    /// created, and the pool grows to `Pool::SIZE` with the default caps.
+    /// Version 0's root and `filled_subtrees` came from a different tree
+    /// algorithm, so both are rebuilt from the leaves and the pool's root
+    /// changes. The authority pays the rent. An instruction can only allocate
+    /// `MAX_PERMITTED_DATA_INCREASE` bytes, so moving the tree takes several
+    /// calls; the pool reads `Pool::VERSION` once it is done, and calling it
+    /// again after that changes nothing.
+    pub fn migrate_pool(ctx: Context<MigratePool>) -> Result<()> {
+        let accounts = &ctx.accounts;
+        let pool_key = accounts.pool.key();
+        let payer = accounts.authority.to_account_info();
+        let system_program = accounts.system_program.to_account_info();
+        
+        // A registry that filled its padding has no byte left for `version`
+        // and cannot be read until it grows
+        let registry = accounts.nullifier_registry.to_account_info();
+        let nullifiers = stored_vec_len(&registry)?;
+        grow_account(
+            &registry,
+            8 + NullifierRegistry::space_for(nullifiers),
+            &payer,
+            &system_program,
+        )?;
+        upgrade_account(&registry, |registry: &mut NullifierRegistry| {
+            registry.version = NullifierRegistry::VERSION;
+        })?;
+        
+        // Version 0 had no root history; the rebuilt root goes in once the
+        // tree has moved
+        let root_history = accounts.root_history.to_account_info();
+        if root_history.data_is_empty() {
+            let seeds: &[&[u8]] = &[
+                ROOT_HISTORY_SEED,
+                pool_key.as_ref(),
+                &0u32.to_le_bytes(),
+                &[ctx.bumps.root_history],
+            ];
+            create_pda(&root_history, 8 + RootHistory::SIZE, seeds, &payer, &system_program)?;
+            let history = RootHistory {
+                entries: [RootEntry::default(); ROOT_HISTORY_SIZE],
+                current_index: 0,
+                version: RootHistory::VERSION,
+            };
+            history.try_serialize(&mut &mut root_history.try_borrow_mut_data()?[..])?;
+        }
+        
+        let deposit_activity = accounts.deposit_activity.to_account_info();
+        if deposit_activity.data_is_empty() {
+            let seeds: &[&[u8]] =
+                &[DEPOSIT_ACTIVITY_SEED, pool_key.as_ref(), &[ctx.bumps.deposit_activity]];
+            let space = 8 + DepositActivity::SIZE;
+            create_pda(&deposit_activity, space, seeds, &payer, &system_program)?;
+            let activity = DepositActivity {
+                buckets: [0; ACTIVITY_BUCKETS],
+                last_bucket: 0,
+                version: DepositActivity::VERSION,
+            };
+            activity.try_serialize(&mut &mut deposit_activity.try_borrow_mut_data()?[..])?;
+        }
+        
+        // The tree is created and grown at its new seeds, then the leaves are
+        // copied over and version 0's account is closed
+        let legacy_tree = accounts.legacy_tree.to_account_info();
+        let tree = accounts.merkle_tree.to_account_info();
+        let mut rebuilt_root = None;
+        if legacy_tree.owner == &crate::ID && !legacy_tree.data_is_empty() {
+            let leaves = stored_vec_len(&legacy_tree)?;
+            let len = 8 + MerkleTree::SIZE.max(MerkleTree::space_for(leaves));
+            if tree.data_is_empty() {
+                let seeds: &[&[u8]] = &[
+                    MERKLE_SEED,
+                    pool_key.as_ref(),
+                    &0u32.to_le_bytes(),
+                    &[ctx.bumps.merkle_tree],
+                ];
+                let space = len.min(MAX_PERMITTED_DATA_INCREASE);
+                create_pda(&tree, space, seeds, &payer, &system_program)?;
+            } else {
+                let grown = len.min(tree.data_len() + MAX_PERMITTED_DATA_INCREASE);
+                grow_account(&tree, grown, &payer, &system_program)?;
+            }
+            
+            if tree.data_len() >= len {
+                // The same layout, less the trailing `version`
+                let stored = 8 + MerkleTree::space_for(leaves) - 1;
+                let legacy_data = legacy_tree.try_borrow_data()?;
+                let leaves_data = legacy_data
+                    .get(..stored)
+                    .ok_or(ErrorCode::AccountDidNotDeserialize)?;
+                tree.try_borrow_mut_data()?[..stored].copy_from_slice(leaves_data);
+                drop(legacy_data);
+                let root = upgrade_account(&tree, |tree: &mut MerkleTree| {
+                    let (filled_subtrees, root) = rebuild_tree(&tree.leaves, MERKLE_TREE_HEIGHT);
+                    tree.filled_subtrees.copy_from_slice(&filled_subtrees);
+                    tree.version = MerkleTree::VERSION;
+                    root
+                })?;
+                let (slot, leaf_count) = (Clock::get()?.slot, accounts.pool.next_index);
+                upgrade_account(&root_history, |history: &mut RootHistory| {
+                    record_root(history, root, slot, leaf_count);
+                })?;
+                close_account(&legacy_tree, &payer)?;
+                rebuilt_root = Some(root);
+            } else {
+                msg!("Tree 0 at {} of {} bytes, migrate again", tree.data_len(), len);
+                return Ok(());
+            }
+        }
+        
+        // The pool was grown by its `realloc` constraint; version 0 read the
+        // new fields from its padding, so its caps were zero
+        let pool = &mut ctx.accounts.pool;
+        if let Some(root) = rebuilt_root {
+            pool.merkle_root = root;
+        }
+        if pool.version == Pool::VERSION {
+            msg!("Pool already at version {}", Pool::VERSION);
+            return Ok(());
+        }
+        let pool_from = pool.version;
+        pool.max_tvl = pool.deposit_amount * DEFAULT_MAX_TVL_DEPOSITS;
+        pool.max_deposits_per_epoch = DEFAULT_MAX_DEPOSITS_PER_EPOCH;
+        pool.version = Pool::VERSION;
+        
+        msg!("Pool migrated from version {}", pool_from);
+        Ok(())
+    }
+
+    /// Create the deployment's deny list, managed by `authority`
+    /// (normally the governance PDA). Only the deployment authority may
+    /// create it.
+    pub fn initialize_deny_list(ctx: Context<InitializeDenyList>, authority: Pubkey) -> Result<()> {
+        let deny_list = &mut ctx.accounts.deny_list;
+        deny_list.authority = authority;
+        deny_list.entry_count = 0;
+        deny_list.bump = ctx.bumps.deny_list;
+        deny_list.version = DenyList::VERSION;
+        
+        msg!("Deny list initialized, authority: {}", authority);
+        Ok(())
+    }
+
+    /// Hand the deny list over to a new authority
+    pub fn set_deny_list_authority(
+        ctx: Context<SetDenyListAuthority>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.deny_list.authority = new_authority;
+        
+        msg!("Deny list authority set to: {}", new_authority);
+        Ok(())
+    }
+
+    /// Block `address` from depositing
+    pub fn deny_address(ctx: Context<DenyAddress>, address: Pubkey) -> Result<()> {
+        let deny_list = &mut ctx.accounts.deny_list;
+        let denied = &mut ctx.accounts.denied_address;
+        denied.address = address;
+        denied.added_at = Clock::get()?.unix_timestamp;
+        denied.bump = ctx.bumps.denied_address;
+        denied.version = DeniedAddress::VERSION;
+        
+        deny_list.entry_count = deny_list.entry_count
+            .checked_add(1)
+            .ok_or(MixerError::ArithmeticError)?;
+        
+        emit!(AddressDenied {
+            address,
+            timestamp: denied.added_at,
+        });
+        Ok(())
+    }
+
+    /// Create the pool's association root registry, published to by `curator`
+    pub fn initialize_association_registry(
+        ctx: Context<InitializeAssociationRegistry>,
+        curator: Pubkey,
+    ) -> Result<()> {
+        let registry = &mut ctx.accounts.association_registry;
+        registry.pool = ctx.accounts.pool.key();
+        registry.curator = curator;
+        registry.roots = [[0u8; 32]; ASSOCIATION_ROOT_HISTORY];
+        registry.current_index = 0;
+        registry.published_count = 0;
+        registry.bump = ctx.bumps.association_registry;
+        registry.version = AssociationRootRegistry::VERSION;
+        
+        msg!("Association registry initialized, curator: {}", curator);
+        Ok(())
+    }
+
+    /// Publish the root of a new association set. Older roots stay valid
+    /// until `ASSOCIATION_ROOT_HISTORY` newer ones have been published.
+    pub fn publish_association_root(
+        ctx: Context<PublishAssociationRoot>,
+        association_root: [u8; 32],
+    ) -> Result<()> {
+        require!(association_root != [0u8; 32], MixerError::UnknownAssociationRoot);
+        
+        let registry = &mut ctx.accounts.association_registry;
+        if registry.published_count > 0 {
+            registry.current_index = (registry.current_index + 1) % ASSOCIATION_ROOT_HISTORY as u8;
+        }
+        let slot = registry.current_index as usize;
+        registry.roots[slot] = association_root;
+        registry.published_count += 1;
+        
+        emit!(AssociationRootPublished {
+            pool: registry.pool,
+            association_root,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Lift the block on `address`, returning the entry's rent to the payer
+    pub fn allow_address(ctx: Context<AllowAddress>, address: Pubkey) -> Result<()> {
+        let deny_list = &mut ctx.accounts.deny_list;
+        deny_list.entry_count = deny_list.entry_count
+            .checked_sub(1)
+            .ok_or(MixerError::ArithmeticError)?;
+        
+        emit!(AddressAllowed {
+            address,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Create the deployment's shielded (join-split) pool, managed by
+    /// `authority`, with its vault and nullifier registry. Only the
+    /// deployment authority may create it. Its tree starts at
+    /// `MAX_PERMITTED_DATA_INCREASE` bytes; `grow_shielded_tree` makes room
+    /// for more notes. The registry starts empty and `transact` grows it by
+    /// the nullifiers it spends.
+    pub fn initialize_shielded_pool(
+        ctx: Context<InitializeShieldedPool>,
+        authority: Pubkey,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.shielded_pool;
+        pool.authority = authority;
+        pool.merkle_root = zero_hashes(MERKLE_TREE_HEIGHT)[MERKLE_TREE_HEIGHT];
+        pool.next_index = 0;
+        pool.total_transactions = 0;
+        pool.bump = ctx.bumps.shielded_pool;
+        pool.max_tvl = DEFAULT_SHIELDED_MAX_TVL;
+        pool.max_deposits_per_epoch = DEFAULT_MAX_DEPOSITS_PER_EPOCH;
+        pool.current_epoch = 0;
+        pool.epoch_deposits = 0;
+        pool.min_leaf_age_slots = 0;
+        pool.min_leaf_age_deposits = 0;
+        pool.emergency = false;
+        pool.retired = false;
+        pool.current_tree = 0;
+        pool.version = ShieldedPool::VERSION;
+        ctx.accounts.merkle_tree.version = MerkleTree::VERSION;
+        ctx.accounts.nullifier_registry.version = NullifierRegistry::VERSION;
+        
+        let root_history = &mut ctx.accounts.root_history;
+        root_history.entries = [RootEntry::default(); ROOT_HISTORY_SIZE];
+        root_history.current_index = 0;
+        root_history.version = RootHistory::VERSION;
+        
+        let pool_key = pool.key();
+        create_pda(
+            &ctx.accounts.pool_vault,
+            0,
+            &[b"vault", pool_key.as_ref(), &[ctx.bumps.pool_vault]],
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        
+        msg!("Shielded pool initialized, authority: {}", authority);
+        Ok(())
+    }
+
+    /// Start the shielded pool's next tree once the current one has no room
+    /// for a transaction's two outputs, as `open_tree` does for a fixed pool.
+    /// Anyone may call it and pays the rent.
+    pub fn open_shielded_tree(ctx: Context<OpenShieldedTree>) -> Result<()> {
+        require!(!tree_has_room(&ctx.accounts.current_tree, 2), MixerError::TreeNotFull);
+        
+        ctx.accounts.next_tree.version = MerkleTree::VERSION;
+        
+        let root_history = &mut ctx.accounts.next_root_history;
+        root_history.entries = [RootEntry::default(); ROOT_HISTORY_SIZE];
+        root_history.current_index = 0;
+        root_history.version = RootHistory::VERSION;
+        
+        let next = ctx.accounts.shielded_pool.current_tree + 1;
+        msg!("Shielded tree {} created, grow it to move the pool on", next);
+        Ok(())
+    }
+
+    /// Grow shielded tree `tree_index` by up to `MAX_PERMITTED_DATA_INCREASE`
+    /// bytes, up to `MerkleTree::SIZE`. Once the tree `open_shielded_tree`
+    /// created is full size, outputs go there. Anyone may call it and pays
+    /// the rent.
+    pub fn grow_shielded_tree(ctx: Context<GrowShieldedTree>, tree_index: u32) -> Result<()> {
+        let tree = ctx.accounts.merkle_tree.to_account_info();
+        let full_len = 8 + MerkleTree::SIZE;
+        let len = full_len.min(tree.data_len() + MAX_PERMITTED_DATA_INCREASE);
+        grow_account(
+            &tree,
+            len,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        
+        let pool = &mut ctx.accounts.shielded_pool;
+        if len < full_len || tree_index != pool.current_tree + 1 {
+            msg!("Shielded tree {} grown to {} bytes", tree_index, len);
+            return Ok(());
+        }
+        pool.current_tree = pool.current_tree
+            .checked_add(1)
+            .ok_or(MixerError::ArithmeticError)?;
+        pool.merkle_root = zero_hashes(MERKLE_TREE_HEIGHT)[MERKLE_TREE_HEIGHT];
+        
+        msg!("Shielded pool moved on to tree {}", pool.current_tree);
+        Ok(())
+    }
+
+    /// Set the shielded pool's deposit caps: the most its vault may hold, in
+    /// lamports, and the deposits it takes per epoch. `u64::MAX` lifts a cap.
+    pub fn set_shielded_pool_caps(
+        ctx: Context<SetShieldedPoolConfig>,
+        max_tvl: u64,
+        max_deposits_per_epoch: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.shielded_pool;
+        pool.max_tvl = max_tvl;
+        pool.max_deposits_per_epoch = max_deposits_per_epoch;
+        
+        msg!(
+            "Shielded pool caps set to {} lamports, {} deposits per epoch",
+            max_tvl,
+            max_deposits_per_epoch
+        );
+        Ok(())
+    }
+
+    /// The shielded pool's anonymity delay, as `set_min_leaf_age`; the age in
+    /// deposits counts notes inserted since the root
+    pub fn set_shielded_min_leaf_age(
+        ctx: Context<SetShieldedPoolConfig>,
+        min_leaf_age_slots: u64,
+        min_leaf_age_deposits: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.shielded_pool;
+        pool.min_leaf_age_slots = min_leaf_age_slots;
+        pool.min_leaf_age_deposits = min_leaf_age_deposits;
+        
+        msg!(
+            "Shielded min leaf age set to {} slots, {} deposits",
+            min_leaf_age_slots,
+            min_leaf_age_deposits
+        );
+        Ok(())
+    }
+
+    /// Stop trusting proofs on the shielded pool, as `enter_emergency_mode`
+    /// does for a fixed pool. Its notes have no emergency exit, so every
+    /// `transact` is refused from then on.
+    pub fn enter_shielded_emergency_mode(ctx: Context<SetShieldedPoolConfig>) -> Result<()> {
+        ctx.accounts.shielded_pool.emergency = true;
+        
+        msg!("Shielded pool entered emergency mode");
+        Ok(())
+    }
+
+    /// Stop the shielded pool taking deposits. Transfers and withdrawals go on.
+    pub fn retire_shielded_pool(ctx: Context<SetShieldedPoolConfig>) -> Result<()> {
+        ctx.accounts.shielded_pool.retired = true;
+        
+        msg!("Shielded pool retired");
+        Ok(())
+    }
+
+    /// Join-split transaction on the shielded pool: spend two notes of tree
+    /// `tree_index` and create two, of any amounts, in the current tree. `public_amount` (`ext_amount - fee`) is what the
+    /// pool's balance changes by: positive for a deposit from `sender`,
+    /// negative for a withdrawal to the recipient, zero for a transfer inside
+    /// the pool. `ext_data` must hash to `ext_data_hash`, which the proof
+    /// commits to. Its encrypted outputs are published in `TransactEvent` so
+    /// the notes' owners can find them.
+    #[allow(clippy::too_many_arguments)]
+    pub fn transact(
+        ctx: Context<Transact>,
+        tree_index: u32,
+        proof: ZkProof,
+        root: [u8; 32],
+        input_nullifiers: [[u8; 32]; 2],
+        output_commitments: [[u8; 32]; 2],
+        public_amount: i64,
+        ext_data_hash: [u8; 32],
+        ext_data: ExtData,
+    ) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        
+        // The proof commits to the external data, so a relayer can neither
+        // redirect the withdrawal nor change its fee
+        require!(hash_ext_data(&ext_data) == ext_data_hash, MixerError::InvalidExtData);
+        check_account(&accounts.recipient, &ext_data.recipient, MixerError::RecipientMismatch)?;
+        check_account(&accounts.relayer, &ext_data.relayer, MixerError::RelayerMismatch)?;
+        require!(
+            ext_data.encrypted_output1.len() <= MAX_ENCRYPTED_NOTE_LEN
+                && ext_data.encrypted_output2.len() <= MAX_ENCRYPTED_NOTE_LEN,
+            MixerError::EncryptedNoteTooLarge
+        );
+        // A fee beyond i64 cannot balance any public amount
+        let fee = i64::try_from(ext_data.fee).map_err(|_| MixerError::InvalidPublicAmount)?;
+        require!(
+            ext_data.ext_amount.checked_sub(fee) == Some(public_amount),
+            MixerError::InvalidPublicAmount
+        );
+        
+        // Inputs must be unspent and distinct, outputs new and distinct
+        require!(
+            input_nullifiers[0] != input_nullifiers[1]
+                && input_nullifiers
+                    .iter()
+                    .all(|nullifier| !is_nullifier_used(&accounts.nullifier_registry, nullifier)),
+            MixerError::NullifierAlreadyUsed
+        );
+        require!(
+            output_commitments[0] != output_commitments[1]
+                && output_commitments
+                    .iter()
+                    .all(|commitment| !is_commitment_used(&accounts.merkle_tree, commitment)),
+            MixerError::CommitmentAlreadyUsed
+        );
+        require!(tree_has_room(&accounts.merkle_tree, 2), MixerError::PoolFull);
+        check_shielded_root(
+            &accounts.shielded_pool,
+            tree_index,
+            &accounts.proven_root_history,
+            &root,
+        )?;
+        
+        check_proof_encoding(&proof)?;
+        if !verify_transact_proof(
+            &proof,
+            &root,
+            public_amount,
+            &ext_data_hash,
+            &input_nullifiers,
+            &output_commitments,
+        ) {
+            return Err(proof_rejected(&root, &input_nullifiers[0]));
+        }
+        
+        // Deposits come from the signer, screened and capped like fixed-pool
+        // deposits
+        if ext_data.ext_amount > 0 {
+            require!(
+                !is_denied(&accounts.denied_sender, ctx.program_id),
+                MixerError::DepositorBlocked
+            );
+            check_shielded_deposit_limits(
+                &mut accounts.shielded_pool,
+                accounts.pool_vault.lamports(),
+                ext_data.ext_amount as u64,
+            )?;
+            accounts.shielded_pool.epoch_deposits += 1;
+            let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
+                &accounts.sender.key(),
+                &accounts.pool_vault.key(),
+                ext_data.ext_amount as u64,
+            );
+            anchor_lang::solana_program::program::invoke(
+                &transfer_ix,
+                &[
+                    accounts.sender.to_account_info(),
+                    accounts.pool_vault.to_account_info(),
+                    accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+        
+        // The registry grows by what it records, paid by the sender
+        let registry = accounts.nullifier_registry.to_account_info();
+        let nullifiers = accounts.nullifier_registry.used_nullifiers.len() + 2;
+        grow_account(
+            &registry,
+            8 + NullifierRegistry::space_for(nullifiers),
+            &accounts.sender.to_account_info(),
+            &accounts.system_program.to_account_info(),
+        )?;
+        for nullifier in input_nullifiers {
+            mark_nullifier_used(&mut accounts.nullifier_registry, nullifier)?;
+        }
+        
+        let pool = &mut accounts.shielded_pool;
+        let merkle_tree = &mut accounts.merkle_tree;
+        let first_leaf_index = merkle_tree.leaves.len() as u64;
+        let mut new_root = pool.merkle_root;
+        for (offset, commitment) in output_commitments.iter().enumerate() {
+            merkle_tree.leaves.push(*commitment);
+            new_root = insert_leaf(
+                &mut merkle_tree.filled_subtrees,
+                first_leaf_index as usize + offset,
+                *commitment,
+            );
+        }
+        pool.next_index += 2;
+        pool.total_transactions += 1;
+        pool.merkle_root = new_root;
+        let clock = Clock::get()?;
+        record_root(&mut accounts.root_history, new_root, clock.slot, pool.next_index);
+        
+        // Withdrawals and fees leave from the vault
+        if ext_data.ext_amount < 0 {
+            move_lamports(
+                &accounts.pool_vault,
+                &accounts.recipient,
+                ext_data.ext_amount.unsigned_abs(),
+            )?;
+        }
+        if ext_data.fee > 0 {
+            move_lamports(&accounts.pool_vault, &accounts.relayer, ext_data.fee)?;
+        }
+        
+        emit!(TransactEvent {
+            input_nullifiers,
+            output_commitments,
+            first_leaf_index,
+            public_amount,
+            timestamp: clock.unix_timestamp,
+            encrypted_output1: ext_data.encrypted_output1,
+            encrypted_output2: ext_data.encrypted_output2,
+            tree_index: accounts.shielded_pool.current_tree,
+        });
+        Ok(())
+    }
+}
+
+// ============================================================================
+// ACCOUNTS
+// ============================================================================
+
+#[derive(Accounts)]
+#[instruction(pool_type: PoolType)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Pool::SIZE,
+        seeds = [POOL_SEED, &[pool_type.to_u8()]],
+        bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = MAX_PERMITTED_DATA_INCREASE,
+        seeds = [MERKLE_SEED, pool.key().as_ref(), &0u32.to_le_bytes()],
+        bump
+    )]
+    pub merkle_tree: Account<'info, MerkleTree>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + RootHistory::SIZE,
+        seeds = [ROOT_HISTORY_SEED, pool.key().as_ref(), &0u32.to_le_bytes()],
+        bump
+    )]
+    pub root_history: Account<'info, RootHistory>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + DepositActivity::SIZE,
+        seeds = [DEPOSIT_ACTIVITY_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub deposit_activity: Account<'info, DepositActivity>,
+    
+    /// CHECK: Pool vault for holding SOL
+    #[account(
+        seeds = [b"vault", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_vault: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(
+        mut,
+        seeds = [POOL_SEED, &[pool.pool_type.to_u8()]],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    /// The pool's current tree; deposits always go there
+    #[account(
+        mut,
+        seeds = [MERKLE_SEED, pool.key().as_ref(), &pool.current_tree.to_le_bytes()],
+        bump
+    )]
+    pub merkle_tree: Account<'info, MerkleTree>,
+    
+    #[account(
+        mut,
+        seeds = [ROOT_HISTORY_SEED, pool.key().as_ref(), &pool.current_tree.to_le_bytes()],
+        bump
+    )]
+    pub root_history: Account<'info, RootHistory>,
+    
+    #[account(
+        mut,
+        seeds = [DEPOSIT_ACTIVITY_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub deposit_activity: Account<'info, DepositActivity>,
+    
+    /// CHECK: Pool vault
+    #[account(
+        mut,
+        seeds = [b"vault", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_vault: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    
+    /// CHECK: The depositor's deny list entry; must not exist
+    #[account(
+        seeds = [DENIED_SEED, depositor.key().as_ref()],
+        bump
+    )]
+    pub denied_depositor: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OpenTree<'info> {
+    #[account(
+        seeds = [POOL_SEED, &[pool.pool_type.to_u8()]],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        seeds = [MERKLE_SEED, pool.key().as_ref(), &pool.current_tree.to_le_bytes()],
+        bump
+    )]
+    pub current_tree: Account<'info, MerkleTree>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = MAX_PERMITTED_DATA_INCREASE,
+        seeds = [MERKLE_SEED, pool.key().as_ref(), &(pool.current_tree + 1).to_le_bytes()],
+        bump
+    )]
+    pub next_tree: Account<'info, MerkleTree>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + RootHistory::SIZE,
+        seeds = [
+            ROOT_HISTORY_SEED,
+            pool.key().as_ref(),
+            &(pool.current_tree + 1).to_le_bytes()
+        ],
+        bump
+    )]
+    pub next_root_history: Account<'info, RootHistory>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(tree_index: u32)]
+pub struct GrowTree<'info> {
+    #[account(
+        mut,
+        seeds = [POOL_SEED, &[pool.pool_type.to_u8()]],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    /// The pool's current tree, or the one `open_tree` created after it
+    #[account(
+        mut,
+        seeds = [MERKLE_SEED, pool.key().as_ref(), &tree_index.to_le_bytes()],
+        bump
+    )]
+    pub merkle_tree: Account<'info, MerkleTree>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(tree_index: u32)]
+pub struct Withdraw<'info> {
+    #[account(
+        mut,
+        seeds = [POOL_SEED, &[pool.pool_type.to_u8()]],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        mut,
+        seeds = [b"nullifiers", pool.key().as_ref()],
+        bump
+    )]
+    pub nullifier_registry: Account<'info, NullifierRegistry>,
+    
+    /// Root history of the tree the withdrawn note was deposited into
+    #[account(
+        seeds = [ROOT_HISTORY_SEED, pool.key().as_ref(), &tree_index.to_le_bytes()],
+        bump
+    )]
+    pub root_history: Account<'info, RootHistory>,
+    
+    /// CHECK: Pool vault
+    #[account(
+        mut,
+        seeds = [b"vault", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_vault: AccountInfo<'info>,
+    
+    /// CHECK: Recipient of the withdrawal
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+    
+    /// CHECK: Optional relayer
+    #[account(mut)]
+    pub relayer: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawWithAssociation<'info> {
+    pub withdraw: Withdraw<'info>,
+    
+    #[account(
+        seeds = [ASSOCIATION_SEED, withdraw.pool.key().as_ref()],
+        bump = association_registry.bump
+    )]
+    pub association_registry: Account<'info, AssociationRootRegistry>,
+}
+
+#[derive(Accounts)]
+#[instruction(tree_index: u32, nullifier: [u8; 32], secret: [u8; 32])]
+pub struct EmergencyWithdraw<'info> {
+    #[account(
+        mut,
+        seeds = [POOL_SEED, &[pool.pool_type.to_u8()]],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    /// Tree the note was deposited into
+    #[account(
+        seeds = [MERKLE_SEED, pool.key().as_ref(), &tree_index.to_le_bytes()],
+        bump
+    )]
+    pub merkle_tree: Account<'info, MerkleTree>,
+    
+    #[account(
+        mut,
+        seeds = [b"nullifiers", pool.key().as_ref()],
+        bump
+    )]
+    pub nullifier_registry: Account<'info, NullifierRegistry>,
+    
+    /// CHECK: Pool vault
+    #[account(
+        mut,
+        seeds = [b"vault", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_vault: AccountInfo<'info>,
+    
+    /// CHECK: Receives the deposit back
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+    
+    /// The claim committing to this note and `recipient`, closed by the reveal
+    #[account(
+        mut,
+        close = claimant,
+        has_one = claimant,
+        seeds = [
+            EMERGENCY_CLAIM_SEED,
+            pool.key().as_ref(),
+            &emergency_claim_hash(&nullifier, &secret, &recipient.key()),
+        ],
+        bump = claim.bump
+    )]
+    pub claim: Account<'info, EmergencyClaim>,
+    
+    /// CHECK: Gets the claim's rent back
+    #[account(mut)]
+    pub claimant: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(claim_hash: [u8; 32])]
+pub struct CommitEmergencyWithdraw<'info> {
+    #[account(
+        seeds = [POOL_SEED, &[pool.pool_type.to_u8()]],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        init,
+        payer = claimant,
+        space = 8 + EmergencyClaim::SIZE,
+        seeds = [EMERGENCY_CLAIM_SEED, pool.key().as_ref(), &claim_hash],
+        bump
+    )]
+    pub claim: Account<'info, EmergencyClaim>,
+    
+    #[account(mut)]
+    pub claimant: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EnterEmergencyMode<'info> {
+    #[account(
+        mut,
+        seeds = [POOL_SEED, &[pool.pool_type.to_u8()]],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        constraint = is_deployment_authority(&deployment_authority, &program_data)
+            @ MixerError::Unauthorized
+    )]
+    pub deployment_authority: Signer<'info>,
+    
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = bpf_loader_upgradeable::ID
+    )]
+    pub program_data: Account<'info, ProgramData>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePool<'info> {
+    #[account(
+        mut,
+        seeds = [POOL_SEED, &[pool.pool_type.to_u8()]],
+        bump = pool.bump,
+        has_one = authority @ MixerError::Unauthorized
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    /// The pool's current tree, closed by this call
+    #[account(
+        mut,
+        close = treasury,
+        seeds = [MERKLE_SEED, pool.key().as_ref(), &pool.current_tree.to_le_bytes()],
+        bump
+    )]
+    pub merkle_tree: Account<'info, MerkleTree>,
+    
+    #[account(
+        mut,
+        close = treasury,
+        seeds = [ROOT_HISTORY_SEED, pool.key().as_ref(), &pool.current_tree.to_le_bytes()],
+        bump
+    )]
+    pub root_history: Account<'info, RootHistory>,
+    
+    #[account(
+        mut,
+        seeds = [b"nullifiers", pool.key().as_ref()],
+        bump
+    )]
+    pub nullifier_registry: Account<'info, NullifierRegistry>,
+    
+    #[account(
+        mut,
+        seeds = [DEPOSIT_ACTIVITY_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub deposit_activity: Account<'info, DepositActivity>,
+    
+    /// CHECK: Pool vault, emptied into the treasury with the last tree
+    #[account(
+        mut,
+        seeds = [b"vault", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_vault: AccountInfo<'info>,
+    
+    pub authority: Signer<'info>,
+    
+    /// CHECK: Receives the reclaimed rent
+    #[account(mut)]
+    pub treasury: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(tree_index: u32)]
+pub struct SwapDenomination<'info> {
+    #[account(
+        mut,
+        seeds = [POOL_SEED, &[source_pool.pool_type.to_u8()]],
+        bump = source_pool.bump
+    )]
+    pub source_pool: Account<'info, Pool>,
+    
+    #[account(
+        mut,
+        seeds = [b"nullifiers", source_pool.key().as_ref()],
+        bump
+    )]
+    pub source_nullifier_registry: Account<'info, NullifierRegistry>,
+    
+    /// Root history of the tree the spent note was deposited into
+    #[account(
+        seeds = [ROOT_HISTORY_SEED, source_pool.key().as_ref(), &tree_index.to_le_bytes()],
+        bump
+    )]
+    pub source_root_history: Account<'info, RootHistory>,
+    
+    /// CHECK: Source pool vault
+    #[account(
+        mut,
+        seeds = [b"vault", source_pool.key().as_ref()],
+        bump
+    )]
+    pub source_vault: AccountInfo<'info>,
+    
+    #[account(
+        mut,
+        seeds = [POOL_SEED, &[target_pool.pool_type.to_u8()]],
+        bump = target_pool.bump
+    )]
+    pub target_pool: Account<'info, Pool>,
+    
+    /// The target pool's current tree; the new notes go there
+    #[account(
+        mut,
+        seeds = [
+            MERKLE_SEED,
+            target_pool.key().as_ref(),
+            &target_pool.current_tree.to_le_bytes()
+        ],
+        bump
+    )]
+    pub target_merkle_tree: Account<'info, MerkleTree>,
+    
+    #[account(
+        mut,
+        seeds = [
+            ROOT_HISTORY_SEED,
+            target_pool.key().as_ref(),
+            &target_pool.current_tree.to_le_bytes()
+        ],
+        bump
+    )]
+    pub target_root_history: Account<'info, RootHistory>,
+    
+    #[account(
+        mut,
+        seeds = [DEPOSIT_ACTIVITY_SEED, target_pool.key().as_ref()],
+        bump
+    )]
+    pub target_deposit_activity: Account<'info, DepositActivity>,
+    
+    /// CHECK: Target pool vault
+    #[account(
+        mut,
+        seeds = [b"vault", target_pool.key().as_ref()],
+        bump
+    )]
+    pub target_vault: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetPoolStats<'info> {
+    pub pool: Account<'info, Pool>,
+    
+    /// Root history of the current tree
+    #[account(
+        seeds = [ROOT_HISTORY_SEED, pool.key().as_ref(), &pool.current_tree.to_le_bytes()],
+        bump
+    )]
+    pub root_history: Account<'info, RootHistory>,
+    
+    #[account(
+        seeds = [DEPOSIT_ACTIVITY_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub deposit_activity: Account<'info, DepositActivity>,
+    
+    /// CHECK: Pool vault, only its balance is read
+    #[account(
+        seeds = [b"vault", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_vault: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct IsSpent<'info> {
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        seeds = [b"nullifiers", pool.key().as_ref()],
+        bump
+    )]
+    pub nullifier_registry: Account<'info, NullifierRegistry>,
+}
+
+#[derive(Accounts)]
+#[instruction(tree_index: u32)]
+pub struct FindCommitment<'info> {
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        seeds = [MERKLE_SEED, pool.key().as_ref(), &tree_index.to_le_bytes()],
+        bump
+    )]
+    pub merkle_tree: Account<'info, MerkleTree>,
+}
+
+#[derive(Accounts)]
+pub struct SetPoolConfig<'info> {
+    #[account(
+        mut,
+        seeds = [POOL_SEED, &[pool.pool_type.to_u8()]],
+        bump = pool.bump,
+        has_one = authority @ MixerError::Unauthorized
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePool<'info> {
+    #[account(
+        mut,
+        seeds = [POOL_SEED, &[pool.pool_type.to_u8()]],
+        bump = pool.bump,
+        has_one = authority @ MixerError::Unauthorized,
+        realloc = 8 + Pool::SIZE,
+        realloc::payer = authority,
+        realloc::zero = false
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    /// CHECK: The tree at version 0's seeds; closed once its leaves have
+    /// moved to `merkle_tree`
+    #[account(
+        mut,
+        seeds = [MERKLE_SEED, &[pool.pool_type.to_u8()]],
+        bump
+    )]
+    pub legacy_tree: UncheckedAccount<'info>,
+    
+    /// CHECK: Tree 0 at its current seeds; created and grown here, and read
+    /// raw until the leaves are copied in
+    #[account(
+        mut,
+        seeds = [MERKLE_SEED, pool.key().as_ref(), &0u32.to_le_bytes()],
+        bump
+    )]
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    /// CHECK: Tree 0's root history, created here if the pool has none
+    #[account(
+        mut,
+        seeds = [ROOT_HISTORY_SEED, pool.key().as_ref(), &0u32.to_le_bytes()],
+        bump
+    )]
+    pub root_history: UncheckedAccount<'info>,
+    
+    /// CHECK: The pool's deposit activity, created here if it has none
+    #[account(
+        mut,
+        seeds = [DEPOSIT_ACTIVITY_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub deposit_activity: UncheckedAccount<'info>,
+    
+    /// CHECK: The pool's nullifier registry; read raw because a full one may
+    /// not deserialize until it has grown
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"nullifiers", pool.key().as_ref()],
+        bump
+    )]
+    pub nullifier_registry: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeDenyList<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + DenyList::SIZE,
+        seeds = [DENY_LIST_SEED],
+        bump
+    )]
+    pub deny_list: Account<'info, DenyList>,
+    
+    #[account(
+        constraint = is_deployment_authority(&deployment_authority, &program_data)
+            @ MixerError::Unauthorized
+    )]
+    pub deployment_authority: Signer<'info>,
+    
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = bpf_loader_upgradeable::ID
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetDenyListAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [DENY_LIST_SEED],
+        bump = deny_list.bump,
+        has_one = authority @ MixerError::Unauthorized
+    )]
+    pub deny_list: Account<'info, DenyList>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(address: Pubkey)]
+pub struct DenyAddress<'info> {
+    #[account(
+        mut,
+        seeds = [DENY_LIST_SEED],
+        bump = deny_list.bump,
+        has_one = authority @ MixerError::Unauthorized
+    )]
+    pub deny_list: Account<'info, DenyList>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + DeniedAddress::SIZE,
+        seeds = [DENIED_SEED, address.as_ref()],
+        bump
+    )]
+    pub denied_address: Account<'info, DeniedAddress>,
+    
+    pub authority: Signer<'info>,
+    
     #[account(mut)]
-    pub relayer: AccountInfo<'info>,
+    pub payer: Signer<'info>,
     
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct GetPoolStats<'info> {
+pub struct InitializeAssociationRegistry<'info> {
+    #[account(
+        seeds = [POOL_SEED, &[pool.pool_type.to_u8()]],
+        bump = pool.bump,
+        has_one = authority @ MixerError::Unauthorized
+    )]
     pub pool: Account<'info, Pool>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AssociationRootRegistry::SIZE,
+        seeds = [ASSOCIATION_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub association_registry: Account<'info, AssociationRootRegistry>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PublishAssociationRoot<'info> {
+    #[account(
+        mut,
+        seeds = [ASSOCIATION_SEED, association_registry.pool.as_ref()],
+        bump = association_registry.bump,
+        has_one = curator @ MixerError::Unauthorized
+    )]
+    pub association_registry: Account<'info, AssociationRootRegistry>,
+    
+    pub curator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(address: Pubkey)]
+pub struct AllowAddress<'info> {
+    #[account(
+        mut,
+        seeds = [DENY_LIST_SEED],
+        bump = deny_list.bump,
+        has_one = authority @ MixerError::Unauthorized
+    )]
+    pub deny_list: Account<'info, DenyList>,
+    
+    #[account(
+        mut,
+        close = payer,
+        seeds = [DENIED_SEED, address.as_ref()],
+        bump = denied_address.bump
+    )]
+    pub denied_address: Account<'info, DeniedAddress>,
+    
+    pub authority: Signer<'info>,
+    
+    /// CHECK: Receives the entry's rent
+    #[account(mut)]
+    pub payer: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeShieldedPool<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ShieldedPool::SIZE,
+        seeds = [SHIELDED_POOL_SEED],
+        bump
+    )]
+    pub shielded_pool: Account<'info, ShieldedPool>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = MAX_PERMITTED_DATA_INCREASE,
+        seeds = [MERKLE_SEED, shielded_pool.key().as_ref(), &0u32.to_le_bytes()],
+        bump
+    )]
+    pub merkle_tree: Account<'info, MerkleTree>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + RootHistory::SIZE,
+        seeds = [ROOT_HISTORY_SEED, shielded_pool.key().as_ref(), &0u32.to_le_bytes()],
+        bump
+    )]
+    pub root_history: Account<'info, RootHistory>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + NullifierRegistry::space_for(0),
+        seeds = [b"nullifiers", shielded_pool.key().as_ref()],
+        bump
+    )]
+    pub nullifier_registry: Account<'info, NullifierRegistry>,
+    
+    /// CHECK: Pool vault, created here as a program-owned account so
+    /// `transact` can pay out of it
+    #[account(
+        mut,
+        seeds = [b"vault", shielded_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_vault: AccountInfo<'info>,
+    
+    #[account(
+        constraint = is_deployment_authority(&deployment_authority, &program_data)
+            @ MixerError::Unauthorized
+    )]
+    pub deployment_authority: Signer<'info>,
+    
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = bpf_loader_upgradeable::ID
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OpenShieldedTree<'info> {
+    #[account(
+        seeds = [SHIELDED_POOL_SEED],
+        bump = shielded_pool.bump
+    )]
+    pub shielded_pool: Account<'info, ShieldedPool>,
+    
+    #[account(
+        seeds = [
+            MERKLE_SEED,
+            shielded_pool.key().as_ref(),
+            &shielded_pool.current_tree.to_le_bytes()
+        ],
+        bump
+    )]
+    pub current_tree: Account<'info, MerkleTree>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = MAX_PERMITTED_DATA_INCREASE,
+        seeds = [
+            MERKLE_SEED,
+            shielded_pool.key().as_ref(),
+            &(shielded_pool.current_tree + 1).to_le_bytes()
+        ],
+        bump
+    )]
+    pub next_tree: Account<'info, MerkleTree>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + RootHistory::SIZE,
+        seeds = [
+            ROOT_HISTORY_SEED,
+            shielded_pool.key().as_ref(),
+            &(shielded_pool.current_tree + 1).to_le_bytes()
+        ],
+        bump
+    )]
+    pub next_root_history: Account<'info, RootHistory>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(tree_index: u32)]
+pub struct GrowShieldedTree<'info> {
+    #[account(
+        mut,
+        seeds = [SHIELDED_POOL_SEED],
+        bump = shielded_pool.bump
+    )]
+    pub shielded_pool: Account<'info, ShieldedPool>,
+    
+    /// The pool's current tree, or the one `open_shielded_tree` created
+    #[account(
+        mut,
+        seeds = [MERKLE_SEED, shielded_pool.key().as_ref(), &tree_index.to_le_bytes()],
+        bump
+    )]
+    pub merkle_tree: Account<'info, MerkleTree>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetShieldedPoolConfig<'info> {
+    #[account(
+        mut,
+        seeds = [SHIELDED_POOL_SEED],
+        bump = shielded_pool.bump,
+        has_one = authority @ MixerError::Unauthorized
+    )]
+    pub shielded_pool: Account<'info, ShieldedPool>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(tree_index: u32)]
+pub struct Transact<'info> {
+    #[account(
+        mut,
+        seeds = [SHIELDED_POOL_SEED],
+        bump = shielded_pool.bump
+    )]
+    pub shielded_pool: Account<'info, ShieldedPool>,
+    
+    /// The current tree, which takes the outputs
+    #[account(
+        mut,
+        seeds = [
+            MERKLE_SEED,
+            shielded_pool.key().as_ref(),
+            &shielded_pool.current_tree.to_le_bytes()
+        ],
+        bump
+    )]
+    pub merkle_tree: Account<'info, MerkleTree>,
+    
+    #[account(
+        mut,
+        seeds = [
+            ROOT_HISTORY_SEED,
+            shielded_pool.key().as_ref(),
+            &shielded_pool.current_tree.to_le_bytes()
+        ],
+        bump
+    )]
+    pub root_history: Account<'info, RootHistory>,
+    
+    /// Root history of the tree the inputs were created in; the same
+    /// account as `root_history` while that is the current tree
+    #[account(
+        seeds = [ROOT_HISTORY_SEED, shielded_pool.key().as_ref(), &tree_index.to_le_bytes()],
+        bump
+    )]
+    pub proven_root_history: Account<'info, RootHistory>,
+    
+    #[account(
+        mut,
+        seeds = [b"nullifiers", shielded_pool.key().as_ref()],
+        bump
+    )]
+    pub nullifier_registry: Account<'info, NullifierRegistry>,
+    
+    /// CHECK: Pool vault
+    #[account(
+        mut,
+        seeds = [b"vault", shielded_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_vault: AccountInfo<'info>,
+    
+    /// Pays deposits; usually the relayer otherwise
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    
+    /// CHECK: The sender's deny list entry; must not exist for a deposit
+    #[account(
+        seeds = [DENIED_SEED, sender.key().as_ref()],
+        bump
+    )]
+    pub denied_sender: AccountInfo<'info>,
+    
+    /// CHECK: Must be `ext_data.recipient`
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+    
+    /// CHECK: Must be `ext_data.relayer`
+    #[account(mut)]
+    pub relayer: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
 }
 
 // ============================================================================
@@ -304,32 +2187,261 @@ pub struct Pool {
     pub deposit_amount: u64,
     pub total_deposits: u64,
     pub total_withdrawals: u64,
+    /// Root of the current tree
     pub merkle_root: [u8; 32],
+    /// Leaves inserted across all of the pool's trees
     pub next_index: u64,
     pub bump: u8,
+    /// Largest balance of outstanding deposits the pool may hold, in lamports
+    pub max_tvl: u64,
+    pub max_deposits_per_epoch: u64,
+    /// `unix_timestamp / DEPOSIT_EPOCH_SECONDS` of the last deposit
+    pub current_epoch: u64,
+    pub epoch_deposits: u64,
+    /// Anonymity delay: how old a proven root must be, in slots and in
+    /// deposits made since. Zero disables either.
+    pub min_leaf_age_slots: u64,
+    pub min_leaf_age_deposits: u64,
+    /// Index of the tree deposits go into; earlier trees are full
+    pub current_tree: u32,
+    /// Layout version; pools created before versioning read 0 until
+    /// `migrate_pool`
+    pub version: u8,
+    /// Set by `enter_emergency_mode`: only `emergency_withdraw` is allowed
+    pub emergency: bool,
+    /// Set by `retire_pool`: no more deposits, and `close_pool` once drained
+    pub retired: bool,
 }
 
 impl Pool {
-    pub const SIZE: usize = 32 + 1 + 8 + 8 + 8 + 32 + 8 + 1 + 64; // padding
+    pub const VERSION: u8 = 1;
+    
+    // Version 0 ended at `bump`, followed by 64 bytes of padding. Everything
+    // after `bump` came later; a version 0 pool reads those fields as zero
+    // until `migrate_pool` grows it to this size and sets its caps.
+    pub const SIZE: usize = 32 + 1 + 8 + 8 + 8 + 32 + 8 + 1
+        + 8 + 8 + 8 + 8 + 8 + 8 + 4 + 1 + 1 + 1
+        + 64; // padding
+}
+
+/// Ring of one tree's most recent roots, newest at `current_index`
+#[account]
+pub struct RootHistory {
+    pub entries: [RootEntry; ROOT_HISTORY_SIZE],
+    pub current_index: u8,
+    /// Layout version
+    pub version: u8,
+}
+
+impl RootHistory {
+    pub const VERSION: u8 = 1;
+    
+    pub const SIZE: usize = RootEntry::SIZE * ROOT_HISTORY_SIZE + 1 + 1;
+
+    /// The recorded entry for `root`, if it is still in the ring
+    pub fn find(&self, root: &[u8; 32]) -> Option<&RootEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.leaf_count > 0 && &entry.root == root)
+    }
+    
+    /// How many roots a withdrawal can currently prove against
+    pub fn depth(&self) -> u8 {
+        self.entries.iter().filter(|entry| entry.leaf_count > 0).count() as u8
+    }
+}
+
+/// Deposits per hour over the last `ACTIVITY_BUCKETS` hours. Bucket `h %
+/// ACTIVITY_BUCKETS` counts hour `h` (`unix_timestamp / ACTIVITY_BUCKET_SECONDS`)
+/// while `h` is within a week of `last_bucket`.
+#[account]
+pub struct DepositActivity {
+    pub buckets: [u32; ACTIVITY_BUCKETS],
+    pub last_bucket: u64,
+    /// Layout version
+    pub version: u8,
+}
+
+impl DepositActivity {
+    pub const VERSION: u8 = 1;
+    
+    pub const SIZE: usize = 4 * ACTIVITY_BUCKETS + 8 + 1;
+    
+    /// Count `deposits` made at `unix_timestamp`
+    pub fn record(&mut self, unix_timestamp: i64, deposits: u32) {
+        let bucket = activity_bucket(unix_timestamp);
+        if bucket > self.last_bucket {
+            // Clear the hours nobody deposited in since the last deposit
+            let stale = (bucket - self.last_bucket).min(ACTIVITY_BUCKETS as u64);
+            for offset in 0..stale {
+                let hour = bucket - offset;
+                self.buckets[(hour % ACTIVITY_BUCKETS as u64) as usize] = 0;
+            }
+            self.last_bucket = bucket;
+        }
+        let slot = (self.last_bucket % ACTIVITY_BUCKETS as u64) as usize;
+        self.buckets[slot] = self.buckets[slot].saturating_add(deposits);
+    }
+    
+    /// Deposits in the current hour and the `hours - 1` before it
+    pub fn deposits_in_last(&self, unix_timestamp: i64, hours: u64) -> u64 {
+        let now = activity_bucket(unix_timestamp);
+        (0..hours.min(ACTIVITY_BUCKETS as u64))
+            .filter_map(|back| now.checked_sub(back))
+            .filter(|hour| {
+                *hour <= self.last_bucket && self.last_bucket - hour < ACTIVITY_BUCKETS as u64
+            })
+            .map(|hour| self.buckets[(hour % ACTIVITY_BUCKETS as u64) as usize] as u64)
+            .sum()
+    }
 }
 
 #[account]
 pub struct MerkleTree {
     pub leaves: Vec<[u8; 32]>,
     pub filled_subtrees: [[u8; 32]; MERKLE_TREE_HEIGHT],
+    /// Layout version; trees created before versioning read 0 until
+    /// `migrate_pool`
+    pub version: u8,
+}
+
+impl MerkleTree {
+    pub const VERSION: u8 = 1;
+    
+    pub const SIZE: usize = 4 + (32 * 1024) + (32 * MERKLE_TREE_HEIGHT) + 64; // First 1024 leaves + subtrees
+    
+    /// Serialized size of a tree holding `leaves` leaves
+    pub fn space_for(leaves: usize) -> usize {
+        4 + (32 * leaves) + (32 * MERKLE_TREE_HEIGHT) + 1
+    }
+}
+
+#[account]
+pub struct NullifierRegistry {
+    pub used_nullifiers: Vec<[u8; 32]>,
+    /// Layout version; registries created before versioning read 0 until
+    /// `migrate_pool`
+    pub version: u8,
+}
+
+impl NullifierRegistry {
+    pub const VERSION: u8 = 1;
+    
+    pub const SIZE: usize = 4 + (32 * 10000) + 64; // Up to 10k nullifiers
+    
+    /// Serialized size of a registry holding `nullifiers` nullifiers
+    pub fn space_for(nullifiers: usize) -> usize {
+        4 + (32 * nullifiers) + 1
+    }
+}
+
+/// Roots of curated association sets for one pool, newest at `current_index`
+#[account]
+pub struct AssociationRootRegistry {
+    pub pool: Pubkey,
+    pub curator: Pubkey,
+    pub roots: [[u8; 32]; ASSOCIATION_ROOT_HISTORY],
+    pub current_index: u8,
+    pub published_count: u64,
+    pub bump: u8,
+    /// Layout version
+    pub version: u8,
+}
+
+impl AssociationRootRegistry {
+    pub const VERSION: u8 = 1;
+    
+    pub const SIZE: usize = 32 + 32 + (32 * ASSOCIATION_ROOT_HISTORY) + 1 + 8 + 1 + 1;
+}
+
+/// One per deployment. Entries are separate `DeniedAddress` PDAs so the list
+/// can grow without bound.
+#[account]
+pub struct DenyList {
+    pub authority: Pubkey,
+    pub entry_count: u64,
+    pub bump: u8,
+    /// Layout version
+    pub version: u8,
+}
+
+impl DenyList {
+    pub const VERSION: u8 = 1;
+    
+    pub const SIZE: usize = 32 + 8 + 1 + 1;
+}
+
+#[account]
+pub struct DeniedAddress {
+    pub address: Pubkey,
+    pub added_at: i64,
+    pub bump: u8,
+    /// Layout version
+    pub version: u8,
+}
+
+impl DeniedAddress {
+    pub const VERSION: u8 = 1;
+    
+    pub const SIZE: usize = 32 + 8 + 1 + 1;
+}
+
+/// The join-split pool, one per deployment. Notes hold any amount. Its trees,
+/// root histories, vault and nullifier registry use a fixed pool's seeds with
+/// this account's key.
+#[account]
+pub struct ShieldedPool {
+    pub authority: Pubkey,
+    pub merkle_root: [u8; 32],
+    pub next_index: u64,
+    pub total_transactions: u64,
+    pub bump: u8,
+    /// Largest balance the vault may hold after a deposit, in lamports
+    pub max_tvl: u64,
+    /// Deposits (transactions with a positive `ext_amount`) per epoch
+    pub max_deposits_per_epoch: u64,
+    /// `unix_timestamp / DEPOSIT_EPOCH_SECONDS` of the last deposit
+    pub current_epoch: u64,
+    pub epoch_deposits: u64,
+    /// Anonymity delay: how old a proven root must be, in slots and in notes
+    /// inserted since. Zero disables either.
+    pub min_leaf_age_slots: u64,
+    pub min_leaf_age_deposits: u64,
+    /// Set by `enter_shielded_emergency_mode`: every `transact` is refused
+    pub emergency: bool,
+    /// Set by `retire_shielded_pool`: no more deposits
+    pub retired: bool,
+    /// Tree that takes new outputs; earlier trees are full
+    pub current_tree: u32,
+    /// Layout version
+    pub version: u8,
 }
 
-impl MerkleTree {
-    pub const SIZE: usize = 4 + (32 * 1024) + (32 * MERKLE_TREE_HEIGHT) + 64; // First 1024 leaves + subtrees
+impl ShieldedPool {
+    pub const VERSION: u8 = 1;
+    
+    // The caps, leaf ages, flags, current tree and version came out of the
+    // original 64 bytes of padding
+    pub const SIZE: usize =
+        32 + 32 + 8 + 8 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 4 + 1 + 9; // padding
 }
 
+/// A commitment to an emergency withdrawal, at the PDA of its claim hash
 #[account]
-pub struct NullifierRegistry {
-    pub used_nullifiers: Vec<[u8; 32]>,
+pub struct EmergencyClaim {
+    /// Paid the rent, and gets it back when the note is revealed
+    pub claimant: Pubkey,
+    /// Slot the claim was committed in
+    pub slot: u64,
+    pub bump: u8,
+    /// Layout version
+    pub version: u8,
 }
 
-impl NullifierRegistry {
-    pub const SIZE: usize = 4 + (32 * 10000) + 64; // Up to 10k nullifiers
+impl EmergencyClaim {
+    pub const VERSION: u8 = 1;
+    
+    pub const SIZE: usize = 32 + 8 + 1 + 1;
 }
 
 // ============================================================================
@@ -355,6 +2467,19 @@ impl PoolType {
     }
 }
 
+/// A root and when it was created: the slot, and how many leaves the pool
+/// held at that point (across all trees, like `Pool::next_index`)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct RootEntry {
+    pub root: [u8; 32],
+    pub slot: u64,
+    pub leaf_count: u64,
+}
+
+impl RootEntry {
+    pub const SIZE: usize = 32 + 8 + 8;
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct ZkProof {
     pub a: [u8; 64],
@@ -362,14 +2487,71 @@ pub struct ZkProof {
     pub c: [u8; 64],
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize)]
+/// The part of a `transact` that is not in the circuit. The proof commits to
+/// it through `ext_data_hash` (see `hash_ext_data`).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct ExtData {
+    pub recipient: Pubkey,
+    /// Lamports into the pool from the sender (positive) or out to
+    /// `recipient` (negative)
+    pub ext_amount: i64,
+    pub relayer: Pubkey,
+    /// Paid to `relayer` from the pool
+    pub fee: u64,
+    /// The output notes, encrypted to their owners' shielded addresses and
+    /// published in `TransactEvent` (opaque to the program)
+    pub encrypted_output1: Vec<u8>,
+    pub encrypted_output2: Vec<u8>,
+}
+
+/// Everything a fixed-pool withdrawal does, as the note's owner hands it to a
+/// relayer. The proof commits to it through `ext_data_hash` (see
+/// `hash_withdraw_intent`), so the relayer can neither change a field nor
+/// submit it after `expiry_slot`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct WithdrawIntent {
+    pub pool_type: PoolType,
+    pub root: [u8; 32],
+    pub nullifier_hash: [u8; 32],
+    pub recipient: Pubkey,
+    /// Paid `fee` out of the deposit; `None` for a withdrawal without one
+    pub relayer: Option<Pubkey>,
+    pub fee: u64,
+    /// Last slot the withdrawal may land in
+    pub expiry_slot: u64,
+}
+
+/// Return data of `find_commitment`: a leaf's tree and its index in it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LeafPosition {
+    pub tree_index: u32,
+    pub leaf_index: u64,
+}
+
+/// Return data of `get_pool_stats`. The layout is stable: `version` comes
+/// first, new fields are only appended (bumping `POOL_STATS_VERSION`) and
+/// existing ones never change, so older clients can decode the prefix they
+/// know.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
 pub struct PoolStats {
+    pub version: u8,
     pub pool_type: PoolType,
     pub deposit_amount: u64,
     pub total_deposits: u64,
     pub total_withdrawals: u64,
-    pub current_balance: u64,
+    /// Deposits not yet withdrawn
+    pub anonymity_set_size: u64,
+    pub vault_lamports: u64,
+    pub deposits_last_24h: u64,
+    pub deposits_last_7d: u64,
     pub merkle_root: [u8; 32],
+    /// Roots a withdrawal can currently prove against
+    pub root_history_depth: u8,
+    pub min_leaf_age_slots: u64,
+    pub min_leaf_age_deposits: u64,
+    // Version 2
+    /// Tree deposits go into; `merkle_root` and `root_history_depth` are its
+    pub current_tree: u32,
 }
 
 // ============================================================================
@@ -382,6 +2564,9 @@ pub struct DepositEvent {
     pub leaf_index: u64,
     pub timestamp: i64,
     pub pool_type: PoolType,
+    pub encrypted_note: Option<Vec<u8>>,
+    /// Tree the leaf went into; `leaf_index` is its position in that tree
+    pub tree_index: u32,
 }
 
 #[event]
@@ -391,6 +2576,102 @@ pub struct WithdrawEvent {
     pub relayer: Option<Pubkey>,
     pub fee: u64,
     pub timestamp: i64,
+    /// Set when the withdrawal also proved association-set membership
+    pub association_root: Option<[u8; 32]>,
+    pub pool_type: PoolType,
+}
+
+#[event]
+pub struct TransactEvent {
+    pub input_nullifiers: [[u8; 32]; 2],
+    pub output_commitments: [[u8; 32]; 2],
+    /// Leaf index of the first output; the second follows it
+    pub first_leaf_index: u64,
+    pub public_amount: i64,
+    pub timestamp: i64,
+    /// `ExtData`'s encrypted outputs, in output order
+    pub encrypted_output1: Vec<u8>,
+    pub encrypted_output2: Vec<u8>,
+    /// Tree the outputs went into; `first_leaf_index` is within it
+    pub tree_index: u32,
+}
+
+/// A note of `from_pool_type` spent into `count` notes of `to_pool_type`,
+/// each also announced by a `DepositEvent`
+#[event]
+pub struct SwapEvent {
+    pub nullifier_hash: [u8; 32],
+    pub from_pool_type: PoolType,
+    pub to_pool_type: PoolType,
+    /// Leaf index of the first new note in tree `tree_index` of the target
+    pub first_leaf_index: u64,
+    pub count: u32,
+    pub tree_index: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmergencyModeEntered {
+    pub pool_type: PoolType,
+    pub timestamp: i64,
+}
+
+/// A deposit reclaimed in emergency mode; unlike `WithdrawEvent`, it names
+/// the commitment and so links the deposit to `recipient`
+#[event]
+pub struct EmergencyWithdrawEvent {
+    pub commitment: [u8; 32],
+    pub nullifier_hash: [u8; 32],
+    pub recipient: Pubkey,
+    pub tree_index: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolCapsUpdated {
+    pub pool_type: PoolType,
+    pub max_tvl: u64,
+    pub max_deposits_per_epoch: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TreeOpened {
+    pub pool_type: PoolType,
+    pub tree_index: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolRetirement {
+    pub pool_type: PoolType,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TreeClosed {
+    pub pool_type: PoolType,
+    pub tree_index: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AssociationRootPublished {
+    pub pool: Pubkey,
+    pub association_root: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AddressDenied {
+    pub address: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AddressAllowed {
+    pub address: Pubkey,
+    pub timestamp: i64,
 }
 
 // ============================================================================
@@ -405,19 +2686,73 @@ pub enum MixerError {
     NullifierAlreadyUsed,
     #[msg("Invalid zero-knowledge proof")]
     InvalidProof,
-    #[msg("Pool is full")]
+    #[msg("The pool's current Merkle tree is full; open the next one")]
     PoolFull,
     #[msg("Arithmetic error")]
     ArithmeticError,
     #[msg("Invalid pool type")]
     InvalidPoolType,
+    #[msg("Encrypted note is too large")]
+    EncryptedNoteTooLarge,
+    #[msg("Depositor is on the deny list")]
+    DepositorBlocked,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Association root is not in the registry")]
+    UnknownAssociationRoot,
+    #[msg("Deposit would exceed the pool's TVL cap")]
+    TvlCapExceeded,
+    #[msg("Deposit cap for this epoch has been reached")]
+    EpochDepositCapExceeded,
+    #[msg("Root is not in the pool's recent root history")]
+    UnknownRoot,
+    #[msg("Root is younger than the pool's minimum leaf age")]
+    RootTooYoung,
+    #[msg("Batch must hold between 1 and MAX_DEPOSIT_BATCH commitments")]
+    InvalidBatchSize,
+    #[msg("The pool's current Merkle tree still has room")]
+    TreeNotFull,
+    #[msg("External data does not match ext_data_hash or the accounts")]
+    InvalidExtData,
+    #[msg("Public amount is not ext_amount - fee")]
+    InvalidPublicAmount,
+    #[msg("A swap must go to another pool and re-deposit exactly the spent amount")]
+    InvalidSwap,
+    #[msg("Pool is in emergency mode; only emergency withdrawals are allowed")]
+    PoolInEmergency,
+    #[msg("Emergency withdrawals need the pool in emergency mode")]
+    NotInEmergency,
+    #[msg("No leaf of the tree matches the note")]
+    UnknownCommitment,
+    #[msg("Withdraw intent has expired")]
+    IntentExpired,
+    #[msg("Pool is retired and takes no deposits")]
+    PoolRetired,
+    #[msg("Only a retired pool can be closed")]
+    PoolNotRetired,
+    #[msg("Pool still holds deposits that have not been withdrawn")]
+    PoolNotDrained,
+    #[msg("A root of the tree is younger than RETIRED_ROOT_WINDOW_SLOTS")]
+    RootsStillRecent,
+    #[msg("Proof coordinates are not canonical BN254 field elements")]
+    InvalidProofEncoding,
+    #[msg("Relayer fee is above its cap")]
+    FeeAboveCap,
+    #[msg("Recipient account is not the one the proof commits to")]
+    RecipientMismatch,
+    #[msg("Relayer account is not the one the proof commits to")]
+    RelayerMismatch,
+    #[msg("Pool vault holds less than the payout")]
+    VaultInsufficient,
+    #[msg("Emergency claim is too recent to reveal its note")]
+    EmergencyClaimTooRecent,
 }
 
 // ============================================================================
 // HELPER FUNCTIONS
 // ============================================================================
 
-fn get_deposit_amount(pool_type: &PoolType) -> u64 {
+pub fn get_deposit_amount(pool_type: &PoolType) -> u64 {
     match pool_type {
         PoolType::Sol0_1 => DEPOSIT_0_1_SOL,
         PoolType::Sol1 => DEPOSIT_1_SOL,
@@ -426,6 +2761,20 @@ fn get_deposit_amount(pool_type: &PoolType) -> u64 {
     }
 }
 
+/// The governance program's PDA, which signs for the protocol once a
+/// proposal passes
+pub fn governance_address() -> Pubkey {
+    Pubkey::find_program_address(&[rift_governance::GOVERNANCE_SEED], &rift_governance::ID).0
+}
+
+/// Whether `signer` may make deployment-wide changes: the governance PDA, or
+/// the program's upgrade authority (which sets the deployment up before
+/// governance takes over)
+fn is_deployment_authority(signer: &Signer, program_data: &ProgramData) -> bool {
+    signer.key() == governance_address()
+        || program_data.upgrade_authority_address == Some(signer.key())
+}
+
 fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
     let mut combined = [0u8; 64];
     combined[..32].copy_from_slice(left);
@@ -437,6 +2786,420 @@ fn is_commitment_used(merkle_tree: &MerkleTree, commitment: &[u8; 32]) -> bool {
     merkle_tree.leaves.contains(commitment)
 }
 
+/// Whether `count` more leaves fit in the tree: within the height's capacity
+/// and within the space its account was allocated
+fn tree_has_room(merkle_tree: &Account<MerkleTree>, count: usize) -> bool {
+    let leaves = merkle_tree.leaves.len() + count;
+    leaves <= MAX_DEPOSITS
+        && 8 + MerkleTree::space_for(leaves) <= merkle_tree.to_account_info().data_len()
+}
+
+/// Require room for `count` more deposits under the TVL cap and the current
+/// epoch's cap (rolling the epoch over first if it has ended), in a pool not
+/// in emergency mode
+fn check_deposit_limits(pool: &mut Pool, count: u64) -> Result<()> {
+    require!(!pool.emergency, MixerError::PoolInEmergency);
+    require!(!pool.retired, MixerError::PoolRetired);
+    let outstanding = pool.total_deposits.saturating_sub(pool.total_withdrawals);
+    let tvl_after = outstanding
+        .checked_add(count)
+        .and_then(|deposits| deposits.checked_mul(pool.deposit_amount))
+        .ok_or(MixerError::ArithmeticError)?;
+    require!(tvl_after <= pool.max_tvl, MixerError::TvlCapExceeded);
+    
+    check_epoch_cap(
+        &mut pool.current_epoch,
+        &mut pool.epoch_deposits,
+        pool.max_deposits_per_epoch,
+        count,
+    )
+}
+
+/// `check_deposit_limits` for a shielded deposit of `amount` lamports into a
+/// vault holding `vault_balance`
+fn check_shielded_deposit_limits(
+    pool: &mut ShieldedPool,
+    vault_balance: u64,
+    amount: u64,
+) -> Result<()> {
+    require!(!pool.emergency, MixerError::PoolInEmergency);
+    require!(!pool.retired, MixerError::PoolRetired);
+    let tvl_after = vault_balance
+        .checked_add(amount)
+        .ok_or(MixerError::ArithmeticError)?;
+    require!(tvl_after <= pool.max_tvl, MixerError::TvlCapExceeded);
+    
+    check_epoch_cap(
+        &mut pool.current_epoch,
+        &mut pool.epoch_deposits,
+        pool.max_deposits_per_epoch,
+        1,
+    )
+}
+
+/// Roll `current_epoch` over if the epoch has ended, then require room in it
+/// for `count` more deposits
+fn check_epoch_cap(
+    current_epoch: &mut u64,
+    epoch_deposits: &mut u64,
+    max_deposits_per_epoch: u64,
+    count: u64,
+) -> Result<()> {
+    let epoch = (Clock::get()?.unix_timestamp / DEPOSIT_EPOCH_SECONDS) as u64;
+    if epoch != *current_epoch {
+        *current_epoch = epoch;
+        *epoch_deposits = 0;
+    }
+    let epoch_after = epoch_deposits
+        .checked_add(count)
+        .ok_or(MixerError::ArithmeticError)?;
+    require!(epoch_after <= max_deposits_per_epoch, MixerError::EpochDepositCapExceeded);
+    Ok(())
+}
+
+/// Insert `commitments` into the pool's current tree with a `DepositEvent`
+/// each, and record only the final root. The caller has already checked the
+/// tree's room and the deposit caps.
+fn insert_deposits(
+    pool: &mut Pool,
+    merkle_tree: &mut MerkleTree,
+    root_history: &mut RootHistory,
+    deposit_activity: &mut DepositActivity,
+    commitments: Vec<[u8; 32]>,
+) -> Result<()> {
+    let count = commitments.len() as u64;
+    
+    // A commitment repeated within the batch is caught because the earlier
+    // copy is already in the tree
+    let clock = Clock::get()?;
+    let mut new_root = pool.merkle_root;
+    for commitment in commitments {
+        require!(
+            !is_commitment_used(merkle_tree, &commitment),
+            MixerError::CommitmentAlreadyUsed
+        );
+        let leaf_index = merkle_tree.leaves.len() as u64;
+        merkle_tree.leaves.push(commitment);
+        new_root = insert_leaf(&mut merkle_tree.filled_subtrees, leaf_index as usize, commitment);
+        pool.next_index += 1;
+        
+        emit!(DepositEvent {
+            commitment,
+            leaf_index,
+            timestamp: clock.unix_timestamp,
+            pool_type: pool.pool_type.clone(),
+            encrypted_note: None,
+            tree_index: pool.current_tree,
+        });
+    }
+    
+    pool.total_deposits += count;
+    pool.epoch_deposits += count;
+    pool.merkle_root = new_root;
+    record_root(root_history, new_root, clock.slot, pool.next_index);
+    deposit_activity.record(clock.unix_timestamp, count as u32);
+    Ok(())
+}
+
+fn activity_bucket(unix_timestamp: i64) -> u64 {
+    (unix_timestamp / ACTIVITY_BUCKET_SECONDS).max(0) as u64
+}
+
+fn record_root(history: &mut RootHistory, root: [u8; 32], slot: u64, leaf_count: u64) {
+    let next = (history.current_index as usize + 1) % ROOT_HISTORY_SIZE;
+    history.entries[next] = RootEntry {
+        root,
+        slot,
+        leaf_count,
+    };
+    history.current_index = next as u8;
+}
+
+/// Require `root` to be in tree `tree_index`'s history and at least the
+/// pool's minimum age. Proofs are not trusted in emergency mode.
+fn check_root(
+    pool: &Pool,
+    tree_index: u32,
+    history: &RootHistory,
+    root: &[u8; 32],
+) -> Result<()> {
+    require!(!pool.emergency, MixerError::PoolInEmergency);
+    let entry = history
+        .find(root)
+        .filter(|_| tree_index <= pool.current_tree)
+        .ok_or_else(|| {
+            fail(
+                MixerError::UnknownRoot,
+                format_args!("root={} tree_index={}", hex(root), tree_index),
+            )
+        })?;
+    
+    check_root_age(entry, pool.next_index, pool.min_leaf_age_slots, pool.min_leaf_age_deposits)
+}
+
+/// `check_root` for the shielded pool. The empty tree's root is always
+/// accepted: the only notes it proves are the zero-amount dummies a deposit
+/// spends, so a new tree's first transaction has a root to name.
+fn check_shielded_root(
+    pool: &ShieldedPool,
+    tree_index: u32,
+    history: &RootHistory,
+    root: &[u8; 32],
+) -> Result<()> {
+    require!(!pool.emergency, MixerError::PoolInEmergency);
+    if *root == zero_hashes(MERKLE_TREE_HEIGHT)[MERKLE_TREE_HEIGHT] {
+        return Ok(());
+    }
+    let entry = history
+        .find(root)
+        .filter(|_| tree_index <= pool.current_tree)
+        .ok_or_else(|| {
+            fail(
+                MixerError::UnknownRoot,
+                format_args!("root={} tree_index={}", hex(root), tree_index),
+            )
+        })?;
+    check_root_age(entry, pool.next_index, pool.min_leaf_age_slots, pool.min_leaf_age_deposits)
+}
+
+/// Require the root `entry` records to be at least `min_slots` slots and
+/// `min_deposits` leaves older than a pool now at `next_index`
+fn check_root_age(
+    entry: &RootEntry,
+    next_index: u64,
+    min_slots: u64,
+    min_deposits: u64,
+) -> Result<()> {
+    let slot_age = Clock::get()?.slot.saturating_sub(entry.slot);
+    let deposit_age = next_index.saturating_sub(entry.leaf_count);
+    require!(
+        slot_age >= min_slots && deposit_age >= min_deposits,
+        MixerError::RootTooYoung
+    );
+    Ok(())
+}
+
+fn is_known_association_root(registry: &AssociationRootRegistry, root: &[u8; 32]) -> bool {
+    *root != [0u8; 32] && registry.roots.contains(root)
+}
+
+/// `intent` must be for this pool and these accounts, pay a relayer no more
+/// than the deposit, and not have expired
+fn check_intent(accounts: &Withdraw, intent: &WithdrawIntent) -> Result<()> {
+    require!(intent.pool_type == accounts.pool.pool_type, MixerError::InvalidExtData);
+    check_account(&accounts.recipient, &intent.recipient, MixerError::RecipientMismatch)?;
+    if let Some(relayer) = intent.relayer {
+        check_account(&accounts.relayer, &relayer, MixerError::RelayerMismatch)?;
+        let cap = accounts.pool.deposit_amount;
+        if intent.fee > cap {
+            let context = format_args!("fee={} cap={}", intent.fee, cap);
+            return Err(fail(MixerError::FeeAboveCap, context));
+        }
+    }
+    require!(Clock::get()?.slot <= intent.expiry_slot, MixerError::IntentExpired);
+    Ok(())
+}
+
+/// Require `account` to be the one the signed data names
+fn check_account(account: &AccountInfo, expected: &Pubkey, error: MixerError) -> Result<()> {
+    if account.key() != *expected {
+        return Err(fail(error, format_args!("expected={} actual={}", expected, account.key())));
+    }
+    Ok(())
+}
+
+/// Require `proof`'s coordinates to be canonical BN254 base field elements.
+/// Whether the points are on the curve is left to the verifier.
+fn check_proof_encoding(proof: &ZkProof) -> Result<()> {
+    let points: [(&str, &[u8]); 3] = [("a", &proof.a), ("b", &proof.b), ("c", &proof.c)];
+    for (point, bytes) in points {
+        for (index, coordinate) in bytes.chunks(32).enumerate() {
+            if coordinate >= BN254_BASE_FIELD_SIZE.as_slice() {
+                return Err(fail(
+                    MixerError::InvalidProofEncoding,
+                    format_args!("point={} coordinate={} value={}", point, index, hex(coordinate)),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `InvalidProof` for a well-formed proof the verifier rejected
+fn proof_rejected(root: &[u8; 32], nullifier_hash: &[u8; 32]) -> Error {
+    fail(
+        MixerError::InvalidProof,
+        format_args!("root={} nullifier_hash={}", hex(root), hex(nullifier_hash)),
+    )
+}
+
+/// Log `error`'s name and the values that failed the check as `key=value`
+/// pairs, so a failed transaction's logs say which one was wrong
+fn fail(error: MixerError, context: std::fmt::Arguments) -> Error {
+    msg!("{}: {}", error.name(), context);
+    error.into()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Spend the intent's nullifier and release the deposit to the recipient
+/// (less the relayer's fee). The caller has already checked the proof.
+fn pay_out(
+    accounts: &mut Withdraw,
+    intent: &WithdrawIntent,
+    association_root: Option<[u8; 32]>,
+) -> Result<()> {
+    let pool = &mut accounts.pool;
+    let WithdrawIntent { nullifier_hash, recipient, relayer, fee, .. } = *intent;
+    
+    // Mark nullifier as used
+    mark_nullifier_used(&mut accounts.nullifier_registry, nullifier_hash)?;
+    
+    // Calculate amounts
+    let withdrawal_amount = pool.deposit_amount;
+    let relayer_fee = if relayer.is_some() { fee } else { 0 };
+    let recipient_amount = withdrawal_amount.checked_sub(relayer_fee)
+        .ok_or(MixerError::ArithmeticError)?;
+    
+    // Transfer to recipient
+    move_lamports(&accounts.pool_vault, &accounts.recipient, recipient_amount)?;
+    
+    // Transfer fee to relayer if applicable
+    if let Some(_relayer_key) = relayer {
+        if relayer_fee > 0 {
+            move_lamports(&accounts.pool_vault, &accounts.relayer, relayer_fee)?;
+        }
+    }
+    
+    // Update pool state
+    pool.total_withdrawals += 1;
+    
+    // Emit withdrawal event
+    emit!(WithdrawEvent {
+        nullifier_hash,
+        recipient,
+        relayer,
+        fee: relayer_fee,
+        timestamp: Clock::get()?.unix_timestamp,
+        association_root,
+        pool_type: pool.pool_type.clone(),
+    });
+    
+    msg!("Withdrawal successful to: {}", recipient);
+    Ok(())
+}
+
+/// Move lamports out of a program-owned account
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let balance = from.lamports();
+    let remaining = balance.checked_sub(amount).ok_or_else(|| {
+        fail(
+            MixerError::VaultInsufficient,
+            format_args!("vault={} balance={} amount={}", from.key(), balance, amount),
+        )
+    })?;
+    **from.try_borrow_mut_lamports()? = remaining;
+    **to.try_borrow_mut_lamports()? += amount;
+    Ok(())
+}
+
+/// Length of the `Vec` an account's data starts with (right after the
+/// discriminator)
+fn stored_vec_len(account: &AccountInfo) -> Result<usize> {
+    let data = account.try_borrow_data()?;
+    let len = data
+        .get(8..12)
+        .ok_or(ErrorCode::AccountDidNotDeserialize)?;
+    Ok(u32::from_le_bytes(len.try_into().unwrap()) as usize)
+}
+
+/// Grow `account` to at least `len` bytes, zero-filled, with `payer`
+/// topping up its rent
+fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    len: usize,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    if account.data_len() >= len {
+        return Ok(());
+    }
+    let shortfall = Rent::get()?.minimum_balance(len).saturating_sub(account.lamports());
+    if shortfall > 0 {
+        let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
+            payer.key,
+            account.key,
+            shortfall,
+        );
+        anchor_lang::solana_program::program::invoke(
+            &transfer_ix,
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+    account.realloc(len, true)?;
+    Ok(())
+}
+
+/// Create `account`, the program-owned PDA `seeds` sign for, with `space`
+/// bytes and `payer` paying its rent. Lamports already sent to the address
+/// count towards the rent rather than blocking the creation.
+fn create_pda<'info>(
+    account: &AccountInfo<'info>,
+    space: usize,
+    seeds: &[&[u8]],
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    use anchor_lang::solana_program::program::{invoke, invoke_signed};
+    use anchor_lang::solana_program::system_instruction;
+    
+    let rent = Rent::get()?.minimum_balance(space);
+    let accounts = [payer.clone(), account.clone(), system_program.clone()];
+    if account.lamports() == 0 {
+        let create_ix = system_instruction::create_account(
+            payer.key,
+            account.key,
+            rent,
+            space as u64,
+            &crate::ID,
+        );
+        invoke_signed(&create_ix, &accounts, &[seeds])?;
+        return Ok(());
+    }
+    let shortfall = rent.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        invoke(&system_instruction::transfer(payer.key, account.key, shortfall), &accounts)?;
+    }
+    invoke_signed(&system_instruction::allocate(account.key, space as u64), &accounts, &[seeds])?;
+    invoke_signed(&system_instruction::assign(account.key, &crate::ID), &accounts, &[seeds])?;
+    Ok(())
+}
+
+/// Close a program-owned `account`, sending its rent to `to`
+fn close_account<'info>(account: &AccountInfo<'info>, to: &AccountInfo<'info>) -> Result<()> {
+    move_lamports(account, to, account.lamports())?;
+    account.assign(&anchor_lang::system_program::ID);
+    account.realloc(0, false)?;
+    Ok(())
+}
+
+/// Read `T` from `account`, apply `upgrade` and write it back
+fn upgrade_account<T, R>(account: &AccountInfo, upgrade: impl FnOnce(&mut T) -> R) -> Result<R>
+where
+    T: AccountSerialize + AccountDeserialize,
+{
+    let mut state = T::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+    let result = upgrade(&mut state);
+    state.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])?;
+    Ok(result)
+}
+
+fn is_denied(entry: &AccountInfo, program_id: &Pubkey) -> bool {
+    entry.owner == program_id && !entry.data_is_empty()
+}
+
 fn is_nullifier_used(registry: &NullifierRegistry, nullifier: &[u8; 32]) -> bool {
     registry.used_nullifiers.contains(nullifier)
 }
@@ -446,64 +3209,269 @@ fn mark_nullifier_used(registry: &mut NullifierRegistry, nullifier: [u8; 32]) ->
     Ok(())
 }
 
-fn insert_leaf(merkle_tree: &mut MerkleTree, index: usize, leaf: [u8; 32]) -> Result<()> {
-    merkle_tree.leaves.push(leaf);
-    
-    // Update filled subtrees
+/// The leaf `deposit` inserts for a note, as `rift_sdk::Note` computes it.
+///
+/// This is the withdraw circuit's commitment hash, `keccak(nullifier ‖ secret)`:
+/// `emergency_withdraw` relies on it to find the leaf a proof would open.
+pub fn note_commitment(nullifier: &[u8; 32], secret: &[u8; 32]) -> [u8; 32] {
+    keccak::hashv(&[nullifier, secret]).to_bytes()
+}
+
+/// What `commit_emergency_withdraw` commits to: the note and who it pays
+pub fn emergency_claim_hash(
+    nullifier: &[u8; 32],
+    secret: &[u8; 32],
+    recipient: &Pubkey,
+) -> [u8; 32] {
+    keccak::hashv(&[nullifier, secret, recipient.as_ref()]).to_bytes()
+}
+
+/// The value a withdrawal of the note marks as spent.
+///
+/// This is the withdraw circuit's `nullifier_hash` public input,
+/// `keccak(nullifier)`, so `emergency_withdraw` marks the same value a proof
+/// withdrawal of the note would and neither can follow the other.
+pub fn note_nullifier_hash(nullifier: &[u8; 32]) -> [u8; 32] {
+    keccak::hash(nullifier).to_bytes()
+}
+
+/// Roots of empty subtrees: `zeros[level]` for every level in `0..=height`,
+/// where an empty leaf is all zero bytes
+pub fn zero_hashes(height: usize) -> Vec<[u8; 32]> {
+    let mut zeros = vec![[0u8; 32]];
+    for level in 0..height {
+        let below = zeros[level];
+        zeros.push(hash_pair(&below, &below));
+    }
+    zeros
+}
+
+/// Append `leaf` at `index` to an incremental Merkle tree of height
+/// `filled_subtrees.len()` and return the new root.
+/// The caller guarantees `index` is the next free slot and the tree is not full.
+pub fn insert_leaf(filled_subtrees: &mut [[u8; 32]], index: usize, leaf: [u8; 32]) -> [u8; 32] {
+    let zeros = zero_hashes(filled_subtrees.len());
     let mut current_hash = leaf;
     let mut current_index = index;
     
-    for level in 0..MERKLE_TREE_HEIGHT {
-        if current_index % 2 == 0 {
-            merkle_tree.filled_subtrees[level] = current_hash;
-            break;
+    for level in 0..filled_subtrees.len() {
+        if current_index & 1 == 0 {
+            // Left child: remember it, pair with the empty right sibling
+            filled_subtrees[level] = current_hash;
+            current_hash = hash_pair(&current_hash, &zeros[level]);
         } else {
-            let left = &merkle_tree.filled_subtrees[level];
-            current_hash = hash_pair(left, &current_hash);
-            current_index /= 2;
+            current_hash = hash_pair(&filled_subtrees[level], &current_hash);
         }
+        current_index /= 2;
     }
     
-    Ok(())
+    current_hash
 }
 
-fn compute_merkle_root(merkle_tree: &MerkleTree) -> Result<[u8; 32]> {
-    if merkle_tree.leaves.is_empty() {
-        return Ok([0u8; 32]);
-    }
-    
-    let mut current_level = merkle_tree.leaves.clone();
+/// Recompute the root of a tree of `height` from all of its leaves,
+/// padding the unused slots with empty leaves
+pub fn compute_merkle_root(leaves: &[[u8; 32]], height: usize) -> [u8; 32] {
+    let zeros = zero_hashes(height);
+    let mut current_level = leaves.to_vec();
     
-    for _ in 0..MERKLE_TREE_HEIGHT {
-        if current_level.len() == 1 {
-            return Ok(current_level[0]);
+    for level in 0..height {
+        if current_level.is_empty() {
+            return zeros[height];
         }
         
-        let mut next_level = Vec::new();
-        for i in (0..current_level.len()).step_by(2) {
-            let left = &current_level[i];
-            let right = if i + 1 < current_level.len() {
-                &current_level[i + 1]
-            } else {
-                &[0u8; 32]
-            };
-            next_level.push(hash_pair(left, right));
+        let mut next_level = Vec::with_capacity(current_level.len().div_ceil(2));
+        for pair in current_level.chunks(2) {
+            let right = pair.get(1).unwrap_or(&zeros[level]);
+            next_level.push(hash_pair(&pair[0], right));
+        }
+        current_level = next_level;
+    }
+    
+    current_level.first().copied().unwrap_or(zeros[height])
+}
+
+/// The `filled_subtrees` and root `insert_leaf` leaves after inserting
+/// `leaves` in order, built a level at a time in about two hashes a leaf
+/// instead of `height`
+pub fn rebuild_tree(leaves: &[[u8; 32]], height: usize) -> (Vec<[u8; 32]>, [u8; 32]) {
+    let zeros = zero_hashes(height);
+    let mut filled_subtrees = vec![[0u8; 32]; height];
+    if leaves.is_empty() {
+        return (filled_subtrees, zeros[height]);
+    }
+    
+    let mut current_level = leaves.to_vec();
+    for level in 0..height {
+        // The last left child, as the latest insert below it left it
+        filled_subtrees[level] = current_level[(current_level.len() - 1) & !1];
+        let mut next_level = Vec::with_capacity(current_level.len().div_ceil(2));
+        for pair in current_level.chunks(2) {
+            let right = pair.get(1).unwrap_or(&zeros[level]);
+            next_level.push(hash_pair(&pair[0], right));
         }
         current_level = next_level;
     }
     
-    Ok(current_level[0])
+    (filled_subtrees, current_level[0])
+}
+
+/// Public inputs of the withdraw circuit, in order: Merkle root, nullifier
+/// hash, ext data hash (of the `WithdrawIntent`)
+pub fn withdraw_public_inputs(
+    merkle_root: &[u8; 32],
+    nullifier_hash: &[u8; 32],
+    ext_data_hash: &[u8; 32],
+) -> Vec<[u8; 32]> {
+    vec![*merkle_root, *nullifier_hash, *ext_data_hash]
+}
+
+/// Public inputs of the association-set circuit: the withdraw inputs
+/// followed by the association root the deposit is proven to belong to
+pub fn association_public_inputs(
+    merkle_root: &[u8; 32],
+    association_root: &[u8; 32],
+    nullifier_hash: &[u8; 32],
+    ext_data_hash: &[u8; 32],
+) -> Vec<[u8; 32]> {
+    let mut inputs = withdraw_public_inputs(merkle_root, nullifier_hash, ext_data_hash);
+    inputs.push(*association_root);
+    inputs
+}
+
+/// Public inputs of the swap circuit, in order: Merkle root, nullifier hash,
+/// and keccak of the target pool type and the new commitments (top byte
+/// cleared), so nobody can replace the notes a proof was made for
+pub fn swap_public_inputs(
+    merkle_root: &[u8; 32],
+    nullifier_hash: &[u8; 32],
+    target_pool_type: &PoolType,
+    commitments: &[[u8; 32]],
+) -> Vec<[u8; 32]> {
+    let mut preimage = vec![target_pool_type.to_u8()];
+    for commitment in commitments {
+        preimage.extend_from_slice(commitment);
+    }
+    let mut commitments_hash = keccak::hash(&preimage).to_bytes();
+    commitments_hash[0] = 0;
+    vec![*merkle_root, *nullifier_hash, commitments_hash]
+}
+
+/// `amount` as a BN254 scalar (big-endian): negative amounts wrap around to
+/// `BN254_FIELD_SIZE - |amount|`, as in the circuit
+pub fn amount_to_field(amount: i64) -> [u8; 32] {
+    let magnitude = amount.unsigned_abs();
+    let mut field = [0u8; 32];
+    if amount >= 0 {
+        field[24..].copy_from_slice(&magnitude.to_be_bytes());
+        return field;
+    }
+    
+    let mut limbs = [0u64; 4];
+    for (limb, bytes) in limbs.iter_mut().zip(BN254_FIELD_SIZE.chunks(8)) {
+        *limb = u64::from_be_bytes(bytes.try_into().unwrap());
+    }
+    let mut borrow = magnitude;
+    for limb in limbs.iter_mut().rev() {
+        let (value, underflow) = limb.overflowing_sub(borrow);
+        *limb = value;
+        borrow = underflow as u64;
+    }
+    for (bytes, limb) in field.chunks_mut(8).zip(limbs) {
+        bytes.copy_from_slice(&limb.to_be_bytes());
+    }
+    field
+}
+
+/// Keccak-256 of the Borsh-encoded `ExtData`, with the top byte cleared so
+/// the hash is a BN254 scalar
+pub fn hash_ext_data(ext_data: &ExtData) -> [u8; 32] {
+    let mut hash = keccak::hash(&ext_data.try_to_vec().unwrap()).to_bytes();
+    hash[0] = 0;
+    hash
+}
+
+/// `ext_data_hash` of a withdrawal: Keccak-256 of the Borsh-encoded intent,
+/// top byte cleared like `hash_ext_data`
+pub fn hash_withdraw_intent(intent: &WithdrawIntent) -> [u8; 32] {
+    let mut hash = keccak::hash(&intent.try_to_vec().unwrap()).to_bytes();
+    hash[0] = 0;
+    hash
+}
+
+/// Public inputs of the join-split circuit, in order: Merkle root, public
+/// amount, ext data hash, the input nullifiers and the output commitments
+pub fn transact_public_inputs(
+    merkle_root: &[u8; 32],
+    public_amount: i64,
+    ext_data_hash: &[u8; 32],
+    input_nullifiers: &[[u8; 32]; 2],
+    output_commitments: &[[u8; 32]; 2],
+) -> Vec<[u8; 32]> {
+    let mut inputs = vec![*merkle_root, amount_to_field(public_amount), *ext_data_hash];
+    inputs.extend_from_slice(input_nullifiers);
+    inputs.extend_from_slice(output_commitments);
+    inputs
+}
+
+// Public so off-chain services (the relayer) run the exact same check
+pub fn verify_proof(
+    proof: &ZkProof,
+    merkle_root: &[u8; 32],
+    nullifier_hash: &[u8; 32],
+    ext_data_hash: &[u8; 32],
+) -> bool {
+    verify_groth16(proof, &withdraw_public_inputs(merkle_root, nullifier_hash, ext_data_hash))
+}
+
+pub fn verify_association_proof(
+    proof: &ZkProof,
+    merkle_root: &[u8; 32],
+    association_root: &[u8; 32],
+    nullifier_hash: &[u8; 32],
+    ext_data_hash: &[u8; 32],
+) -> bool {
+    verify_groth16(
+        proof,
+        &association_public_inputs(merkle_root, association_root, nullifier_hash, ext_data_hash),
+    )
+}
+
+pub fn verify_transact_proof(
+    proof: &ZkProof,
+    merkle_root: &[u8; 32],
+    public_amount: i64,
+    ext_data_hash: &[u8; 32],
+    input_nullifiers: &[[u8; 32]; 2],
+    output_commitments: &[[u8; 32]; 2],
+) -> bool {
+    verify_groth16(
+        proof,
+        &transact_public_inputs(
+            merkle_root,
+            public_amount,
+            ext_data_hash,
+            input_nullifiers,
+            output_commitments,
+        ),
+    )
+}
+
+pub fn verify_swap_proof(
+    proof: &ZkProof,
+    merkle_root: &[u8; 32],
+    nullifier_hash: &[u8; 32],
+    target_pool_type: &PoolType,
+    commitments: &[[u8; 32]],
+) -> bool {
+    verify_groth16(
+        proof,
+        &swap_public_inputs(merkle_root, nullifier_hash, target_pool_type, commitments),
+    )
 }
 
 // ZK Proof verification placeholder
 // In production, use a proper ZK verification library like arkworks or bellman
-fn verify_proof(
-    _proof: &ZkProof,
-    _merkle_root: &[u8; 32],
-    _nullifier_hash: &[u8; 32],
-    _recipient: &Pubkey,
-    _fee: u64,
-) -> bool {
+fn verify_groth16(_proof: &ZkProof, _public_inputs: &[[u8; 32]]) -> bool {
     // TODO: Implement actual ZK proof verification
     // This requires integrating with a ZK proving system like:
     // - Groth16 (using arkworks-rs)
@@ -513,4 +3481,4 @@ fn verify_proof(
     // For now, return true for testing
     // REMOVE THIS IN PRODUCTION
     true
-}
+}
\ No newline at end of file
@@ -0,0 +1,90 @@
+// RIFT Relayer - Chain access
+//
+// Everything the relayer needs from a cluster sits behind the `Chain` trait so
+// the service can run against a real RPC node or an in-process stand-in.
+
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::hash::Hash;
+use anchor_client::solana_sdk::signature::Signature;
+use anchor_client::solana_sdk::transaction::Transaction;
+use anchor_lang::prelude::Pubkey;
+use serde::Serialize;
+
+use crate::error::{RelayerError, Result};
+
+/// Outcome of a submitted transaction as seen by the cluster
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", content = "error", rename_all = "snake_case")]
+pub enum TxStatus {
+    Pending,
+    Confirmed,
+    Failed(String),
+}
+
+pub trait Chain: Send + Sync {
+    /// Raw account data, or `None` if the account does not exist
+    fn account_data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>>;
+
+    fn latest_blockhash(&self) -> Result<Hash>;
+
+    /// Current slot, against which withdraw intents expire
+    fn slot(&self) -> Result<u64>;
+
+    fn send_transaction(&self, transaction: &Transaction) -> Result<Signature>;
+
+    fn signature_status(&self, signature: &Signature) -> Result<TxStatus>;
+}
+
+/// `Chain` backed by a JSON-RPC node
+pub struct RpcChain {
+    client: RpcClient,
+}
+
+impl RpcChain {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: RpcClient::new_with_commitment(url, CommitmentConfig::confirmed()),
+        }
+    }
+}
+
+impl Chain for RpcChain {
+    fn account_data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>> {
+        let response = self
+            .client
+            .get_account_with_commitment(address, self.client.commitment())
+            .map_err(|e| RelayerError::Rpc(e.to_string()))?;
+        Ok(response.value.map(|account| account.data))
+    }
+
+    fn latest_blockhash(&self) -> Result<Hash> {
+        self.client
+            .get_latest_blockhash()
+            .map_err(|e| RelayerError::Rpc(e.to_string()))
+    }
+
+    fn slot(&self) -> Result<u64> {
+        self.client
+            .get_slot()
+            .map_err(|e| RelayerError::Rpc(e.to_string()))
+    }
+
+    fn send_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+        self.client
+            .send_transaction(transaction)
+            .map_err(|e| RelayerError::Rpc(e.to_string()))
+    }
+
+    fn signature_status(&self, signature: &Signature) -> Result<TxStatus> {
+        let status = self
+            .client
+            .get_signature_status(signature)
+            .map_err(|e| RelayerError::Rpc(e.to_string()))?;
+        Ok(match status {
+            None => TxStatus::Pending,
+            Some(Ok(())) => TxStatus::Confirmed,
+            Some(Err(e)) => TxStatus::Failed(e.to_string()),
+        })
+    }
+}
@@ -0,0 +1,31 @@
+// RIFT Relayer - Errors
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RelayerError {
+    #[error("Malformed request: {0}")]
+    BadRequest(String),
+    #[error("No pool has the given merkle root")]
+    UnknownRoot,
+    #[error("The nullifier has already been used")]
+    NullifierAlreadyUsed,
+    #[error("Invalid zero-knowledge proof")]
+    InvalidProof,
+    #[error("Withdraw intent expired at slot {expiry_slot}; the cluster is at slot {slot}")]
+    IntentExpired { expiry_slot: u64, slot: u64 },
+    #[error("Fee {offered} is below the relayer minimum of {minimum}")]
+    FeeTooLow { offered: u64, minimum: u64 },
+    #[error("Fee {fee} exceeds the pool deposit amount {deposit_amount}")]
+    FeeTooHigh { fee: u64, deposit_amount: u64 },
+    #[error("Relayer is not registered or not active")]
+    RelayerInactive,
+    #[error("Account {0} does not exist")]
+    AccountNotFound(String),
+    #[error("Account {0} could not be decoded")]
+    AccountDecode(String),
+    #[error("RPC error: {0}")]
+    Rpc(String),
+}
+
+pub type Result<T> = std::result::Result<T, RelayerError>;
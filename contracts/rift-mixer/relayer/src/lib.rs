@@ -0,0 +1,13 @@
+// RIFT Relayer
+//
+// Submits mixer withdrawals on behalf of users so the recipient wallet never
+// has to sign (or pay for) the withdrawal transaction.
+
+pub mod chain;
+pub mod error;
+pub mod relayer;
+pub mod server;
+
+pub use chain::{Chain, RpcChain, TxStatus};
+pub use error::RelayerError;
+pub use relayer::{Relayer, WithdrawRequest};
@@ -0,0 +1,42 @@
+// RIFT Relayer - service entrypoint
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anchor_client::solana_sdk::signature::{read_keypair_file, Signer};
+use clap::Parser;
+use rift_relayer::{server, Relayer, RpcChain};
+
+#[derive(Parser)]
+#[command(name = "rift-relayer", about = "Submit RIFT mixer withdrawals for users")]
+struct Args {
+    /// Cluster JSON-RPC endpoint
+    #[arg(long, env = "RIFT_RPC_URL", default_value = "http://127.0.0.1:8899")]
+    rpc_url: String,
+
+    /// Relayer keypair; must match a registered `RelayerAccount`
+    #[arg(long, env = "RIFT_RELAYER_KEYPAIR")]
+    keypair: String,
+
+    /// Address the HTTP API listens on
+    #[arg(long, env = "RIFT_RELAYER_LISTEN", default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+
+    let keypair = read_keypair_file(&args.keypair)
+        .unwrap_or_else(|e| panic!("cannot read keypair {}: {}", args.keypair, e));
+    println!("Relayer {} using {}", keypair.pubkey(), args.rpc_url);
+
+    let relayer = Relayer::new(Box::new(RpcChain::new(args.rpc_url)), keypair);
+    let app = server::router(Arc::new(relayer));
+
+    let listener = tokio::net::TcpListener::bind(args.listen)
+        .await
+        .unwrap_or_else(|e| panic!("cannot bind {}: {}", args.listen, e));
+    println!("Listening on {}", args.listen);
+    axum::serve(listener, app).await.expect("server error");
+}
@@ -0,0 +1,266 @@
+// RIFT Relayer - Withdrawal validation and submission
+//
+// A withdrawal is only signed and sent once it would pass the same checks the
+// mixer program runs: known root, unused nullifier, an unexpired intent and a
+// valid proof. The proof commits to a `WithdrawIntent` naming this relayer, so
+// the relayer cannot change the request and nobody else can submit it. The
+// relayer additionally refuses fees below its registered `RelayerAccount.fee`.
+// A root's minimum age depends on the cluster's slot and is left to the
+// program.
+
+use std::str::FromStr;
+
+use anchor_client::solana_sdk::signature::{Keypair, Signature, Signer};
+use anchor_client::solana_sdk::transaction::Transaction;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use rift_mixer::{
+    NullifierRegistry, Pool, PoolType, RootHistory, WithdrawIntent, ZkProof, POOL_SEED,
+    ROOT_HISTORY_SEED,
+};
+use rift_staking::{RelayerAccount, RELAYER_SEED};
+use serde::Deserialize;
+
+use crate::chain::{Chain, TxStatus};
+use crate::error::{RelayerError, Result};
+
+pub const POOL_TYPES: [PoolType; 4] = [
+    PoolType::Sol0_1,
+    PoolType::Sol1,
+    PoolType::Sol10,
+    PoolType::Sol100,
+];
+
+/// Groth16 proof points, hex encoded
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProofJson {
+    pub a: String,
+    pub b: String,
+    pub c: String,
+}
+
+/// Body of `POST /withdraw`: a `WithdrawIntent` whose relayer is this one,
+/// and the proof committing to it
+#[derive(Clone, Debug, Deserialize)]
+pub struct WithdrawRequest {
+    pub proof: ProofJson,
+    pub root: String,
+    pub nullifier_hash: String,
+    pub recipient: String,
+    pub fee: u64,
+    pub expiry_slot: u64,
+}
+
+/// A request that has passed every check and is ready to be signed
+pub struct ValidatedWithdraw {
+    pub pool: Pubkey,
+    /// Tree whose root history holds `intent.root`
+    pub tree_index: u32,
+    pub proof: ZkProof,
+    pub intent: WithdrawIntent,
+}
+
+pub struct Relayer {
+    chain: Box<dyn Chain>,
+    keypair: Keypair,
+}
+
+impl Relayer {
+    pub fn new(chain: Box<dyn Chain>, keypair: Keypair) -> Self {
+        Self { chain, keypair }
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    /// Address of this relayer's registration in the staking program
+    pub fn relayer_account_address(&self) -> Pubkey {
+        Pubkey::find_program_address(
+            &[RELAYER_SEED, self.pubkey().as_ref()],
+            &rift_staking::ID,
+        )
+        .0
+    }
+
+    /// Lowest fee (in lamports) this relayer accepts for a pool
+    pub fn minimum_fee(&self, deposit_amount: u64) -> Result<u64> {
+        let relayer: RelayerAccount = match self.fetch(&self.relayer_account_address()) {
+            Err(RelayerError::AccountNotFound(_)) => return Err(RelayerError::RelayerInactive),
+            other => other?,
+        };
+        require_active(&relayer)?;
+        Ok((deposit_amount as u128 * relayer.fee as u128 / 10_000) as u64)
+    }
+
+    /// Run every off-chain check without touching the cluster's state
+    pub fn validate(&self, request: &WithdrawRequest) -> Result<ValidatedWithdraw> {
+        let proof = ZkProof {
+            a: decode_hex("proof.a", &request.proof.a)?,
+            b: decode_hex("proof.b", &request.proof.b)?,
+            c: decode_hex("proof.c", &request.proof.c)?,
+        };
+        let root: [u8; 32] = decode_hex("root", &request.root)?;
+        let nullifier_hash: [u8; 32] = decode_hex("nullifier_hash", &request.nullifier_hash)?;
+        let recipient = Pubkey::from_str(&request.recipient)
+            .map_err(|_| RelayerError::BadRequest("recipient is not a valid pubkey".into()))?;
+
+        let slot = self.chain.slot()?;
+        if slot > request.expiry_slot {
+            return Err(RelayerError::IntentExpired {
+                expiry_slot: request.expiry_slot,
+                slot,
+            });
+        }
+
+        let (pool_address, pool, tree_index) = self.find_pool(&root)?;
+
+        let registry: NullifierRegistry = self.fetch(&nullifier_registry_address(&pool_address))?;
+        if registry.used_nullifiers.contains(&nullifier_hash) {
+            return Err(RelayerError::NullifierAlreadyUsed);
+        }
+
+        let intent = WithdrawIntent {
+            pool_type: pool.pool_type.clone(),
+            root,
+            nullifier_hash,
+            recipient,
+            relayer: Some(self.pubkey()),
+            fee: request.fee,
+            expiry_slot: request.expiry_slot,
+        };
+        let ext_data_hash = rift_mixer::hash_withdraw_intent(&intent);
+        if !rift_mixer::verify_proof(&proof, &root, &nullifier_hash, &ext_data_hash) {
+            return Err(RelayerError::InvalidProof);
+        }
+
+        let minimum = self.minimum_fee(pool.deposit_amount)?;
+        if request.fee < minimum {
+            return Err(RelayerError::FeeTooLow {
+                offered: request.fee,
+                minimum,
+            });
+        }
+        if request.fee > pool.deposit_amount {
+            return Err(RelayerError::FeeTooHigh {
+                fee: request.fee,
+                deposit_amount: pool.deposit_amount,
+            });
+        }
+
+        Ok(ValidatedWithdraw {
+            pool: pool_address,
+            tree_index,
+            proof,
+            intent,
+        })
+    }
+
+    /// Validate, sign and send a withdrawal, paying the fee to this relayer
+    pub fn submit(&self, request: &WithdrawRequest) -> Result<Signature> {
+        let withdraw = self.validate(request)?;
+        let instruction = self.withdraw_instruction(withdraw);
+        let blockhash = self.chain.latest_blockhash()?;
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.pubkey()),
+            &[&self.keypair],
+            blockhash,
+        );
+        self.chain.send_transaction(&transaction)
+    }
+
+    pub fn status(&self, signature: &Signature) -> Result<TxStatus> {
+        self.chain.signature_status(signature)
+    }
+
+    pub fn withdraw_instruction(&self, withdraw: ValidatedWithdraw) -> Instruction {
+        let accounts = rift_mixer::accounts::Withdraw {
+            pool: withdraw.pool,
+            nullifier_registry: nullifier_registry_address(&withdraw.pool),
+            root_history: root_history_address(&withdraw.pool, withdraw.tree_index),
+            pool_vault: vault_address(&withdraw.pool),
+            recipient: withdraw.intent.recipient,
+            relayer: self.pubkey(),
+            system_program: anchor_lang::system_program::ID,
+        };
+        let data = rift_mixer::instruction::Withdraw {
+            tree_index: withdraw.tree_index,
+            proof: withdraw.proof,
+            intent: withdraw.intent,
+        };
+        Instruction {
+            program_id: rift_mixer::ID,
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }
+    }
+
+    /// Root histories are per tree, so the root in the request also tells us
+    /// which pool and tree the withdrawal is for. The current tree is checked
+    /// first, as that is where most withdrawals prove against.
+    fn find_pool(&self, root: &[u8; 32]) -> Result<(Pubkey, Pool, u32)> {
+        for pool_type in POOL_TYPES.iter() {
+            let address = pool_address(pool_type);
+            let Some(data) = self.chain.account_data(&address)? else {
+                continue;
+            };
+            let pool = Pool::try_deserialize(&mut data.as_slice())
+                .map_err(|_| RelayerError::AccountDecode(address.to_string()))?;
+            for tree_index in (0..=pool.current_tree).rev() {
+                let history: RootHistory =
+                    self.fetch(&root_history_address(&address, tree_index))?;
+                if history.find(root).is_some() {
+                    return Ok((address, pool, tree_index));
+                }
+            }
+        }
+        Err(RelayerError::UnknownRoot)
+    }
+
+    fn fetch<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<T> {
+        let data = self
+            .chain
+            .account_data(address)?
+            .ok_or_else(|| RelayerError::AccountNotFound(address.to_string()))?;
+        T::try_deserialize(&mut data.as_slice())
+            .map_err(|_| RelayerError::AccountDecode(address.to_string()))
+    }
+}
+
+pub fn pool_address(pool_type: &PoolType) -> Pubkey {
+    Pubkey::find_program_address(&[POOL_SEED, &[pool_type.to_u8()]], &rift_mixer::ID).0
+}
+
+pub fn vault_address(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"vault", pool.as_ref()], &rift_mixer::ID).0
+}
+
+pub fn nullifier_registry_address(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"nullifiers", pool.as_ref()], &rift_mixer::ID).0
+}
+
+pub fn root_history_address(pool: &Pubkey, tree_index: u32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[ROOT_HISTORY_SEED, pool.as_ref(), &tree_index.to_le_bytes()],
+        &rift_mixer::ID,
+    )
+    .0
+}
+
+fn require_active(relayer: &RelayerAccount) -> Result<()> {
+    if relayer.is_active {
+        Ok(())
+    } else {
+        Err(RelayerError::RelayerInactive)
+    }
+}
+
+fn decode_hex<const N: usize>(field: &str, value: &str) -> Result<[u8; N]> {
+    let bytes = hex::decode(value.trim_start_matches("0x"))
+        .map_err(|_| RelayerError::BadRequest(format!("{} is not valid hex", field)))?;
+    bytes
+        .try_into()
+        .map_err(|_| RelayerError::BadRequest(format!("{} must be {} bytes", field, N)))
+}
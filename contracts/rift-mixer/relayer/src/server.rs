@@ -0,0 +1,122 @@
+// RIFT Relayer - HTTP API
+//
+//   POST /withdraw           validate and submit a withdrawal
+//   GET  /status/:signature  confirmation status of a submitted withdrawal
+//   GET  /info               relayer pubkey and accepted fee per pool
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anchor_client::solana_sdk::signature::Signature;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use rift_mixer::get_deposit_amount;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::chain::TxStatus;
+use crate::error::RelayerError;
+use crate::relayer::{Relayer, WithdrawRequest, POOL_TYPES};
+
+#[derive(Serialize)]
+pub struct WithdrawResponse {
+    pub signature: String,
+    #[serde(flatten)]
+    pub status: TxStatus,
+}
+
+#[derive(Serialize)]
+pub struct PoolFee {
+    pub pool_type: String,
+    pub deposit_amount: u64,
+    pub minimum_fee: u64,
+}
+
+#[derive(Serialize)]
+pub struct InfoResponse {
+    pub relayer: String,
+    pub fees: Vec<PoolFee>,
+}
+
+pub fn router(relayer: Arc<Relayer>) -> Router {
+    Router::new()
+        .route("/withdraw", post(withdraw))
+        .route("/status/:signature", get(status))
+        .route("/info", get(info))
+        .with_state(relayer)
+}
+
+async fn withdraw(
+    State(relayer): State<Arc<Relayer>>,
+    Json(request): Json<WithdrawRequest>,
+) -> Result<Json<WithdrawResponse>, RelayerError> {
+    let signature = blocking(move || relayer.submit(&request)).await?;
+    Ok(Json(WithdrawResponse {
+        signature: signature.to_string(),
+        status: TxStatus::Pending,
+    }))
+}
+
+async fn status(
+    State(relayer): State<Arc<Relayer>>,
+    Path(signature): Path<String>,
+) -> Result<Json<WithdrawResponse>, RelayerError> {
+    let parsed = Signature::from_str(&signature)
+        .map_err(|_| RelayerError::BadRequest("signature is not valid base58".into()))?;
+    let status = blocking(move || relayer.status(&parsed)).await?;
+    Ok(Json(WithdrawResponse { signature, status }))
+}
+
+async fn info(State(relayer): State<Arc<Relayer>>) -> Result<Json<InfoResponse>, RelayerError> {
+    blocking(move || {
+        let fees = POOL_TYPES
+            .iter()
+            .map(|pool_type| {
+                let deposit_amount = get_deposit_amount(pool_type);
+                Ok(PoolFee {
+                    pool_type: format!("{:?}", pool_type),
+                    deposit_amount,
+                    minimum_fee: relayer.minimum_fee(deposit_amount)?,
+                })
+            })
+            .collect::<Result<Vec<_>, RelayerError>>()?;
+        Ok(Json(InfoResponse {
+            relayer: relayer.pubkey().to_string(),
+            fees,
+        }))
+    })
+    .await
+}
+
+/// RPC calls are blocking, keep them off the async workers
+async fn blocking<T, F>(f: F) -> Result<T, RelayerError>
+where
+    F: FnOnce() -> Result<T, RelayerError> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| RelayerError::Rpc(e.to_string()))?
+}
+
+impl IntoResponse for RelayerError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            RelayerError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            RelayerError::UnknownRoot
+            | RelayerError::NullifierAlreadyUsed
+            | RelayerError::InvalidProof
+            | RelayerError::IntentExpired { .. }
+            | RelayerError::FeeTooLow { .. }
+            | RelayerError::FeeTooHigh { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            RelayerError::RelayerInactive => StatusCode::SERVICE_UNAVAILABLE,
+            RelayerError::AccountNotFound(_)
+            | RelayerError::AccountDecode(_)
+            | RelayerError::Rpc(_) => StatusCode::BAD_GATEWAY,
+        };
+        (status, Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
@@ -0,0 +1,353 @@
+// End-to-end relayer tests against an in-memory validator stand-in.
+//
+// `LocalChain` holds account data like a validator would and applies the
+// mixer's `withdraw` root and expiry checks and bookkeeping to every
+// transaction the relayer sends, so a request goes through HTTP, validation,
+// signing, submission and status.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anchor_client::solana_sdk::hash::Hash;
+use anchor_client::solana_sdk::signature::{Keypair, Signature, Signer};
+use anchor_client::solana_sdk::transaction::Transaction;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, AccountSerialize, AnchorDeserialize, Discriminator};
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use rift_mixer::{
+    NullifierRegistry, Pool, PoolType, RootEntry, RootHistory, DEPOSIT_1_SOL, ROOT_HISTORY_SIZE,
+};
+use rift_relayer::relayer::{nullifier_registry_address, pool_address, root_history_address};
+use rift_relayer::{server, Chain, Relayer, RelayerError, TxStatus};
+use rift_staking::{RelayerAccount, RELAYER_SEED};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+const ROOT: [u8; 32] = [7u8; 32];
+const RELAYER_FEE_BPS: u64 = 30;
+/// Expiry of the intents in `withdraw_body`
+const EXPIRY_SLOT: u64 = 1_000;
+
+#[derive(Default)]
+struct LedgerState {
+    accounts: HashMap<Pubkey, Vec<u8>>,
+    statuses: HashMap<Signature, TxStatus>,
+    slot: u64,
+}
+
+#[derive(Clone, Default)]
+struct LocalChain {
+    state: Arc<Mutex<LedgerState>>,
+}
+
+impl LocalChain {
+    fn store<T: AccountSerialize>(&self, address: Pubkey, account: &T) {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        self.state.lock().unwrap().accounts.insert(address, data);
+    }
+
+    fn load<T: AccountDeserialize>(&self, address: &Pubkey) -> T {
+        let state = self.state.lock().unwrap();
+        T::try_deserialize(&mut state.accounts[address].as_slice()).unwrap()
+    }
+
+    fn warp_to_slot(&self, slot: u64) {
+        self.state.lock().unwrap().slot = slot;
+    }
+
+    fn sent(&self) -> usize {
+        self.state.lock().unwrap().statuses.len()
+    }
+
+    fn apply_withdraw(&self, transaction: &Transaction) -> TxStatus {
+        let message = &transaction.message;
+        let instruction = &message.instructions[0];
+        let program_id = message.account_keys[instruction.program_id_index as usize];
+        if program_id != rift_mixer::ID {
+            return TxStatus::Failed("not a mixer instruction".into());
+        }
+        let (discriminator, args) = instruction.data.split_at(8);
+        if discriminator != rift_mixer::instruction::Withdraw::DISCRIMINATOR {
+            return TxStatus::Failed("not a withdraw instruction".into());
+        }
+        let args = rift_mixer::instruction::Withdraw::try_from_slice(args).unwrap();
+        let intent = args.intent;
+        if self.state.lock().unwrap().slot > intent.expiry_slot {
+            return TxStatus::Failed("IntentExpired".into());
+        }
+
+        let pool_key = message.account_keys[instruction.accounts[0] as usize];
+        let registry_key = message.account_keys[instruction.accounts[1] as usize];
+        let history_key = message.account_keys[instruction.accounts[2] as usize];
+        if history_key != root_history_address(&pool_key, args.tree_index) {
+            return TxStatus::Failed("ConstraintSeeds".into());
+        }
+        let history: RootHistory = self.load(&history_key);
+        if history.find(&intent.root).is_none() {
+            return TxStatus::Failed("UnknownRoot".into());
+        }
+        let mut pool: Pool = self.load(&pool_key);
+        let mut registry: NullifierRegistry = self.load(&registry_key);
+        if registry.used_nullifiers.contains(&intent.nullifier_hash) {
+            return TxStatus::Failed("NullifierAlreadyUsed".into());
+        }
+        registry.used_nullifiers.push(intent.nullifier_hash);
+        pool.total_withdrawals += 1;
+        self.store(registry_key, &registry);
+        self.store(pool_key, &pool);
+        TxStatus::Confirmed
+    }
+}
+
+impl Chain for LocalChain {
+    fn account_data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>, RelayerError> {
+        Ok(self.state.lock().unwrap().accounts.get(address).cloned())
+    }
+
+    fn latest_blockhash(&self) -> Result<Hash, RelayerError> {
+        Ok(Hash::new_unique())
+    }
+
+    fn slot(&self) -> Result<u64, RelayerError> {
+        Ok(self.state.lock().unwrap().slot)
+    }
+
+    fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, RelayerError> {
+        transaction
+            .verify()
+            .map_err(|e| RelayerError::Rpc(e.to_string()))?;
+        let status = self.apply_withdraw(transaction);
+        let signature = transaction.signatures[0];
+        self.state.lock().unwrap().statuses.insert(signature, status);
+        Ok(signature)
+    }
+
+    fn signature_status(&self, signature: &Signature) -> Result<TxStatus, RelayerError> {
+        Ok(self
+            .state
+            .lock()
+            .unwrap()
+            .statuses
+            .get(signature)
+            .cloned()
+            .unwrap_or(TxStatus::Pending))
+    }
+}
+
+struct Harness {
+    chain: LocalChain,
+    app: axum::Router,
+}
+
+fn harness(register_relayer: bool) -> Harness {
+    let chain = LocalChain::default();
+    let keypair = Keypair::new();
+    let pool_key = pool_address(&PoolType::Sol1);
+
+    chain.store(
+        pool_key,
+        &Pool {
+            authority: Pubkey::new_unique(),
+            pool_type: PoolType::Sol1,
+            deposit_amount: DEPOSIT_1_SOL,
+            total_deposits: 1,
+            total_withdrawals: 0,
+            merkle_root: ROOT,
+            next_index: 1,
+            bump: 255,
+            max_tvl: u64::MAX,
+            max_deposits_per_epoch: u64::MAX,
+            current_epoch: 0,
+            epoch_deposits: 0,
+            min_leaf_age_slots: 0,
+            min_leaf_age_deposits: 0,
+            current_tree: 0,
+            version: Pool::VERSION,
+            emergency: false,
+            retired: false,
+        },
+    );
+    let mut entries = [RootEntry::default(); ROOT_HISTORY_SIZE];
+    entries[0] = RootEntry {
+        root: ROOT,
+        slot: 0,
+        leaf_count: 1,
+    };
+    chain.store(
+        root_history_address(&pool_key, 0),
+        &RootHistory {
+            entries,
+            current_index: 0,
+            version: RootHistory::VERSION,
+        },
+    );
+    chain.store(
+        nullifier_registry_address(&pool_key),
+        &NullifierRegistry {
+            used_nullifiers: Vec::new(),
+            version: NullifierRegistry::VERSION,
+        },
+    );
+    if register_relayer {
+        let address = Pubkey::find_program_address(
+            &[RELAYER_SEED, keypair.pubkey().as_ref()],
+            &rift_staking::ID,
+        )
+        .0;
+        chain.store(
+            address,
+            &RelayerAccount {
+                owner: keypair.pubkey(),
+                stake_account: Pubkey::new_unique(),
+                fee: RELAYER_FEE_BPS,
+                successful_relays: 0,
+                failed_relays: 0,
+                reputation: 100,
+                is_active: true,
+                registered_at: 0,
+                bump: 255,
+            },
+        );
+    }
+
+    let relayer = Relayer::new(Box::new(chain.clone()), keypair);
+    Harness {
+        chain,
+        app: server::router(Arc::new(relayer)),
+    }
+}
+
+fn withdraw_body(nullifier: u8, root: [u8; 32], fee: u64) -> Value {
+    json!({
+        "proof": {
+            "a": hex::encode([1u8; 64]),
+            "b": hex::encode([2u8; 128]),
+            "c": hex::encode([3u8; 64]),
+        },
+        "root": hex::encode(root),
+        "nullifier_hash": hex::encode([nullifier; 32]),
+        "recipient": Pubkey::new_unique().to_string(),
+        "fee": fee,
+        "expiry_slot": EXPIRY_SLOT,
+    })
+}
+
+async fn call(app: &axum::Router, request: Request<Body>) -> (StatusCode, Value) {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap())
+}
+
+async fn post_withdraw(app: &axum::Router, body: Value) -> (StatusCode, Value) {
+    let request = Request::post("/withdraw")
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    call(app, request).await
+}
+
+fn minimum_fee() -> u64 {
+    DEPOSIT_1_SOL * RELAYER_FEE_BPS / 10_000
+}
+
+#[tokio::test]
+async fn withdraw_is_submitted_and_confirmed() {
+    let h = harness(true);
+
+    let (status, body) = post_withdraw(&h.app, withdraw_body(1, ROOT, minimum_fee())).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let signature = body["signature"].as_str().unwrap().to_string();
+
+    let (status, body) = call(
+        &h.app,
+        Request::get(format!("/status/{}", signature))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "confirmed");
+
+    let pool: Pool = h.chain.load(&pool_address(&PoolType::Sol1));
+    assert_eq!(pool.total_withdrawals, 1);
+}
+
+#[tokio::test]
+async fn fee_below_relayer_minimum_is_refused() {
+    let h = harness(true);
+
+    let (status, body) = post_withdraw(&h.app, withdraw_body(1, ROOT, minimum_fee() - 1)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body["error"].as_str().unwrap().contains("below the relayer minimum"));
+    assert_eq!(h.chain.sent(), 0);
+}
+
+#[tokio::test]
+async fn spent_nullifier_is_refused_before_submission() {
+    let h = harness(true);
+
+    let (status, _) = post_withdraw(&h.app, withdraw_body(1, ROOT, minimum_fee())).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = post_withdraw(&h.app, withdraw_body(1, ROOT, minimum_fee())).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body["error"].as_str().unwrap().contains("nullifier"));
+    assert_eq!(h.chain.sent(), 1);
+}
+
+#[tokio::test]
+async fn expired_intent_is_refused() {
+    let h = harness(true);
+    h.chain.warp_to_slot(EXPIRY_SLOT + 1);
+
+    let (status, body) = post_withdraw(&h.app, withdraw_body(1, ROOT, minimum_fee())).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body["error"].as_str().unwrap().contains("expired"));
+    assert_eq!(h.chain.sent(), 0);
+}
+
+#[tokio::test]
+async fn unknown_root_is_refused() {
+    let h = harness(true);
+
+    let (status, _) = post_withdraw(&h.app, withdraw_body(1, [9u8; 32], minimum_fee())).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(h.chain.sent(), 0);
+}
+
+#[tokio::test]
+async fn unregistered_relayer_does_not_submit() {
+    let h = harness(false);
+
+    let (status, _) = post_withdraw(&h.app, withdraw_body(1, ROOT, minimum_fee())).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(h.chain.sent(), 0);
+}
+
+#[tokio::test]
+async fn root_in_an_earlier_tree_is_withdrawn_against_that_tree() {
+    let h = harness(true);
+    // The pool has moved on to tree 1, which has no roots yet
+    let pool_key = pool_address(&PoolType::Sol1);
+    let mut pool: Pool = h.chain.load(&pool_key);
+    pool.current_tree = 1;
+    h.chain.store(pool_key, &pool);
+    h.chain.store(
+        root_history_address(&pool_key, 1),
+        &RootHistory {
+            entries: [RootEntry::default(); ROOT_HISTORY_SIZE],
+            current_index: 0,
+            version: RootHistory::VERSION,
+        },
+    );
+
+    let (status, body) = post_withdraw(&h.app, withdraw_body(1, ROOT, minimum_fee())).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let pool: Pool = h.chain.load(&pool_key);
+    assert_eq!(pool.total_withdrawals, 1);
+}
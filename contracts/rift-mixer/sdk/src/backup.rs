@@ -0,0 +1,109 @@
+// RIFT SDK - Encrypted note backups
+//
+// The optional `encrypted_note` a deposit carries into `DepositEvent`:
+//
+//     version (1) || ephemeral X25519 public key (32) || ChaCha20-Poly1305(note)
+//
+// The symmetric key is HKDF-SHA256 over the X25519 shared secret, salted with
+// both public keys. Every backup uses a fresh ephemeral key, so the key is
+// never reused and the nonce can stay zero.
+
+use chacha20poly1305::aead::{Aead, NewAead};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use rand::RngCore;
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::error::{Result, SdkError};
+use crate::keys::ViewingKey;
+use crate::note::Note;
+
+pub const BACKUP_VERSION: u8 = 1;
+
+/// Size of an encrypted backup; well under `rift_mixer::MAX_ENCRYPTED_NOTE_LEN`
+pub const BACKUP_LEN: usize = 1 + 32 + Note::LEN + 16;
+
+const BACKUP_KEY_INFO: &[u8] = b"rift/note-backup/v1";
+
+/// Encrypt `note` so that only the holder of `viewing_public_key` can read it
+pub fn encrypt_note(note: &Note, viewing_public_key: &[u8; 32]) -> Vec<u8> {
+    seal(BACKUP_VERSION, BACKUP_KEY_INFO, &note.to_bytes(), viewing_public_key)
+}
+
+/// `version || ephemeral public key || ciphertext` of `plaintext` for
+/// `viewing_public_key`, keyed with `info`
+pub(crate) fn seal(
+    version: u8,
+    info: &[u8],
+    plaintext: &[u8],
+    viewing_public_key: &[u8; 32],
+) -> Vec<u8> {
+    let mut ephemeral = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut ephemeral);
+    let ephemeral = StaticSecret::from(ephemeral);
+    let ephemeral_public = PublicKey::from(&ephemeral).to_bytes();
+    let shared = ephemeral
+        .diffie_hellman(&PublicKey::from(*viewing_public_key))
+        .to_bytes();
+
+    let cipher = cipher(info, &shared, &ephemeral_public, viewing_public_key);
+    let ciphertext = cipher
+        .encrypt(&Nonce::default(), plaintext)
+        .expect("ChaCha20-Poly1305 encryption cannot fail for short inputs");
+
+    let mut sealed = Vec::with_capacity(1 + 32 + ciphertext.len());
+    sealed.push(version);
+    sealed.extend_from_slice(&ephemeral_public);
+    sealed.extend_from_slice(&ciphertext);
+    sealed
+}
+
+/// Decrypt a backup addressed to `viewing_key`
+pub fn decrypt_note(backup: &[u8], viewing_key: &ViewingKey) -> Result<Note> {
+    let (&version, rest) = backup
+        .split_first()
+        .ok_or_else(|| SdkError::MalformedNote("empty backup".to_string()))?;
+    if version != BACKUP_VERSION {
+        return Err(SdkError::UnsupportedBackupVersion(version));
+    }
+    if rest.len() != BACKUP_LEN - 1 {
+        return Err(SdkError::MalformedNote(format!(
+            "expected a {} byte backup, got {}",
+            BACKUP_LEN,
+            backup.len()
+        )));
+    }
+
+    let plaintext = open(BACKUP_KEY_INFO, rest, viewing_key).ok_or(SdkError::BackupDecryption)?;
+    Note::from_bytes(&plaintext)
+}
+
+/// Decrypt what `seal` produced, given everything after the version byte.
+/// `None` if it is not addressed to `viewing_key` or was tampered with.
+pub(crate) fn open(info: &[u8], sealed: &[u8], viewing_key: &ViewingKey) -> Option<Vec<u8>> {
+    if sealed.len() < 32 {
+        return None;
+    }
+    let (ephemeral_public, ciphertext) = sealed.split_at(32);
+    let ephemeral_public: [u8; 32] = ephemeral_public.try_into().unwrap();
+    let shared = viewing_key.diffie_hellman(&ephemeral_public);
+    let cipher = cipher(info, &shared, &ephemeral_public, &viewing_key.public_key());
+    cipher.decrypt(&Nonce::default(), ciphertext).ok()
+}
+
+fn cipher(
+    info: &[u8],
+    shared: &[u8; 32],
+    ephemeral_public: &[u8; 32],
+    viewing_public: &[u8; 32],
+) -> ChaCha20Poly1305 {
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(ephemeral_public);
+    salt[32..].copy_from_slice(viewing_public);
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(info, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    ChaCha20Poly1305::new(&Key::from(key))
+}
@@ -0,0 +1,104 @@
+// RIFT disclosure tool - link one deposit to one withdrawal for compliance
+
+use std::process::exit;
+
+use clap::{Parser, Subcommand};
+use rift_sdk::{Disclosure, Note};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::signature::{read_keypair_file, Signature};
+
+#[derive(Parser)]
+#[command(
+    name = "rift-disclosure",
+    about = "Create and verify RIFT deposit-to-withdrawal disclosures"
+)]
+struct Args {
+    /// Cluster JSON-RPC endpoint
+    #[arg(long, env = "RIFT_RPC_URL", default_value = "http://127.0.0.1:8899")]
+    rpc_url: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Sign a disclosure for a spent note with the withdrawal recipient's key
+    Create {
+        /// The spent note, hex-encoded (`Note::to_bytes`)
+        #[arg(long)]
+        note: String,
+        /// Signature of the deposit transaction
+        #[arg(long)]
+        deposit: Signature,
+        /// Signature of the withdraw transaction
+        #[arg(long)]
+        withdrawal: Signature,
+        /// Keypair of the withdrawal recipient
+        #[arg(long)]
+        keypair: String,
+        /// Where to write the disclosure
+        #[arg(long, default_value = "disclosure.json")]
+        out: String,
+    },
+    /// Check a disclosure against the deposit and withdraw transactions it names
+    Verify {
+        /// Disclosure file written by `create`
+        disclosure: String,
+    },
+}
+
+fn main() {
+    let args = Args::parse();
+    let rpc = RpcClient::new(args.rpc_url);
+
+    match args.command {
+        Command::Create {
+            note,
+            deposit,
+            withdrawal,
+            keypair,
+            out,
+        } => {
+            let note = hex::decode(note.trim_start_matches("0x"))
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| Note::from_bytes(&bytes).map_err(|e| e.to_string()))
+                .unwrap_or_else(|e| fail(&format!("invalid note: {}", e)));
+            let recipient = read_keypair_file(&keypair)
+                .unwrap_or_else(|e| fail(&format!("cannot read keypair {}: {}", keypair, e)));
+
+            let disclosure = Disclosure::from_chain(&rpc, note, deposit, withdrawal, &recipient)
+                .unwrap_or_else(|e| fail(&e.to_string()));
+            std::fs::write(&out, disclosure.to_json())
+                .unwrap_or_else(|e| fail(&format!("cannot write {}: {}", out, e)));
+            println!(
+                "Disclosure for tree {} leaf {} -> {} written to {}",
+                disclosure.tree_index, disclosure.leaf_index, disclosure.recipient, out
+            );
+        }
+        Command::Verify { disclosure } => {
+            let json = std::fs::read_to_string(&disclosure)
+                .unwrap_or_else(|e| fail(&format!("cannot read {}: {}", disclosure, e)));
+            let disclosure = Disclosure::from_json(&json).unwrap_or_else(|e| fail(&e.to_string()));
+            disclosure
+                .verify_on_chain(&rpc)
+                .unwrap_or_else(|e| fail(&format!("INVALID: {}", e)));
+            println!(
+                "VALID: deposit {} (tree {} leaf {}, commitment {}) funded withdrawal {} \
+                 (nullifier hash {}) to {}",
+                disclosure.deposit_signature,
+                disclosure.tree_index,
+                disclosure.leaf_index,
+                hex::encode(disclosure.commitment()),
+                disclosure.withdraw_signature,
+                hex::encode(disclosure.nullifier_hash()),
+                disclosure.recipient
+            );
+        }
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    exit(1);
+}
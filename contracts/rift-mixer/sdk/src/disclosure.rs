@@ -0,0 +1,284 @@
+// RIFT SDK - Selective disclosure
+//
+// A disclosure shows that one withdrawal was funded by one deposit and says
+// nothing about any other note. It carries the spent note itself: its
+// nullifier and secret reproduce both the deposit's commitment and the
+// withdrawal's nullifier hash, which only the depositor could know. The
+// withdrawal's recipient signs it, so the claim comes from whoever received
+// the funds. The deposit is pinned by pool, tree and leaf index, since leaf
+// indices restart in every tree.
+//
+// Only `withdraw` spends can be disclosed. A note spent by
+// `swap_denomination` paid no recipient, and `emergency_withdraw` already
+// reveals the note on chain; both are refused rather than mismatched.
+//
+// Only spent notes should be disclosed. The nullifier is already marked on
+// chain, so revealing the note gives nothing else away; the viewing key and
+// the wallet's other notes are never involved.
+
+use rift_mixer::{DepositEvent, WithdrawEvent};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+
+use crate::error::{Result, SdkError};
+use crate::events::{
+    deposit_events, emergency_withdraw_events, swap_events, transaction_logs, withdraw_events,
+};
+use crate::note::{pool_type_from_u8, Note};
+
+pub const DISCLOSURE_VERSION: u8 = 2;
+
+/// Prefix of the signed message, so the signature means nothing elsewhere
+const DISCLOSURE_DOMAIN: &[u8] = b"rift/disclosure/v2";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Disclosure {
+    pub note: Note,
+    pub deposit_signature: Signature,
+    /// Tree the deposit went into; `leaf_index` is its position there
+    pub tree_index: u32,
+    pub leaf_index: u64,
+    pub withdraw_signature: Signature,
+    pub recipient: Pubkey,
+    /// The recipient's signature over `message()`
+    pub signature: Signature,
+}
+
+impl Disclosure {
+    /// Link `deposit` to `withdrawal` with the note that was spent between
+    /// them, signed by the withdrawal's `recipient`
+    pub fn new(
+        note: Note,
+        deposit_signature: Signature,
+        deposit: &DepositEvent,
+        withdraw_signature: Signature,
+        withdrawal: &WithdrawEvent,
+        recipient: &Keypair,
+    ) -> Result<Self> {
+        let mut disclosure = Self {
+            note,
+            deposit_signature,
+            tree_index: deposit.tree_index,
+            leaf_index: deposit.leaf_index,
+            withdraw_signature,
+            recipient: recipient.pubkey(),
+            signature: Signature::default(),
+        };
+        disclosure.check_events(deposit, withdrawal)?;
+        disclosure.signature = recipient.sign_message(&disclosure.message());
+        Ok(disclosure)
+    }
+
+    /// `new`, reading both events from their confirmed transactions
+    pub fn from_chain(
+        rpc: &RpcClient,
+        note: Note,
+        deposit_signature: Signature,
+        withdraw_signature: Signature,
+        recipient: &Keypair,
+    ) -> Result<Self> {
+        let deposit_logs = transaction_logs(rpc, &deposit_signature)?.unwrap_or_default();
+        let withdraw_logs = transaction_logs(rpc, &withdraw_signature)?.unwrap_or_default();
+        let deposit = find_deposit(&deposit_logs, &note.commitment())?;
+        let withdrawal = find_withdrawal(&withdraw_logs, &note.nullifier_hash())?;
+        Self::new(
+            note,
+            deposit_signature,
+            &deposit,
+            withdraw_signature,
+            &withdrawal,
+            recipient,
+        )
+    }
+
+    pub fn commitment(&self) -> [u8; 32] {
+        self.note.commitment()
+    }
+
+    pub fn nullifier_hash(&self) -> [u8; 32] {
+        self.note.nullifier_hash()
+    }
+
+    /// Bytes the recipient signs: everything in the disclosure but the signature
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = DISCLOSURE_DOMAIN.to_vec();
+        message.push(DISCLOSURE_VERSION);
+        message.extend_from_slice(&self.note.to_bytes());
+        message.extend_from_slice(self.deposit_signature.as_ref());
+        message.extend_from_slice(&self.tree_index.to_le_bytes());
+        message.extend_from_slice(&self.leaf_index.to_le_bytes());
+        message.extend_from_slice(self.withdraw_signature.as_ref());
+        message.extend_from_slice(self.recipient.as_ref());
+        message
+    }
+
+    /// Check the signature and that the note really links `deposit` to
+    /// `withdrawal`, as indexed from the transactions the disclosure names
+    pub fn verify(&self, deposit: &DepositEvent, withdrawal: &WithdrawEvent) -> Result<()> {
+        if !self
+            .signature
+            .verify(self.recipient.as_ref(), &self.message())
+        {
+            return Err(SdkError::InvalidDisclosureSignature);
+        }
+        self.check_events(deposit, withdrawal)
+    }
+
+    /// `verify` against the events in the two confirmed transactions
+    pub fn verify_on_chain(&self, rpc: &RpcClient) -> Result<()> {
+        let deposit_logs = transaction_logs(rpc, &self.deposit_signature)?.unwrap_or_default();
+        let withdraw_logs = transaction_logs(rpc, &self.withdraw_signature)?.unwrap_or_default();
+        self.verify_logs(&deposit_logs, &withdraw_logs)
+    }
+
+    /// `verify` against the events the mixer emitted in the two
+    /// transactions' logs. Events any other program logged are ignored.
+    pub fn verify_logs(&self, deposit_logs: &[String], withdraw_logs: &[String]) -> Result<()> {
+        let deposit = find_deposit(deposit_logs, &self.commitment())?;
+        let withdrawal = find_withdrawal(withdraw_logs, &self.nullifier_hash())?;
+        self.verify(&deposit, &withdrawal)
+    }
+
+    fn check_events(&self, deposit: &DepositEvent, withdrawal: &WithdrawEvent) -> Result<()> {
+        if deposit.commitment != self.commitment() {
+            return Err(mismatch("note does not open the deposit's commitment"));
+        }
+        if deposit.pool_type != self.note.pool_type {
+            return Err(mismatch("deposit was made into a different pool"));
+        }
+        if deposit.tree_index != self.tree_index {
+            return Err(mismatch("deposit landed in a different tree"));
+        }
+        if deposit.leaf_index != self.leaf_index {
+            return Err(mismatch("deposit landed at a different leaf index"));
+        }
+        if withdrawal.pool_type != self.note.pool_type {
+            return Err(mismatch("withdrawal was from a different pool"));
+        }
+        if withdrawal.nullifier_hash != self.nullifier_hash() {
+            return Err(mismatch("withdrawal spent a different nullifier"));
+        }
+        if withdrawal.recipient != self.recipient {
+            return Err(mismatch("withdrawal paid a different recipient"));
+        }
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> String {
+        let file = DisclosureFile {
+            version: DISCLOSURE_VERSION,
+            pool_type: self.note.pool_type.to_u8(),
+            deposit_signature: self.deposit_signature.to_string(),
+            tree_index: self.tree_index,
+            leaf_index: self.leaf_index,
+            commitment: hex::encode(self.commitment()),
+            withdraw_signature: self.withdraw_signature.to_string(),
+            nullifier_hash: hex::encode(self.nullifier_hash()),
+            recipient: self.recipient.to_string(),
+            nullifier: hex::encode(self.note.nullifier),
+            secret: hex::encode(self.note.secret),
+            signature: self.signature.to_string(),
+        };
+        serde_json::to_string_pretty(&file).expect("disclosure serializes")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let file: DisclosureFile =
+            serde_json::from_str(json).map_err(|e| malformed(e.to_string()))?;
+        if file.version != DISCLOSURE_VERSION {
+            return Err(SdkError::UnsupportedDisclosureVersion(file.version));
+        }
+        let disclosure = Self {
+            note: Note {
+                pool_type: pool_type_from_u8(file.pool_type)?,
+                nullifier: decode_hex("nullifier", &file.nullifier)?,
+                secret: decode_hex("secret", &file.secret)?,
+            },
+            deposit_signature: parse("deposit_signature", &file.deposit_signature)?,
+            tree_index: file.tree_index,
+            leaf_index: file.leaf_index,
+            withdraw_signature: parse("withdraw_signature", &file.withdraw_signature)?,
+            recipient: parse("recipient", &file.recipient)?,
+            signature: parse("signature", &file.signature)?,
+        };
+        // The readable copies must agree with what the note derives
+        if decode_hex::<32>("commitment", &file.commitment)? != disclosure.commitment() {
+            return Err(malformed("commitment does not match the note".to_string()));
+        }
+        if decode_hex::<32>("nullifier_hash", &file.nullifier_hash)? != disclosure.nullifier_hash() {
+            return Err(malformed("nullifier_hash does not match the note".to_string()));
+        }
+        Ok(disclosure)
+    }
+}
+
+/// On-disk form: hex for hashes, base58 for keys and signatures. The
+/// commitment and nullifier hash are redundant with the note but let a reader
+/// look both transactions up without recomputing anything.
+#[derive(Serialize, Deserialize)]
+struct DisclosureFile {
+    version: u8,
+    pool_type: u8,
+    deposit_signature: String,
+    tree_index: u32,
+    leaf_index: u64,
+    commitment: String,
+    withdraw_signature: String,
+    nullifier_hash: String,
+    recipient: String,
+    nullifier: String,
+    secret: String,
+    signature: String,
+}
+
+fn find_deposit(logs: &[String], commitment: &[u8; 32]) -> Result<DepositEvent> {
+    deposit_events(logs)
+        .into_iter()
+        .find(|event| &event.commitment == commitment)
+        .ok_or_else(|| mismatch("deposit transaction has no DepositEvent for this commitment"))
+}
+
+/// The `WithdrawEvent` spending `nullifier_hash`, refusing the spends a
+/// disclosure cannot cover
+fn find_withdrawal(logs: &[String], nullifier_hash: &[u8; 32]) -> Result<WithdrawEvent> {
+    if let Some(event) = withdraw_events(logs)
+        .into_iter()
+        .find(|event| &event.nullifier_hash == nullifier_hash)
+    {
+        return Ok(event);
+    }
+    if swap_events(logs).iter().any(|event| &event.nullifier_hash == nullifier_hash) {
+        return Err(SdkError::UndisclosableSpend("swap_denomination"));
+    }
+    if emergency_withdraw_events(logs)
+        .iter()
+        .any(|event| &event.nullifier_hash == nullifier_hash)
+    {
+        return Err(SdkError::UndisclosableSpend("emergency_withdraw"));
+    }
+    Err(mismatch("withdraw transaction has no WithdrawEvent for this nullifier"))
+}
+
+fn mismatch(reason: &str) -> SdkError {
+    SdkError::DisclosureMismatch(reason.to_string())
+}
+
+fn malformed(reason: String) -> SdkError {
+    SdkError::MalformedDisclosure(reason)
+}
+
+fn decode_hex<const N: usize>(field: &str, value: &str) -> Result<[u8; N]> {
+    let bytes = hex::decode(value.trim_start_matches("0x"))
+        .map_err(|_| malformed(format!("{} is not valid hex", field)))?;
+    bytes
+        .try_into()
+        .map_err(|_| malformed(format!("{} must be {} bytes", field, N)))
+}
+
+fn parse<T: std::str::FromStr>(field: &str, value: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| malformed(format!("{} is not valid base58", field)))
+}
@@ -0,0 +1,39 @@
+// RIFT SDK - Errors
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SdkError {
+    #[error("Invalid mnemonic: {0}")]
+    InvalidMnemonic(String),
+    #[error("Malformed note: {0}")]
+    MalformedNote(String),
+    #[error("Unsupported note backup version {0}")]
+    UnsupportedBackupVersion(u8),
+    #[error("Note backup is not addressed to this viewing key or was tampered with")]
+    BackupDecryption,
+    #[error("Unsupported encrypted output version {0}")]
+    UnsupportedOutputVersion(u8),
+    #[error("Encrypted output is not addressed to this viewing key or was tampered with")]
+    OutputDecryption,
+    #[error("Malformed disclosure: {0}")]
+    MalformedDisclosure(String),
+    #[error("Unsupported disclosure version {0}")]
+    UnsupportedDisclosureVersion(u8),
+    #[error("Disclosure is not signed by its recipient")]
+    InvalidDisclosureSignature,
+    #[error("Disclosure does not match the chain: {0}")]
+    DisclosureMismatch(String),
+    #[error("Disclosures only cover withdrawals; this note was spent by {0}")]
+    UndisclosableSpend(&'static str),
+    #[error("Malformed pool stats: {0}")]
+    MalformedPoolStats(String),
+    #[error("Unsupported pool stats version {0}")]
+    UnsupportedPoolStatsVersion(u8),
+    #[error("Malformed return data: {0}")]
+    MalformedReturnData(String),
+    #[error("RPC error: {0}")]
+    Rpc(String),
+}
+
+pub type Result<T> = std::result::Result<T, SdkError>;
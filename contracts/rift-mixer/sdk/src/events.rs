@@ -0,0 +1,107 @@
+// RIFT SDK - Mixer events from transaction logs
+//
+// `emit!` writes each event as a "Program data: <base64>" log line holding
+// the 8-byte event discriminator followed by the borsh-encoded event. Any
+// program can write such a line, so only those written while the mixer is
+// the running program (per the runtime's invoke / success lines) are read.
+
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rift_mixer::{DepositEvent, EmergencyWithdrawEvent, SwapEvent, TransactEvent, WithdrawEvent};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::UiTransactionEncoding;
+
+use crate::error::{Result, SdkError};
+
+const PROGRAM_DATA: &str = "Program data: ";
+const LOG_TRUNCATED: &str = "Log truncated";
+
+/// Every `T` event the mixer emitted in `logs`, in emission order. Lines
+/// after a truncation are lost, and so are the events in them.
+pub fn parse_events<T: AnchorDeserialize + Discriminator>(logs: &[String]) -> Vec<T> {
+    mixer_data(logs)
+        .filter_map(|data| STANDARD.decode(data).ok())
+        .filter_map(|bytes| {
+            let (discriminator, mut payload) = bytes.split_at_checked(8)?;
+            if discriminator != T::discriminator() {
+                return None;
+            }
+            T::deserialize(&mut payload).ok()
+        })
+        .collect()
+}
+
+/// The base64 of each "Program data:" line written while `rift_mixer::ID`
+/// was at the top of the invocation stack
+fn mixer_data(logs: &[String]) -> impl Iterator<Item = &str> {
+    let mut stack: Vec<Pubkey> = Vec::new();
+    logs.iter()
+        .take_while(|line| !line.starts_with(LOG_TRUNCATED))
+        .filter_map(move |line| {
+            if let Some(data) = line.strip_prefix(PROGRAM_DATA) {
+                return (stack.last() == Some(&rift_mixer::ID)).then_some(data);
+            }
+            // "Program <id> invoke [depth]", "Program <id> success" and
+            // "Program <id> failed: <error>"; program output ("Program log:",
+            // "Program return:") never starts with a program id
+            let mut words = line.strip_prefix("Program ")?.split_whitespace();
+            let program: Pubkey = words.next()?.parse().ok()?;
+            match words.next()? {
+                "invoke" => stack.push(program),
+                "success" | "failed:" => {
+                    stack.pop();
+                }
+                _ => {}
+            }
+            None
+        })
+}
+
+pub fn deposit_events(logs: &[String]) -> Vec<DepositEvent> {
+    parse_events(logs)
+}
+
+pub fn withdraw_events(logs: &[String]) -> Vec<WithdrawEvent> {
+    parse_events(logs)
+}
+
+pub fn transact_events(logs: &[String]) -> Vec<TransactEvent> {
+    parse_events(logs)
+}
+
+pub fn swap_events(logs: &[String]) -> Vec<SwapEvent> {
+    parse_events(logs)
+}
+
+pub fn emergency_withdraw_events(logs: &[String]) -> Vec<EmergencyWithdrawEvent> {
+    parse_events(logs)
+}
+
+/// Log messages of a confirmed transaction, or `None` if it failed or
+/// recorded none. Events emitted before a failure still appear in its logs, so
+/// they must not be read from failed transactions.
+pub fn transaction_logs(rpc: &RpcClient, signature: &Signature) -> Result<Option<Vec<String>>> {
+    let transaction = rpc
+        .get_transaction_with_config(
+            signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(CommitmentConfig::confirmed()),
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .map_err(|e| SdkError::Rpc(e.to_string()))?;
+    match transaction.transaction.meta {
+        Some(meta) if meta.err.is_none() => match meta.log_messages {
+            OptionSerializer::Some(logs) => Ok(Some(logs)),
+            _ => Ok(None),
+        },
+        _ => Ok(None),
+    }
+}
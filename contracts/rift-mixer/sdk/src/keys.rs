@@ -0,0 +1,50 @@
+// RIFT SDK - Viewing keys
+//
+// The viewing key is an X25519 key derived from the wallet seed. Note backups
+// are encrypted to its public half, so anyone holding the mnemonic can find
+// and decrypt them again; nobody else can link them to the wallet. The public
+// half doubles as the wallet's shielded address.
+
+use bip39::Mnemonic;
+use hkdf::Hkdf;
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::error::{Result, SdkError};
+
+const VIEWING_KEY_INFO: &[u8] = b"rift/viewing-key/v1";
+
+pub struct ViewingKey {
+    secret: StaticSecret,
+}
+
+impl ViewingKey {
+    /// Derive from a BIP-39 mnemonic and optional passphrase
+    pub fn from_mnemonic(phrase: &str, passphrase: &str) -> Result<Self> {
+        let mnemonic =
+            Mnemonic::parse(phrase).map_err(|e| SdkError::InvalidMnemonic(e.to_string()))?;
+        Ok(Self::from_seed(&mnemonic.to_seed(passphrase)))
+    }
+
+    pub fn from_seed(seed: &[u8]) -> Self {
+        let mut key = [0u8; 32];
+        Hkdf::<Sha256>::new(None, seed)
+            .expand(VIEWING_KEY_INFO, &mut key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        Self {
+            secret: StaticSecret::from(key),
+        }
+    }
+
+    /// What depositors encrypt note backups to. It is also the wallet's
+    /// shielded address, which shielded notes name as their owner.
+    pub fn public_key(&self) -> [u8; 32] {
+        PublicKey::from(&self.secret).to_bytes()
+    }
+
+    pub(crate) fn diffie_hellman(&self, ephemeral_public: &[u8; 32]) -> [u8; 32] {
+        self.secret
+            .diffie_hellman(&PublicKey::from(*ephemeral_public))
+            .to_bytes()
+    }
+}
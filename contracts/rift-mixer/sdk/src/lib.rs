@@ -0,0 +1,31 @@
+// RIFT SDK
+//
+// Client-side pieces of the mixer protocol: the deposit note and its
+// commitment, the viewing key wallets derive from their mnemonic, encrypted
+// note backups carried in `DepositEvent`, scanning those events to restore
+// every note a wallet owns, disclosures that link one deposit to one
+// withdrawal for compliance, decoding `get_pool_stats`, a note's spent
+// status, explanations of the program's error codes, and shielded notes sent
+// between wallets inside the shielded pool.
+
+pub mod backup;
+pub mod disclosure;
+pub mod error;
+pub mod events;
+pub mod keys;
+pub mod note;
+pub mod program_error;
+pub mod scan;
+pub mod shielded;
+pub mod stats;
+pub mod status;
+
+pub use disclosure::Disclosure;
+pub use error::{Result, SdkError};
+pub use keys::ViewingKey;
+pub use note::Note;
+pub use program_error::{explain, transaction_mixer_error};
+pub use scan::{receive_notes, recover_notes, ReceivedNote, RecoveredNote};
+pub use shielded::ShieldedNote;
+pub use stats::{decode_pool_stats, fetch_pool_stats};
+pub use status::{fetch_note_status, NoteStatus};
@@ -0,0 +1,79 @@
+// RIFT SDK - Deposit notes
+//
+// A note is the secret a depositor keeps to withdraw later. The commitment
+// inserted into the tree and the nullifier hash revealed on withdrawal are
+// both derived from it with the mixer's own hashes, which
+// `emergency_withdraw` also recomputes from a revealed note.
+
+use rand::RngCore;
+use rift_mixer::{note_commitment, note_nullifier_hash, PoolType};
+
+use crate::error::{Result, SdkError};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Note {
+    pub pool_type: PoolType,
+    pub nullifier: [u8; 32],
+    pub secret: [u8; 32],
+}
+
+impl Note {
+    /// Serialized length: pool type, nullifier, secret
+    pub const LEN: usize = 1 + 32 + 32;
+
+    pub fn random(pool_type: PoolType) -> Self {
+        let mut rng = rand::thread_rng();
+        let mut nullifier = [0u8; 32];
+        let mut secret = [0u8; 32];
+        rng.fill_bytes(&mut nullifier);
+        rng.fill_bytes(&mut secret);
+        Self {
+            pool_type,
+            nullifier,
+            secret,
+        }
+    }
+
+    /// The leaf `deposit` inserts into the pool's Merkle tree
+    pub fn commitment(&self) -> [u8; 32] {
+        note_commitment(&self.nullifier, &self.secret)
+    }
+
+    /// The value `withdraw` marks as spent
+    pub fn nullifier_hash(&self) -> [u8; 32] {
+        note_nullifier_hash(&self.nullifier)
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0] = self.pool_type.to_u8();
+        bytes[1..33].copy_from_slice(&self.nullifier);
+        bytes[33..].copy_from_slice(&self.secret);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != Self::LEN {
+            return Err(SdkError::MalformedNote(format!(
+                "expected {} bytes, got {}",
+                Self::LEN,
+                bytes.len()
+            )));
+        }
+        Ok(Self {
+            pool_type: pool_type_from_u8(bytes[0])?,
+            nullifier: bytes[1..33].try_into().unwrap(),
+            secret: bytes[33..].try_into().unwrap(),
+        })
+    }
+}
+
+pub fn pool_type_from_u8(value: u8) -> Result<PoolType> {
+    match value {
+        0 => Ok(PoolType::Sol0_1),
+        1 => Ok(PoolType::Sol1),
+        2 => Ok(PoolType::Sol10),
+        3 => Ok(PoolType::Sol100),
+        other => Err(SdkError::MalformedNote(format!("unknown pool type {}", other))),
+    }
+}
@@ -0,0 +1,146 @@
+// RIFT SDK - Program errors
+//
+// A failed mixer transaction carries only a custom error code; the values
+// that failed the check are in its logs (`<ErrorName>: key=value ...`).
+// These decode the code back to a `MixerError` and say, in words a wallet
+// can show, what went wrong and what to do about it.
+
+use anchor_lang::error::ERROR_CODE_OFFSET;
+use rift_mixer::MixerError;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::transaction::TransactionError;
+
+/// Every `MixerError`, in code order
+const MIXER_ERRORS: [MixerError; 33] = [
+    MixerError::CommitmentAlreadyUsed,
+    MixerError::NullifierAlreadyUsed,
+    MixerError::InvalidProof,
+    MixerError::PoolFull,
+    MixerError::ArithmeticError,
+    MixerError::InvalidPoolType,
+    MixerError::EncryptedNoteTooLarge,
+    MixerError::DepositorBlocked,
+    MixerError::Unauthorized,
+    MixerError::UnknownAssociationRoot,
+    MixerError::TvlCapExceeded,
+    MixerError::EpochDepositCapExceeded,
+    MixerError::UnknownRoot,
+    MixerError::RootTooYoung,
+    MixerError::InvalidBatchSize,
+    MixerError::TreeNotFull,
+    MixerError::InvalidExtData,
+    MixerError::InvalidPublicAmount,
+    MixerError::InvalidSwap,
+    MixerError::PoolInEmergency,
+    MixerError::NotInEmergency,
+    MixerError::UnknownCommitment,
+    MixerError::IntentExpired,
+    MixerError::PoolRetired,
+    MixerError::PoolNotRetired,
+    MixerError::PoolNotDrained,
+    MixerError::RootsStillRecent,
+    MixerError::InvalidProofEncoding,
+    MixerError::FeeAboveCap,
+    MixerError::RecipientMismatch,
+    MixerError::RelayerMismatch,
+    MixerError::VaultInsufficient,
+    MixerError::EmergencyClaimTooRecent,
+];
+
+/// The `MixerError` with custom error code `code`, if it is one
+pub fn mixer_error(code: u32) -> Option<MixerError> {
+    let index = code.checked_sub(ERROR_CODE_OFFSET)?;
+    MIXER_ERRORS.get(index as usize).copied()
+}
+
+/// The `MixerError` a transaction failed with. Only meaningful when the
+/// failing instruction is the mixer's; other programs reuse the same codes.
+pub fn transaction_mixer_error(error: &TransactionError) -> Option<MixerError> {
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => mixer_error(*code),
+        _ => None,
+    }
+}
+
+/// What `error` means for the person who sent the transaction
+pub fn explain(error: MixerError) -> &'static str {
+    match error {
+        MixerError::CommitmentAlreadyUsed => {
+            "This note was already deposited. Generate a new note and deposit that."
+        }
+        MixerError::NullifierAlreadyUsed => "This note has already been withdrawn.",
+        MixerError::InvalidProof => {
+            "The withdrawal proof does not match the note, root or withdrawal details. \
+             Generate the proof again."
+        }
+        MixerError::PoolFull => {
+            "The pool's current tree is full. Try again once the next tree is opened."
+        }
+        MixerError::ArithmeticError => "The pool's accounting overflowed. Nothing was moved.",
+        MixerError::InvalidPoolType => "That pool does not exist.",
+        MixerError::EncryptedNoteTooLarge => "The encrypted note backup is too large to attach.",
+        MixerError::DepositorBlocked => "Deposits from this address are blocked.",
+        MixerError::Unauthorized => "Only the pool's or the deployment's authority can do this.",
+        MixerError::UnknownAssociationRoot => {
+            "The association set is not one the curator has published recently. \
+             Prove against a newer set."
+        }
+        MixerError::TvlCapExceeded => {
+            "The pool is at its deposit limit. Try a later or smaller deposit."
+        }
+        MixerError::EpochDepositCapExceeded => {
+            "The pool has taken all the deposits it accepts today. Try again tomorrow."
+        }
+        MixerError::UnknownRoot => {
+            "The proof is for a pool state that is too old or unknown. \
+             Generate the proof again against a current root."
+        }
+        MixerError::RootTooYoung => {
+            "The proof's root is too recent to withdraw against yet. Wait, then try again."
+        }
+        MixerError::InvalidBatchSize => "A batch holds no notes or more than the batch limit.",
+        MixerError::TreeNotFull => {
+            "The pool's current tree still has room; no new tree is needed."
+        }
+        MixerError::InvalidExtData => {
+            "The withdrawal details do not match what the proof was made for."
+        }
+        MixerError::InvalidPublicAmount => "The amount does not match the deposit and fee.",
+        MixerError::InvalidSwap => {
+            "A swap must go to another pool and re-deposit exactly the amount spent."
+        }
+        MixerError::PoolInEmergency => {
+            "The pool is in emergency mode. Reclaim the deposit with an emergency withdrawal."
+        }
+        MixerError::NotInEmergency => "Emergency withdrawals only open in emergency mode.",
+        MixerError::UnknownCommitment => "This note was never deposited into the pool.",
+        MixerError::IntentExpired => {
+            "The withdrawal request expired before it landed. Sign a new one."
+        }
+        MixerError::PoolRetired => "The pool is retired and takes no new deposits.",
+        MixerError::PoolNotRetired => "Only a retired pool can be closed.",
+        MixerError::PoolNotDrained => {
+            "The pool still holds deposits that have not been withdrawn."
+        }
+        MixerError::RootsStillRecent => {
+            "The pool's tree changed too recently for it to be closed yet."
+        }
+        MixerError::InvalidProofEncoding => {
+            "The proof is malformed. Generate it again with an up-to-date prover."
+        }
+        MixerError::FeeAboveCap => "The relayer fee is larger than the deposit itself.",
+        MixerError::RecipientMismatch => {
+            "The recipient is not the one the withdrawal was signed for."
+        }
+        MixerError::RelayerMismatch => {
+            "The relayer is not the one the withdrawal was signed for. \
+             Use the relayer you requested it from."
+        }
+        MixerError::VaultInsufficient => {
+            "The pool does not hold enough to pay this out. Contact the pool's operators."
+        }
+        MixerError::EmergencyClaimTooRecent => {
+            "The emergency claim was committed too recently. Wait, then reveal the note again."
+        }
+    }
+}
@@ -0,0 +1,185 @@
+// RIFT SDK - Restoring notes from the chain
+//
+// Each `DepositEvent` that carries an encrypted backup is tried against the
+// wallet's viewing key. A decrypted note is only accepted if it reproduces the
+// event's commitment and pool, so a forged backup cannot plant a note that
+// does not match its deposit.
+//
+// Shielded notes are found the same way: each output in a `TransactEvent` is
+// tried against the viewing key and kept only if it reproduces the output's
+// commitment and names the wallet as its owner.
+
+use rift_mixer::{DepositEvent, PoolType, TransactEvent, POOL_SEED, SHIELDED_POOL_SEED};
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+
+use crate::backup::decrypt_note;
+use crate::error::{Result, SdkError};
+use crate::events::{deposit_events, transact_events, transaction_logs};
+use crate::keys::ViewingKey;
+use crate::note::Note;
+use crate::shielded::{decrypt_output, ShieldedNote};
+
+/// Signatures fetched per `getSignaturesForAddress` page
+const PAGE_SIZE: usize = 1_000;
+
+#[derive(Clone, Debug)]
+pub struct RecoveredNote {
+    pub note: Note,
+    /// Position in tree `tree_index`, which withdrawals prove against
+    pub leaf_index: u64,
+    pub tree_index: u32,
+    pub commitment: [u8; 32],
+    /// Deposit transaction, when the event came from `scan_pool`
+    pub signature: Option<Signature>,
+}
+
+/// A shielded note sent to the wallet
+#[derive(Clone, Debug)]
+pub struct ReceivedNote {
+    pub note: ShieldedNote,
+    /// Position in the shielded pool's tree `tree_index`, which spends
+    /// prove against
+    pub leaf_index: u64,
+    pub tree_index: u32,
+    pub commitment: [u8; 32],
+    /// `transact` transaction, when the event came from `scan_shielded_pool`
+    pub signature: Option<Signature>,
+}
+
+/// Notes in `events` that belong to `viewing_key`
+pub fn recover_notes(viewing_key: &ViewingKey, events: &[DepositEvent]) -> Vec<RecoveredNote> {
+    events
+        .iter()
+        .filter_map(|event| recover_note(viewing_key, event, None))
+        .collect()
+}
+
+fn recover_note(
+    viewing_key: &ViewingKey,
+    event: &DepositEvent,
+    signature: Option<Signature>,
+) -> Option<RecoveredNote> {
+    let note = decrypt_note(event.encrypted_note.as_ref()?, viewing_key).ok()?;
+    if note.commitment() != event.commitment || note.pool_type != event.pool_type {
+        return None;
+    }
+    Some(RecoveredNote {
+        note,
+        leaf_index: event.leaf_index,
+        tree_index: event.tree_index,
+        commitment: event.commitment,
+        signature,
+    })
+}
+
+/// Walk every transaction that touched `pool_type`'s pool, oldest first, and
+/// return the notes that belong to `viewing_key`
+pub fn scan_pool(
+    rpc: &RpcClient,
+    pool_type: &PoolType,
+    viewing_key: &ViewingKey,
+) -> Result<Vec<RecoveredNote>> {
+    let (pool, _) = Pubkey::find_program_address(&[POOL_SEED, &[pool_type.to_u8()]], &rift_mixer::ID);
+
+    let mut notes = Vec::new();
+    for signature in signatures_for(rpc, &pool)? {
+        let logs = match transaction_logs(rpc, &signature)? {
+            Some(logs) => logs,
+            None => continue,
+        };
+        notes.extend(
+            deposit_events(&logs)
+                .iter()
+                .filter_map(|event| recover_note(viewing_key, event, Some(signature))),
+        );
+    }
+    Ok(notes)
+}
+
+/// Shielded notes in `events` sent to `viewing_key`
+pub fn receive_notes(viewing_key: &ViewingKey, events: &[TransactEvent]) -> Vec<ReceivedNote> {
+    events
+        .iter()
+        .flat_map(|event| receive_outputs(viewing_key, event, None))
+        .collect()
+}
+
+fn receive_outputs(
+    viewing_key: &ViewingKey,
+    event: &TransactEvent,
+    signature: Option<Signature>,
+) -> Vec<ReceivedNote> {
+    let outputs = [&event.encrypted_output1, &event.encrypted_output2];
+    outputs
+        .into_iter()
+        .zip(event.output_commitments)
+        .zip(event.first_leaf_index..)
+        .filter_map(|((output, commitment), leaf_index)| {
+            let note = decrypt_output(output, viewing_key).ok()?;
+            if note.commitment() != commitment || note.owner != viewing_key.public_key() {
+                return None;
+            }
+            Some(ReceivedNote {
+                note,
+                leaf_index,
+                tree_index: event.tree_index,
+                commitment,
+                signature,
+            })
+        })
+        .collect()
+}
+
+/// Walk every `transact` on the shielded pool, oldest first, and return the
+/// notes sent to `viewing_key`
+pub fn scan_shielded_pool(rpc: &RpcClient, viewing_key: &ViewingKey) -> Result<Vec<ReceivedNote>> {
+    let (pool, _) = Pubkey::find_program_address(&[SHIELDED_POOL_SEED], &rift_mixer::ID);
+
+    let mut notes = Vec::new();
+    for signature in signatures_for(rpc, &pool)? {
+        let logs = match transaction_logs(rpc, &signature)? {
+            Some(logs) => logs,
+            None => continue,
+        };
+        for event in transact_events(&logs) {
+            notes.extend(receive_outputs(viewing_key, &event, Some(signature)));
+        }
+    }
+    Ok(notes)
+}
+
+/// Successful transactions that touched `address`, oldest first
+fn signatures_for(rpc: &RpcClient, address: &Pubkey) -> Result<Vec<Signature>> {
+    let mut signatures = Vec::new();
+    let mut before = None;
+    loop {
+        let page = rpc
+            .get_signatures_for_address_with_config(
+                address,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: None,
+                    limit: Some(PAGE_SIZE),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                },
+            )
+            .map_err(|e| SdkError::Rpc(e.to_string()))?;
+        let full_page = page.len() == PAGE_SIZE;
+        for status in page.into_iter().filter(|status| status.err.is_none()) {
+            let signature = status
+                .signature
+                .parse::<Signature>()
+                .map_err(|e| SdkError::Rpc(e.to_string()))?;
+            before = Some(signature);
+            signatures.push(signature);
+        }
+        if !full_page {
+            break;
+        }
+    }
+    signatures.reverse();
+    Ok(signatures)
+}
@@ -0,0 +1,123 @@
+// RIFT SDK - Shielded notes
+//
+// A shielded note holds any amount inside the shielded pool for its owner,
+// named by their shielded address: the public half of their viewing key.
+// `transact` publishes each output note encrypted to its owner in
+// `TransactEvent`, in the same envelope as note backups:
+//
+//     version (1) || ephemeral X25519 public key (32) || ChaCha20-Poly1305(note)
+//
+// keyed under its own HKDF info, so an output can never be read as a backup.
+
+use rand::RngCore;
+use rift_mixer::ExtData;
+use solana_sdk::keccak;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::backup::{open, seal};
+use crate::error::{Result, SdkError};
+use crate::keys::ViewingKey;
+
+pub const OUTPUT_VERSION: u8 = 1;
+
+/// Size of an encrypted output; well under `rift_mixer::MAX_ENCRYPTED_NOTE_LEN`
+pub const OUTPUT_LEN: usize = 1 + 32 + ShieldedNote::LEN + 16;
+
+const OUTPUT_KEY_INFO: &[u8] = b"rift/shielded-output/v1";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShieldedNote {
+    /// Lamports
+    pub amount: u64,
+    /// Shielded address of the owner
+    pub owner: [u8; 32],
+    pub blinding: [u8; 32],
+}
+
+impl ShieldedNote {
+    /// Serialized length: amount, owner, blinding
+    pub const LEN: usize = 8 + 32 + 32;
+
+    pub fn new(amount: u64, owner: [u8; 32]) -> Self {
+        let mut blinding = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut blinding);
+        Self {
+            amount,
+            owner,
+            blinding,
+        }
+    }
+
+    /// The leaf `transact` inserts for this note
+    pub fn commitment(&self) -> [u8; 32] {
+        keccak::hashv(&[&self.amount.to_be_bytes(), &self.owner, &self.blinding]).to_bytes()
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[..8].copy_from_slice(&self.amount.to_le_bytes());
+        bytes[8..40].copy_from_slice(&self.owner);
+        bytes[40..].copy_from_slice(&self.blinding);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != Self::LEN {
+            return Err(SdkError::MalformedNote(format!(
+                "expected {} bytes, got {}",
+                Self::LEN,
+                bytes.len()
+            )));
+        }
+        Ok(Self {
+            amount: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            owner: bytes[8..40].try_into().unwrap(),
+            blinding: bytes[40..].try_into().unwrap(),
+        })
+    }
+}
+
+/// Encrypt `note` to its owner's shielded address
+pub fn encrypt_output(note: &ShieldedNote) -> Vec<u8> {
+    seal(OUTPUT_VERSION, OUTPUT_KEY_INFO, &note.to_bytes(), &note.owner)
+}
+
+/// Decrypt an output addressed to `viewing_key`
+pub fn decrypt_output(output: &[u8], viewing_key: &ViewingKey) -> Result<ShieldedNote> {
+    let (&version, rest) = output
+        .split_first()
+        .ok_or_else(|| SdkError::MalformedNote("empty output".to_string()))?;
+    if version != OUTPUT_VERSION {
+        return Err(SdkError::UnsupportedOutputVersion(version));
+    }
+    if rest.len() != OUTPUT_LEN - 1 {
+        return Err(SdkError::MalformedNote(format!(
+            "expected a {} byte output, got {}",
+            OUTPUT_LEN,
+            output.len()
+        )));
+    }
+
+    let plaintext = open(OUTPUT_KEY_INFO, rest, viewing_key).ok_or(SdkError::OutputDecryption)?;
+    ShieldedNote::from_bytes(&plaintext)
+}
+
+/// `ExtData` for a `transact` creating `outputs`, each encrypted to its owner.
+/// A transfer inside the pool has an `ext_amount` of zero; only `fee` leaves
+/// the pool.
+pub fn ext_data(
+    recipient: Pubkey,
+    ext_amount: i64,
+    relayer: Pubkey,
+    fee: u64,
+    outputs: &[ShieldedNote; 2],
+) -> ExtData {
+    ExtData {
+        recipient,
+        ext_amount,
+        relayer,
+        fee,
+        encrypted_output1: encrypt_output(&outputs[0]),
+        encrypted_output2: encrypt_output(&outputs[1]),
+    }
+}
@@ -0,0 +1,119 @@
+// RIFT SDK - Pool statistics
+//
+// `get_pool_stats` hands back a `PoolStats` as return data. Its layout only
+// grows at the end, so a client decodes the fields it knows and ignores any
+// that a newer program appends.
+
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, InstructionData, ToAccountMetas};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rift_mixer::{
+    Pool, PoolStats, PoolType, DEPOSIT_ACTIVITY_SEED, POOL_SEED, POOL_STATS_VERSION,
+    ROOT_HISTORY_SEED,
+};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::Transaction;
+
+use crate::error::{Result, SdkError};
+
+/// Decode `get_pool_stats` return data
+pub fn decode_pool_stats(return_data: &[u8]) -> Result<PoolStats> {
+    let version = *return_data
+        .first()
+        .ok_or_else(|| malformed("return data is empty".to_string()))?;
+    if version < POOL_STATS_VERSION {
+        return Err(SdkError::UnsupportedPoolStatsVersion(version));
+    }
+    // Not `try_from_slice`: fields added after this version are left unread
+    let mut data = return_data;
+    PoolStats::deserialize(&mut data).map_err(|e| malformed(e.to_string()))
+}
+
+/// `get_pool_stats` for a pool whose current tree is `current_tree`
+pub fn get_pool_stats_instruction(pool_type: &PoolType, current_tree: u32) -> Instruction {
+    let pool = pool_address(pool_type);
+    let derive =
+        |seed: &[u8]| Pubkey::find_program_address(&[seed, pool.as_ref()], &rift_mixer::ID).0;
+    let (root_history, _) = Pubkey::find_program_address(
+        &[ROOT_HISTORY_SEED, pool.as_ref(), &current_tree.to_le_bytes()],
+        &rift_mixer::ID,
+    );
+    let accounts = rift_mixer::accounts::GetPoolStats {
+        pool,
+        root_history,
+        deposit_activity: derive(DEPOSIT_ACTIVITY_SEED),
+        pool_vault: derive(b"vault"),
+    };
+    Instruction {
+        program_id: rift_mixer::ID,
+        accounts: accounts.to_account_metas(None),
+        data: rift_mixer::instruction::GetPoolStats {}.data(),
+    }
+}
+
+/// Read a pool's statistics by simulating `get_pool_stats`. Nothing is
+/// signed or sent; `payer` only has to be an existing account.
+pub fn fetch_pool_stats(
+    rpc: &RpcClient,
+    payer: &Pubkey,
+    pool_type: &PoolType,
+) -> Result<PoolStats> {
+    // The instruction names the current tree's root history
+    let pool = fetch_pool(rpc, pool_type)?;
+    let instruction = get_pool_stats_instruction(pool_type, pool.current_tree);
+    let bytes = simulate_view(rpc, payer, instruction, "get_pool_stats")?;
+    decode_pool_stats(&bytes)
+}
+
+/// Simulate a mixer view instruction and return its return data. Nothing is
+/// signed or sent; `payer` only has to be an existing account.
+pub(crate) fn simulate_view(
+    rpc: &RpcClient,
+    payer: &Pubkey,
+    instruction: Instruction,
+    name: &str,
+) -> Result<Vec<u8>> {
+    let transaction = Transaction::new_with_payer(&[instruction], Some(payer));
+    let simulation = rpc
+        .simulate_transaction_with_config(
+            &transaction,
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                commitment: Some(CommitmentConfig::confirmed()),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )
+        .map_err(|e| SdkError::Rpc(e.to_string()))?
+        .value;
+    if let Some(err) = simulation.err {
+        return Err(SdkError::Rpc(format!("{} failed: {}", name, err)));
+    }
+    let return_data = simulation
+        .return_data
+        .filter(|return_data| return_data.program_id == rift_mixer::ID.to_string())
+        .ok_or_else(|| SdkError::MalformedReturnData(format!("{} returned no data", name)))?;
+    STANDARD
+        .decode(&return_data.data.0)
+        .map_err(|e| SdkError::MalformedReturnData(e.to_string()))
+}
+
+pub(crate) fn fetch_pool(rpc: &RpcClient, pool_type: &PoolType) -> Result<Pool> {
+    let data = rpc
+        .get_account_data(&pool_address(pool_type))
+        .map_err(|e| SdkError::Rpc(e.to_string()))?;
+    Pool::try_deserialize(&mut data.as_slice())
+        .map_err(|e| SdkError::Rpc(format!("pool account: {}", e)))
+}
+
+pub(crate) fn pool_address(pool_type: &PoolType) -> Pubkey {
+    Pubkey::find_program_address(&[POOL_SEED, &[pool_type.to_u8()]], &rift_mixer::ID).0
+}
+
+fn malformed(reason: String) -> SdkError {
+    SdkError::MalformedPoolStats(reason)
+}
@@ -0,0 +1,119 @@
+// RIFT SDK - Note status
+//
+// `is_spent` and `find_commitment` answer from the pool's nullifier registry
+// and trees as return data, so a wallet learns whether a note has landed and
+// whether it has been withdrawn by simulating them instead of downloading
+// those accounts.
+
+use anchor_lang::{AnchorDeserialize, InstructionData, ToAccountMetas};
+use rift_mixer::{LeafPosition, PoolType, MERKLE_SEED};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::{Result, SdkError};
+use crate::note::Note;
+use crate::stats::{fetch_pool, pool_address, simulate_view};
+
+/// Where a note stands, as a wallet shows it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoteStatus {
+    /// Its commitment is in none of the pool's trees
+    NotDeposited,
+    /// Deposited at `LeafPosition` and still withdrawable
+    Unspent(LeafPosition),
+    Spent,
+}
+
+pub fn is_spent_instruction(pool_type: &PoolType, nullifier_hash: [u8; 32]) -> Instruction {
+    let pool = pool_address(pool_type);
+    let (nullifier_registry, _) =
+        Pubkey::find_program_address(&[b"nullifiers", pool.as_ref()], &rift_mixer::ID);
+    let accounts = rift_mixer::accounts::IsSpent {
+        pool,
+        nullifier_registry,
+    };
+    Instruction {
+        program_id: rift_mixer::ID,
+        accounts: accounts.to_account_metas(None),
+        data: rift_mixer::instruction::IsSpent { nullifier_hash }.data(),
+    }
+}
+
+pub fn find_commitment_instruction(
+    pool_type: &PoolType,
+    tree_index: u32,
+    commitment: [u8; 32],
+) -> Instruction {
+    let pool = pool_address(pool_type);
+    let (merkle_tree, _) = Pubkey::find_program_address(
+        &[MERKLE_SEED, pool.as_ref(), &tree_index.to_le_bytes()],
+        &rift_mixer::ID,
+    );
+    let accounts = rift_mixer::accounts::FindCommitment { pool, merkle_tree };
+    Instruction {
+        program_id: rift_mixer::ID,
+        accounts: accounts.to_account_metas(None),
+        data: rift_mixer::instruction::FindCommitment {
+            tree_index,
+            commitment,
+        }
+        .data(),
+    }
+}
+
+/// Decode `is_spent` return data
+pub fn decode_is_spent(return_data: &[u8]) -> Result<bool> {
+    bool::try_from_slice(return_data).map_err(|e| malformed("is_spent", e))
+}
+
+/// Decode `find_commitment` return data
+pub fn decode_leaf_position(return_data: &[u8]) -> Result<Option<LeafPosition>> {
+    Option::<LeafPosition>::try_from_slice(return_data).map_err(|e| malformed("find_commitment", e))
+}
+
+/// Whether `nullifier_hash` has been spent, by simulating `is_spent`
+pub fn fetch_is_spent(
+    rpc: &RpcClient,
+    payer: &Pubkey,
+    pool_type: &PoolType,
+    nullifier_hash: [u8; 32],
+) -> Result<bool> {
+    let instruction = is_spent_instruction(pool_type, nullifier_hash);
+    decode_is_spent(&simulate_view(rpc, payer, instruction, "is_spent")?)
+}
+
+/// Where `commitment` was deposited, by simulating `find_commitment` against
+/// each of the pool's trees, newest first
+pub fn fetch_commitment(
+    rpc: &RpcClient,
+    payer: &Pubkey,
+    pool_type: &PoolType,
+    commitment: [u8; 32],
+) -> Result<Option<LeafPosition>> {
+    let pool = fetch_pool(rpc, pool_type)?;
+    for tree_index in (0..=pool.current_tree).rev() {
+        let instruction = find_commitment_instruction(pool_type, tree_index, commitment);
+        let position =
+            decode_leaf_position(&simulate_view(rpc, payer, instruction, "find_commitment")?)?;
+        if position.is_some() {
+            return Ok(position);
+        }
+    }
+    Ok(None)
+}
+
+/// `note`'s status in its pool
+pub fn fetch_note_status(rpc: &RpcClient, payer: &Pubkey, note: &Note) -> Result<NoteStatus> {
+    if fetch_is_spent(rpc, payer, &note.pool_type, note.nullifier_hash())? {
+        return Ok(NoteStatus::Spent);
+    }
+    Ok(match fetch_commitment(rpc, payer, &note.pool_type, note.commitment())? {
+        Some(position) => NoteStatus::Unspent(position),
+        None => NoteStatus::NotDeposited,
+    })
+}
+
+fn malformed(view: &str, error: std::io::Error) -> SdkError {
+    SdkError::MalformedReturnData(format!("{}: {}", view, error))
+}
@@ -0,0 +1,68 @@
+use rift_mixer::{DepositEvent, PoolType};
+use rift_sdk::backup::{decrypt_note, encrypt_note, BACKUP_LEN};
+use rift_sdk::{recover_notes, Note, SdkError, ViewingKey};
+
+fn event(note: &Note, leaf_index: u64, encrypted_note: Option<Vec<u8>>) -> DepositEvent {
+    DepositEvent {
+        commitment: note.commitment(),
+        leaf_index,
+        timestamp: 0,
+        pool_type: note.pool_type.clone(),
+        encrypted_note,
+        tree_index: 0,
+    }
+}
+
+#[test]
+fn backup_round_trips_for_the_owner_only() {
+    let owner = ViewingKey::from_seed(b"owner seed");
+    let note = Note::random(PoolType::Sol10);
+
+    let backup = encrypt_note(&note, &owner.public_key());
+    assert_eq!(backup.len(), BACKUP_LEN);
+    assert!(backup.len() <= rift_mixer::MAX_ENCRYPTED_NOTE_LEN);
+    assert_eq!(decrypt_note(&backup, &owner).unwrap(), note);
+
+    let other = ViewingKey::from_seed(b"other seed");
+    assert!(matches!(
+        decrypt_note(&backup, &other),
+        Err(SdkError::BackupDecryption)
+    ));
+
+    let mut tampered = backup.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert!(matches!(
+        decrypt_note(&tampered, &owner),
+        Err(SdkError::BackupDecryption)
+    ));
+}
+
+#[test]
+fn mnemonic_derivation_is_deterministic() {
+    let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    let a = ViewingKey::from_mnemonic(phrase, "").unwrap();
+    let b = ViewingKey::from_mnemonic(phrase, "").unwrap();
+    let with_passphrase = ViewingKey::from_mnemonic(phrase, "TREZOR").unwrap();
+    assert_eq!(a.public_key(), b.public_key());
+    assert_ne!(a.public_key(), with_passphrase.public_key());
+    assert!(ViewingKey::from_mnemonic("not a mnemonic", "").is_err());
+}
+
+#[test]
+fn recovery_rejects_backups_that_do_not_match_their_deposit() {
+    let owner = ViewingKey::from_seed(b"owner seed");
+    let note = Note::random(PoolType::Sol1);
+    let decoy = Note::random(PoolType::Sol1);
+
+    let events = vec![
+        event(&note, 4, Some(encrypt_note(&note, &owner.public_key()))),
+        // Decrypts fine, but the note inside is not the one deposited here
+        event(&decoy, 5, Some(encrypt_note(&note, &owner.public_key()))),
+        event(&decoy, 6, None),
+    ];
+
+    let recovered = recover_notes(&owner, &events);
+    assert_eq!(recovered.len(), 1);
+    assert_eq!(recovered[0].note, note);
+    assert_eq!(recovered[0].leaf_index, 4);
+}
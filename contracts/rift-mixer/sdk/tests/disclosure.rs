@@ -0,0 +1,236 @@
+use anchor_lang::Event;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rift_mixer::{DepositEvent, EmergencyWithdrawEvent, PoolType, SwapEvent, WithdrawEvent};
+use rift_sdk::{Disclosure, Note, SdkError};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+
+struct Spent {
+    note: Note,
+    deposit: DepositEvent,
+    withdrawal: WithdrawEvent,
+    recipient: Keypair,
+}
+
+fn deposit_event(note: &Note) -> DepositEvent {
+    DepositEvent {
+        commitment: note.commitment(),
+        leaf_index: 7,
+        timestamp: 0,
+        pool_type: note.pool_type.clone(),
+        encrypted_note: None,
+        tree_index: 0,
+    }
+}
+
+fn withdraw_event(note: &Note, recipient: &Keypair) -> WithdrawEvent {
+    WithdrawEvent {
+        nullifier_hash: note.nullifier_hash(),
+        recipient: recipient.pubkey(),
+        relayer: None,
+        fee: 0,
+        timestamp: 0,
+        association_root: None,
+        pool_type: note.pool_type.clone(),
+    }
+}
+
+fn spent() -> Spent {
+    let note = Note::random(PoolType::Sol10);
+    let recipient = Keypair::new();
+    Spent {
+        deposit: deposit_event(&note),
+        withdrawal: withdraw_event(&note, &recipient),
+        note,
+        recipient,
+    }
+}
+
+fn disclose(spent: &Spent) -> Disclosure {
+    Disclosure::new(
+        spent.note.clone(),
+        Signature::new_unique(),
+        &spent.deposit,
+        Signature::new_unique(),
+        &spent.withdrawal,
+        &spent.recipient,
+    )
+    .unwrap()
+}
+
+fn assert_mismatch(result: rift_sdk::Result<()>) {
+    assert!(
+        matches!(result, Err(SdkError::DisclosureMismatch(_))),
+        "expected a mismatch, got {:?}",
+        result
+    );
+}
+
+#[test]
+fn disclosure_verifies_and_survives_json() {
+    let spent = spent();
+    let disclosure = disclose(&spent);
+    disclosure.verify(&spent.deposit, &spent.withdrawal).unwrap();
+
+    let restored = Disclosure::from_json(&disclosure.to_json()).unwrap();
+    assert_eq!(restored, disclosure);
+    restored.verify(&spent.deposit, &spent.withdrawal).unwrap();
+}
+
+#[test]
+fn disclosure_must_match_both_events() {
+    let spent = spent();
+    let disclosure = disclose(&spent);
+
+    let other_leaf = DepositEvent {
+        leaf_index: 8,
+        ..deposit_event(&spent.note)
+    };
+    assert_mismatch(disclosure.verify(&other_leaf, &spent.withdrawal));
+
+    // Leaf indices restart in every tree
+    let other_tree = DepositEvent {
+        tree_index: 1,
+        ..deposit_event(&spent.note)
+    };
+    assert_mismatch(disclosure.verify(&other_tree, &spent.withdrawal));
+
+    let other_commitment = DepositEvent {
+        commitment: [1u8; 32],
+        ..deposit_event(&spent.note)
+    };
+    assert_mismatch(disclosure.verify(&other_commitment, &spent.withdrawal));
+
+    let other_pool = DepositEvent {
+        pool_type: PoolType::Sol1,
+        ..deposit_event(&spent.note)
+    };
+    assert_mismatch(disclosure.verify(&other_pool, &spent.withdrawal));
+
+    let other_recipient = withdraw_event(&spent.note, &Keypair::new());
+    assert_mismatch(disclosure.verify(&spent.deposit, &other_recipient));
+
+    let other_nullifier = WithdrawEvent {
+        nullifier_hash: [2u8; 32],
+        ..withdraw_event(&spent.note, &spent.recipient)
+    };
+    assert_mismatch(disclosure.verify(&spent.deposit, &other_nullifier));
+
+    let other_pool_withdrawal = WithdrawEvent {
+        pool_type: PoolType::Sol1,
+        ..withdraw_event(&spent.note, &spent.recipient)
+    };
+    assert_mismatch(disclosure.verify(&spent.deposit, &other_pool_withdrawal));
+}
+
+/// Logs of a transaction in which `programs` (outermost first) invoke each
+/// other and the innermost one emits `event`
+fn logs_emitting(programs: &[Pubkey], event: &impl Event) -> Vec<String> {
+    let mut logs: Vec<String> = programs
+        .iter()
+        .enumerate()
+        .map(|(depth, program)| format!("Program {} invoke [{}]", program, depth + 1))
+        .collect();
+    logs.push(format!("Program data: {}", STANDARD.encode(event.data())));
+    for program in programs.iter().rev() {
+        logs.push(format!("Program {} success", program));
+    }
+    logs
+}
+
+#[test]
+fn only_events_the_mixer_emitted_count() {
+    let spent = spent();
+    let disclosure = disclose(&spent);
+    let mixer = rift_mixer::ID;
+    let forger = Pubkey::new_unique();
+    let deposit_logs = logs_emitting(&[mixer], &spent.deposit);
+    let withdraw_logs = logs_emitting(&[mixer], &spent.withdrawal);
+    disclosure.verify_logs(&deposit_logs, &withdraw_logs).unwrap();
+
+    // The same bytes logged by another program, at the top level or called
+    // from the mixer, link nothing
+    let forged = logs_emitting(&[forger], &spent.withdrawal);
+    assert_mismatch(disclosure.verify_logs(&deposit_logs, &forged));
+    let forged = logs_emitting(&[mixer, forger], &spent.deposit);
+    assert_mismatch(disclosure.verify_logs(&forged, &withdraw_logs));
+
+    // Nor does one logged after the mixer returned
+    let mut forged = logs_emitting(&[forger], &spent.deposit);
+    forged.splice(0..0, logs_emitting(&[mixer], &spent.withdrawal));
+    assert_mismatch(disclosure.verify_logs(&forged, &withdraw_logs));
+}
+
+#[test]
+fn swapped_and_emergency_spends_are_refused() {
+    let spent = spent();
+    let disclosure = disclose(&spent);
+    let deposit_logs = logs_emitting(&[rift_mixer::ID], &spent.deposit);
+
+    let swap = SwapEvent {
+        nullifier_hash: spent.note.nullifier_hash(),
+        from_pool_type: PoolType::Sol10,
+        to_pool_type: PoolType::Sol1,
+        first_leaf_index: 0,
+        count: 10,
+        tree_index: 0,
+        timestamp: 0,
+    };
+    let swap_logs = logs_emitting(&[rift_mixer::ID], &swap);
+    assert!(matches!(
+        disclosure.verify_logs(&deposit_logs, &swap_logs),
+        Err(SdkError::UndisclosableSpend("swap_denomination"))
+    ));
+
+    let emergency = EmergencyWithdrawEvent {
+        commitment: spent.note.commitment(),
+        nullifier_hash: spent.note.nullifier_hash(),
+        recipient: spent.recipient.pubkey(),
+        tree_index: 0,
+        timestamp: 0,
+    };
+    let emergency_logs = logs_emitting(&[rift_mixer::ID], &emergency);
+    assert!(matches!(
+        disclosure.verify_logs(&deposit_logs, &emergency_logs),
+        Err(SdkError::UndisclosableSpend("emergency_withdraw"))
+    ));
+}
+
+#[test]
+fn only_the_recipient_can_sign() {
+    let spent = spent();
+    let stranger = Keypair::new();
+    let result = Disclosure::new(
+        spent.note.clone(),
+        Signature::new_unique(),
+        &spent.deposit,
+        Signature::new_unique(),
+        &spent.withdrawal,
+        &stranger,
+    );
+    assert!(matches!(result, Err(SdkError::DisclosureMismatch(_))));
+
+    // Editing a signed disclosure breaks the signature
+    let mut edited = disclose(&spent);
+    edited.leaf_index = 8;
+    let deposit = DepositEvent {
+        leaf_index: 8,
+        ..deposit_event(&spent.note)
+    };
+    assert!(matches!(
+        edited.verify(&deposit, &spent.withdrawal),
+        Err(SdkError::InvalidDisclosureSignature)
+    ));
+}
+
+#[test]
+fn json_copies_must_agree_with_the_note() {
+    let spent = spent();
+    let json = disclose(&spent).to_json();
+    let tampered = json.replace(&hex::encode(spent.note.commitment()), &hex::encode([3u8; 32]));
+    assert!(matches!(
+        Disclosure::from_json(&tampered),
+        Err(SdkError::MalformedDisclosure(_))
+    ));
+}
@@ -0,0 +1,31 @@
+use anchor_lang::error::ERROR_CODE_OFFSET;
+use rift_mixer::MixerError;
+use rift_sdk::program_error::mixer_error;
+use rift_sdk::{explain, transaction_mixer_error};
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::transaction::TransactionError;
+
+#[test]
+fn codes_decode_to_their_errors() {
+    for code in ERROR_CODE_OFFSET.. {
+        let Some(error) = mixer_error(code) else {
+            break;
+        };
+        assert_eq!(u32::from(error), code);
+        assert!(!explain(error).is_empty());
+    }
+    let last = MixerError::VaultInsufficient;
+    assert_eq!(mixer_error(u32::from(last)).map(u32::from), Some(u32::from(last)));
+    assert!(mixer_error(ERROR_CODE_OFFSET - 1).is_none());
+}
+
+#[test]
+fn failed_transactions_are_explained() {
+    let failed = TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(MixerError::RelayerMismatch.into()),
+    );
+    let error = transaction_mixer_error(&failed).unwrap();
+    assert_eq!(explain(error), explain(MixerError::RelayerMismatch));
+    assert!(transaction_mixer_error(&TransactionError::AccountInUse).is_none());
+}
@@ -0,0 +1,73 @@
+use rift_mixer::{PoolType, TransactEvent};
+use rift_sdk::backup::encrypt_note;
+use rift_sdk::shielded::{decrypt_output, encrypt_output, ext_data, OUTPUT_LEN};
+use rift_sdk::{receive_notes, Note, SdkError, ShieldedNote, ViewingKey};
+use solana_sdk::pubkey::Pubkey;
+
+fn event(outputs: [&ShieldedNote; 2], encrypted: [Vec<u8>; 2]) -> TransactEvent {
+    let [encrypted_output1, encrypted_output2] = encrypted;
+    TransactEvent {
+        input_nullifiers: [[1u8; 32], [2u8; 32]],
+        output_commitments: [outputs[0].commitment(), outputs[1].commitment()],
+        first_leaf_index: 6,
+        public_amount: 0,
+        timestamp: 0,
+        encrypted_output1,
+        encrypted_output2,
+        tree_index: 2,
+    }
+}
+
+#[test]
+fn output_round_trips_for_its_owner_only() {
+    let owner = ViewingKey::from_seed(b"owner seed");
+    let note = ShieldedNote::new(1_234_567, owner.public_key());
+
+    let output = encrypt_output(&note);
+    assert_eq!(output.len(), OUTPUT_LEN);
+    assert!(output.len() <= rift_mixer::MAX_ENCRYPTED_NOTE_LEN);
+    assert_eq!(decrypt_output(&output, &owner).unwrap(), note);
+
+    let other = ViewingKey::from_seed(b"other seed");
+    assert!(matches!(
+        decrypt_output(&output, &other),
+        Err(SdkError::OutputDecryption)
+    ));
+}
+
+#[test]
+fn ext_data_carries_both_outputs() {
+    let recipient = ViewingKey::from_seed(b"recipient seed");
+    let sender = ViewingKey::from_seed(b"sender seed");
+    let outputs = [
+        ShieldedNote::new(700, recipient.public_key()),
+        ShieldedNote::new(300, sender.public_key()),
+    ];
+
+    let ext = ext_data(Pubkey::default(), 0, Pubkey::new_unique(), 0, &outputs);
+    assert_eq!(decrypt_output(&ext.encrypted_output1, &recipient).unwrap(), outputs[0]);
+    assert_eq!(decrypt_output(&ext.encrypted_output2, &sender).unwrap(), outputs[1]);
+}
+
+#[test]
+fn receiving_keeps_only_matching_outputs_for_the_wallet() {
+    let wallet = ViewingKey::from_seed(b"wallet seed");
+    let other = ViewingKey::from_seed(b"other seed");
+    let payment = ShieldedNote::new(500, wallet.public_key());
+    let change = ShieldedNote::new(100, other.public_key());
+    // A deposit backup for the wallet is not a shielded output
+    let backup = encrypt_note(&Note::random(PoolType::Sol1), &wallet.public_key());
+
+    let events = vec![
+        event([&change, &payment], [encrypt_output(&change), encrypt_output(&payment)]),
+        // Decrypts fine, but the note inside is not the one committed here
+        event([&change, &change], [encrypt_output(&payment), Vec::new()]),
+        event([&change, &change], [backup, Vec::new()]),
+    ];
+
+    let received = receive_notes(&wallet, &events);
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].note, payment);
+    assert_eq!((received[0].tree_index, received[0].leaf_index), (2, 7));
+    assert_eq!(received[0].commitment, payment.commitment());
+}
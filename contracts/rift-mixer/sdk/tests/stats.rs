@@ -0,0 +1,58 @@
+use anchor_lang::AnchorSerialize;
+use rift_mixer::{PoolStats, PoolType, POOL_STATS_VERSION};
+use rift_sdk::{decode_pool_stats, SdkError};
+
+fn stats() -> PoolStats {
+    PoolStats {
+        version: POOL_STATS_VERSION,
+        pool_type: PoolType::Sol10,
+        deposit_amount: 10,
+        total_deposits: 5,
+        total_withdrawals: 2,
+        anonymity_set_size: 3,
+        vault_lamports: 30,
+        deposits_last_24h: 1,
+        deposits_last_7d: 4,
+        merkle_root: [7u8; 32],
+        root_history_depth: 5,
+        min_leaf_age_slots: 0,
+        min_leaf_age_deposits: 0,
+        current_tree: 1,
+    }
+}
+
+#[test]
+fn stats_round_trip() {
+    let bytes = stats().try_to_vec().unwrap();
+    assert_eq!(decode_pool_stats(&bytes).unwrap(), stats());
+}
+
+#[test]
+fn fields_from_a_newer_layout_are_ignored() {
+    let newer = PoolStats {
+        version: POOL_STATS_VERSION + 1,
+        ..stats()
+    };
+    let mut bytes = newer.try_to_vec().unwrap();
+    bytes.extend_from_slice(&[1u8; 16]);
+    assert_eq!(decode_pool_stats(&bytes).unwrap(), newer);
+}
+
+#[test]
+fn older_or_short_data_is_rejected() {
+    let older = PoolStats {
+        version: 0,
+        ..stats()
+    };
+    assert!(matches!(
+        decode_pool_stats(&older.try_to_vec().unwrap()),
+        Err(SdkError::UnsupportedPoolStatsVersion(0))
+    ));
+
+    let bytes = stats().try_to_vec().unwrap();
+    assert!(matches!(
+        decode_pool_stats(&bytes[..bytes.len() - 1]),
+        Err(SdkError::MalformedPoolStats(_))
+    ));
+    assert!(matches!(decode_pool_stats(&[]), Err(SdkError::MalformedPoolStats(_))));
+}
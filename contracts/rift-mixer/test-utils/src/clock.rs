@@ -0,0 +1,68 @@
+// Clock warping for program-test.
+//
+// Lockups, voting periods, timelocks and reward accrual all read
+// `Clock::get()?.unix_timestamp`. These helpers overwrite the bank's clock
+// sysvar so a test can land exactly on either side of such a boundary:
+//
+//     warp_to_just_before(&mut ctx, stake.lockup_ends_at).await;
+//     // unstake -> StillLocked
+//     warp_to(&mut ctx, stake.lockup_ends_at).await;
+//     // unstake -> ok
+//
+// Time only moves forward. Newer banks never report a timestamp older than
+// their parent's, so rewinding would not reliably stick.
+
+use solana_program_test::ProgramTestContext;
+use solana_sdk::clock::{Clock, Slot, UnixTimestamp};
+
+pub const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+pub async fn clock(ctx: &mut ProgramTestContext) -> Clock {
+    ctx.banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .expect("clock sysvar")
+}
+
+/// Current `Clock::unix_timestamp` as programs will see it
+pub async fn now(ctx: &mut ProgramTestContext) -> UnixTimestamp {
+    clock(ctx).await.unix_timestamp
+}
+
+/// Set the clock to exactly `unix_timestamp`
+pub async fn warp_to(ctx: &mut ProgramTestContext, unix_timestamp: UnixTimestamp) {
+    let mut clock = clock(ctx).await;
+    assert!(
+        unix_timestamp >= clock.unix_timestamp,
+        "cannot warp backwards from {} to {}",
+        clock.unix_timestamp,
+        unix_timestamp
+    );
+    clock.unix_timestamp = unix_timestamp;
+    ctx.set_sysvar(&clock);
+}
+
+/// Set the clock to the last second before `deadline`
+pub async fn warp_to_just_before(ctx: &mut ProgramTestContext, deadline: UnixTimestamp) {
+    warp_to(ctx, deadline - 1).await;
+}
+
+pub async fn warp_seconds(ctx: &mut ProgramTestContext, seconds: i64) {
+    let now = now(ctx).await;
+    warp_to(ctx, now + seconds).await;
+}
+
+pub async fn warp_days(ctx: &mut ProgramTestContext, days: i64) {
+    warp_seconds(ctx, days * SECONDS_PER_DAY).await;
+}
+
+/// Advance the bank by `slots`, keeping the clock's timestamp monotonic
+pub async fn warp_slots(ctx: &mut ProgramTestContext, slots: Slot) {
+    let before = clock(ctx).await;
+    ctx.warp_to_slot(before.slot + slots).expect("warp to slot");
+    let mut after = clock(ctx).await;
+    if after.unix_timestamp < before.unix_timestamp {
+        after.unix_timestamp = before.unix_timestamp;
+        ctx.set_sysvar(&after);
+    }
+}
@@ -0,0 +1,7 @@
+// RIFT test utilities
+//
+// Helpers shared by the program-test suites that are not tied to a single
+// program.
+
+pub mod clock;
+pub mod merkle;
@@ -0,0 +1,70 @@
+// Reference sparse Merkle tree.
+//
+// Written independently of `rift_mixer`'s incremental tree so the two can be
+// checked against each other: nodes live in a map keyed by (level, index),
+// missing nodes are the root of an empty subtree, and every insert rehashes
+// the full path to the root. Slow, but obviously correct.
+
+use std::collections::HashMap;
+
+use solana_sdk::keccak;
+
+pub type Node = [u8; 32];
+
+pub struct SparseMerkleTree {
+    height: usize,
+    empty: Vec<Node>,
+    nodes: HashMap<(usize, u64), Node>,
+}
+
+impl SparseMerkleTree {
+    pub fn new(height: usize) -> Self {
+        let mut empty = vec![[0u8; 32]];
+        for level in 0..height {
+            empty.push(parent(&empty[level], &empty[level]));
+        }
+        Self {
+            height,
+            empty,
+            nodes: HashMap::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> u64 {
+        1 << self.height
+    }
+
+    /// Set the leaf at `index` and rehash its path
+    pub fn set(&mut self, index: u64, leaf: Node) {
+        assert!(index < self.capacity(), "leaf {} outside the tree", index);
+        self.nodes.insert((0, index), leaf);
+
+        let mut index = index;
+        for level in 0..self.height {
+            let sibling = self.node(level, index ^ 1);
+            let current = self.node(level, index);
+            let hash = if index & 1 == 0 {
+                parent(&current, &sibling)
+            } else {
+                parent(&sibling, &current)
+            };
+            index /= 2;
+            self.nodes.insert((level + 1, index), hash);
+        }
+    }
+
+    pub fn root(&self) -> Node {
+        self.node(self.height, 0)
+    }
+
+    fn node(&self, level: usize, index: u64) -> Node {
+        self.nodes
+            .get(&(level, index))
+            .copied()
+            .unwrap_or(self.empty[level])
+    }
+}
+
+fn parent(left: &Node, right: &Node) -> Node {
+    keccak::hashv(&[left, right]).to_bytes()
+}